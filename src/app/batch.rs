@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::core::error::{ExportError, GrainError};
+use crate::export::batch::{BatchJob, BatchReport, FileOutcome, FileReport, DEFAULT_NAMING};
+
+/// Inputs of the batch dialog, and the job it is running
+pub struct BatchPanel {
+    pub open: bool,
+    pub input: String,
+    pub output_dir: String,
+    pub naming: String,
+    pub force: bool,
+    pub run: Option<BatchRun>,
+    /// One line per finished file, newest last
+    pub log: Vec<String>,
+}
+
+impl Default for BatchPanel {
+    fn default() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            output_dir: String::new(),
+            naming: DEFAULT_NAMING.to_string(),
+            force: false,
+            run: None,
+            log: Vec::new(),
+        }
+    }
+}
+
+/// A batch job on a background thread, so the window stays responsive while it renders
+pub struct BatchRun {
+    cancel: Arc<AtomicBool>,
+    progress: Receiver<String>,
+    handle: Option<JoinHandle<Result<BatchReport, GrainError>>>,
+    pub files_done: usize,
+    pub file_count: usize,
+}
+
+impl BatchRun {
+    pub fn start(job: BatchJob) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, progress) = channel();
+        let file_count = job.inputs().map_or(0, |inputs| inputs.len());
+
+        let handle = std::thread::spawn({
+            let cancel = cancel.clone();
+            move || job.run(&cancel, |file| {
+                let _ = sender.send(describe(file));
+            })
+        });
+        Self { cancel, progress, handle: Some(handle), files_done: 0, file_count }
+    }
+
+    /// Stop after the files already being rendered
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Move finished files into `log`; returns the outcome once the whole job has ended
+    pub fn poll(&mut self, log: &mut Vec<String>) -> Option<Result<BatchReport, GrainError>> {
+        for line in self.progress.try_iter() {
+            self.files_done += 1;
+            log.push(line);
+        }
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let result = self.handle.take()?.join()
+            .unwrap_or_else(|_| Err(GrainError::Export(ExportError::WriteFailed("Batch worker panicked".to_string()))));
+        Some(result)
+    }
+}
+
+fn describe(file: &FileReport) -> String {
+    match &file.result {
+        Ok(FileOutcome::Written) => format!("Wrote {}", file.output.display()),
+        Ok(FileOutcome::Skipped) => format!("Skipped {} (exists)", file.output.display()),
+        Err(e) => format!("Failed {}: {e}", file.input.display()),
+    }
+}
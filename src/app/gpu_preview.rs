@@ -0,0 +1,68 @@
+use image::{Rgba32FImage, RgbaImage};
+
+use crate::core::error::GrainError;
+use crate::core::film_stock::PostProcessChain;
+use crate::engine::compute_pipeline::grain_shader;
+use crate::engine::gpu_context::GpuContext;
+use crate::engine::grain_renderer::{GrainParams, GrainRenderer};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::shaders::ShaderLibrary;
+
+/// Renders the preview on the GPU, keeping one renderer alive while the preview size is unchanged
+pub struct GpuPreview {
+    gpu: GpuContext,
+    // Shaders preview renderers are built from; hot reload swaps in edited sources
+    library: ShaderLibrary,
+    renderer: Option<GrainRenderer>,
+}
+
+impl GpuPreview {
+    pub fn new(gpu: GpuContext) -> Self {
+        Self {
+            gpu,
+            library: ShaderLibrary::builtin(),
+            renderer: None,
+        }
+    }
+
+    /// Device and queue the preview renders on; exports on the GPU share them
+    pub fn context(&self) -> &GpuContext {
+        &self.gpu
+    }
+
+    /// Render from `library` from now on. On error the current shaders stay in use.
+    pub fn set_library(&mut self, library: ShaderLibrary) -> Result<(), GrainError> {
+        // Exports may use any format, so the edit must compile for all of them
+        for format in OutputFormat::ALL {
+            grain_shader(&library, format)?;
+        }
+        if let Some(renderer) = &mut self.renderer {
+            renderer.reload_shaders(&self.gpu.device, &library)?;
+        }
+        self.library = library;
+        Ok(())
+    }
+
+    /// Render a preview with the current shaders
+    pub fn render(
+        &mut self,
+        params: &GrainParams,
+        post_process: &PostProcessChain,
+        plate: Option<&Rgba32FImage>,
+    ) -> Result<RgbaImage, GrainError> {
+        let (width, height) = (params.width as u32, params.height as u32);
+        let device = &self.gpu.device;
+
+        let renderer = match &mut self.renderer {
+            Some(renderer) if renderer.width() == width && renderer.height() == height => renderer,
+            slot => slot.insert(GrainRenderer::with_shaders(device, width, height, OutputFormat::Rgba8, &self.library)?),
+        };
+        match plate {
+            Some(plate) => renderer.set_input_image(device, &self.gpu.queue, plate)?,
+            None => renderer.clear_input(device),
+        }
+        renderer.render(device, &self.gpu.queue, params);
+        renderer.post_process(device, &self.gpu.queue, post_process)?;
+        renderer.read_image(device, &self.gpu.queue)
+    }
+}
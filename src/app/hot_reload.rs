@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use crate::app::gpu_preview::GpuPreview;
+use crate::core::error::GrainError;
+use crate::engine::shaders::ShaderWatcher;
+
+/// Directory to load shaders from; setting it turns hot reload on
+pub const SHADER_DIR_VAR: &str = "GRAINFORGE_SHADER_DIR";
+
+/// Development mode that previews grain with WGSL read from disk and recompiles it on save.
+///
+/// The CPU renderer only mirrors the built-in shaders, so while this is on the preview
+/// renders on the GPU. A source that fails to compile leaves the last good pipeline in use.
+pub struct ShaderHotReload {
+    watcher: ShaderWatcher,
+}
+
+impl ShaderHotReload {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { watcher: ShaderWatcher::new(dir) }
+    }
+
+    /// Hot reload from the directory in [`SHADER_DIR_VAR`], if it is set
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var_os(SHADER_DIR_VAR)?;
+        Some(Self::new(dir))
+    }
+
+    /// Pick up saved shader files: `Some(Ok)` once edits are compiled and in use by
+    /// `preview`, `Some(Err)` when they fail and the previous shaders stay active
+    pub fn poll(&mut self, preview: &mut GpuPreview) -> Option<Result<(), GrainError>> {
+        let library = match self.watcher.poll()? {
+            Ok(library) => library,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(preview.set_library(library))
+    }
+
+    pub fn watcher(&self) -> &ShaderWatcher {
+        &self.watcher
+    }
+}
@@ -1,3 +1,6 @@
 pub mod state;
 pub mod settings;
 pub mod theme;
+pub mod gpu_preview;
+pub mod hot_reload;
+pub mod batch;
@@ -1,14 +1,60 @@
+use image::Rgba32FImage;
+use crate::app::batch::BatchPanel;
+use crate::app::gpu_preview::GpuPreview;
+use crate::app::hot_reload::ShaderHotReload;
 use crate::core::parameter::Parameter;
 use crate::core::history::HistoryManager;
+use crate::core::film_stock::FilmStock;
+use crate::engine::backend::RenderBackend;
+use crate::engine::grain_renderer::{BlendMode, DEFAULT_BOOLEAN_SAMPLES};
+use crate::engine::output_format::OutputFormat;
 
 pub struct AppState {
     pub parameters: Vec<Parameter>,
     pub history: HistoryManager,
     pub active_mode: EditMode,
+    // Backend the preview should render on; chosen in the toolbar
+    pub backend: RenderBackend,
+    // GPU preview renderer, when a device is available
+    pub gpu_preview: Option<GpuPreview>,
     // Grain preview parameters (bound to sliders)
     pub grain_amount: f32,
     pub grain_size: f32,
-    pub preview_seed: f32,
+    pub film_stock: FilmStock,
+    // Seed field contents while it is being edited
+    pub seed_text: String,
+    // Photograph the grain is composited onto (None renders a standalone texture)
+    pub plate: Option<Rgba32FImage>,
+    pub plate_path: String,
+    pub blend_mode: BlendMode,
+    // Monte Carlo samples per pixel for Boolean-model synthesis
+    pub grain_samples: u32,
+    // Wrap the grain at the output edges so it can be used as a tiled overlay
+    pub tileable: bool,
+    // Precision of exported renders; float formats export as 16-bit PNG/TIFF or float EXR
+    pub output_format: OutputFormat,
+    // Render exports tile by tile and stream them to disk, for plates too large to hold in memory
+    pub tiled_export: bool,
+    pub export_path: String,
+    pub preview: PreviewCache,
+    // Shown in the status bar instead of "Ready"
+    pub status_message: Option<String>,
+    // Look-dev mode: shaders loaded from disk and recompiled on save (off unless requested)
+    pub shader_reload: Option<ShaderHotReload>,
+    // Folder-at-a-time export with the current stock
+    pub batch: BatchPanel,
+}
+
+/// Last rendered preview, re-rendered only when its inputs change
+#[derive(Default)]
+pub struct PreviewCache {
+    pub texture: Option<egui::TextureHandle>,
+    // Plate downscaled to preview size
+    pub plate: Option<Rgba32FImage>,
+    pub rendered_params: Option<Vec<u8>>,
+    pub plate_changed: bool,
+    // Backend that rendered the texture, which differs from the requested one after a fallback
+    pub backend: Option<RenderBackend>,
 }
 
 #[derive(Default, PartialEq)]
@@ -24,9 +70,24 @@ impl Default for AppState {
             parameters: Vec::new(),
             history: HistoryManager::default(),
             active_mode: EditMode::Simple,
+            backend: RenderBackend::default(),
+            gpu_preview: None,
             grain_amount: 0.5,
             grain_size: 1.0,
-            preview_seed: 0.0,
+            film_stock: FilmStock::default(),
+            seed_text: String::new(),
+            plate: None,
+            plate_path: String::new(),
+            blend_mode: BlendMode::default(),
+            grain_samples: DEFAULT_BOOLEAN_SAMPLES,
+            tileable: false,
+            output_format: OutputFormat::default(),
+            tiled_export: false,
+            export_path: "grain.png".to_string(),
+            preview: PreviewCache::default(),
+            status_message: None,
+            shader_reload: None,
+            batch: BatchPanel::default(),
         };
         state.init_default_parameters();
         state
@@ -34,6 +95,20 @@ impl Default for AppState {
 }
 
 impl AppState {
+    /// Seed of the current stock's grain pattern; it is saved with the stock
+    pub fn seed(&self) -> u64 {
+        self.film_stock.grain.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.film_stock.grain.seed = seed;
+    }
+
+    /// Switch to a fresh random grain pattern
+    pub fn reseed(&mut self) {
+        self.set_seed(rand::random());
+    }
+
     fn init_default_parameters(&mut self) {
         // Section 4 Example Mappings
         self.parameters.push(Parameter::new_float(
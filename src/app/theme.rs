@@ -1,4 +1,4 @@
-use egui::{Color32, Context, FontDefinitions, FontFamily, Style, Visuals};
+use egui::{Color32, Context, FontDefinitions, Style, Visuals};
 
 pub const BG_PRIMARY: Color32 = Color32::from_rgb(26, 26, 30); // #1A1A1E
 pub const BG_SECONDARY: Color32 = Color32::from_rgb(36, 36, 40); // #242428
@@ -62,7 +62,7 @@ pub fn apply_theme(ctx: &Context) {
 
     // Font setup (using defaults for now, but structured for easy replacement)
     // In a real scenario with asset loading, we would load Inter/JetBrains Mono here.
-    let mut fonts = FontDefinitions::default();
+    let fonts = FontDefinitions::default();
     
     // Example: prioritizing a font if we had it loaded
     // fonts.families.get_mut(&FontFamily::Proportional).unwrap().insert(0, "Inter".to_owned());
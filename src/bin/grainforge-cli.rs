@@ -0,0 +1,330 @@
+//! Headless renderer for pipelines and render farm nodes: loads a film stock saved by the
+//! app and writes grain textures, sequences or grained plates without opening a window.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use grainforge::core::film_stock::FilmStock;
+use grainforge::core::import;
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::grain_renderer::{BlendMode, GrainParams, DEFAULT_BOOLEAN_SAMPLES};
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer, DEFAULT_TILE_OVERLAP, DEFAULT_TILE_SIZE};
+use grainforge::export::batch::{BatchJob, FileOutcome, FileReport, DEFAULT_NAMING};
+use grainforge::export::sequence_export::{export_sequence, export_still, SequenceOutcome, SequenceSettings};
+use grainforge::export::watch_folder::{WatchFolder, DEFAULT_POLL_INTERVAL};
+
+#[derive(Parser)]
+#[command(name = "grainforge-cli", version, about = "Render film grain without a display")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a standalone grain texture
+    Single {
+        #[command(flatten)]
+        render: RenderArgs,
+        #[arg(long, default_value_t = 1024)]
+        width: u32,
+        #[arg(long, default_value_t = 1024)]
+        height: u32,
+        /// Output image (.png, .tif or .exr)
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Render numbered frames of animated grain
+    Sequence {
+        #[command(flatten)]
+        render: RenderArgs,
+        #[arg(long, default_value_t = 1024)]
+        width: u32,
+        #[arg(long, default_value_t = 1024)]
+        height: u32,
+        #[arg(long, default_value_t = 24.0)]
+        fps: f32,
+        /// Length in seconds
+        #[arg(long, default_value_t = 1.0)]
+        duration: f32,
+        /// 0 re-rolls the grain every frame; towards 1 grains re-roll ever more rarely
+        #[arg(long, default_value_t = 0.0)]
+        coherence: f32,
+        /// Number of the first file
+        #[arg(long, default_value_t = 1)]
+        start_frame: u32,
+        /// Write the stock's dust and scratches to `*_damage` files instead of over the grain
+        #[arg(long)]
+        separate_damage: bool,
+        /// File name pattern; the last run of `#` becomes the frame number
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Composite grain onto a photograph at its full resolution
+    Apply {
+        #[command(flatten)]
+        render: RenderArgs,
+        /// Plate to grain (.png or .tif)
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = Blend::Overlay)]
+        blend: Blend,
+        /// Output image (.png, .tif or .exr)
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Composite grain onto every image matching a pattern, several at once on the CPU
+    Batch {
+        #[command(flatten)]
+        batch: BatchArgs,
+    },
+    /// Keep graining new images as they arrive, until interrupted
+    Watch {
+        #[command(flatten)]
+        batch: BatchArgs,
+        /// Seconds between looks at the input; a file is picked up once it has stopped changing
+        #[arg(long, default_value_t = DEFAULT_POLL_INTERVAL.as_secs_f32())]
+        interval: f32,
+        /// Processed-files log, so a restart does not redo work; defaults to one in the output directory
+        #[arg(long)]
+        log: Option<PathBuf>,
+    },
+}
+
+/// Options of the batch and watch subcommands
+#[derive(Args)]
+struct BatchArgs {
+    /// Glob of plates, e.g. 'stills/*.tif'; quote it so the shell leaves it alone
+    #[arg(short, long)]
+    input: String,
+    /// Film stock JSON saved by GrainForge; the default stock when omitted
+    #[arg(long)]
+    stock: Option<PathBuf>,
+    /// Replace the stock's seed
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, value_enum, default_value_t = Format::Rgba8)]
+    format: Format,
+    #[arg(long, value_enum, default_value_t = Blend::Overlay)]
+    blend: Blend,
+    /// Boolean-model samples per pixel
+    #[arg(long, default_value_t = DEFAULT_BOOLEAN_SAMPLES)]
+    samples: u32,
+    /// Directory for the grained files
+    #[arg(short, long)]
+    output_dir: PathBuf,
+    /// Output file name; {name}, {ext} and {stock} are replaced
+    #[arg(long, default_value = DEFAULT_NAMING)]
+    naming: String,
+    /// Re-render files whose output already exists
+    #[arg(long)]
+    force: bool,
+    /// Files processed at once; 0 uses every core
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+/// Options of the single, sequence and apply subcommands
+#[derive(Args)]
+struct RenderArgs {
+    /// Film stock JSON saved by GrainForge; the default stock when omitted
+    #[arg(long)]
+    stock: Option<PathBuf>,
+    /// Replace the stock's seed
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, value_enum, default_value_t = Format::Rgba8)]
+    format: Format,
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    backend: Backend,
+    /// Edge length of each render tile; smaller tiles use less memory
+    #[arg(long, default_value_t = DEFAULT_TILE_SIZE)]
+    tile_size: u32,
+    /// Boolean-model samples per pixel
+    #[arg(long, default_value_t = DEFAULT_BOOLEAN_SAMPLES)]
+    samples: u32,
+    /// Wrap the grain at the output edges so the texture tiles seamlessly
+    #[arg(long)]
+    tileable: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Rgba8,
+    Rgba16f,
+    Rgba32f,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Rgba8 => OutputFormat::Rgba8,
+            Format::Rgba16f => OutputFormat::Rgba16Float,
+            Format::Rgba32f => OutputFormat::Rgba32Float,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Blend {
+    Overlay,
+    SoftLight,
+    Additive,
+}
+
+impl From<Blend> for BlendMode {
+    fn from(blend: Blend) -> Self {
+        match blend {
+            Blend::Overlay => BlendMode::Overlay,
+            Blend::SoftLight => BlendMode::SoftLight,
+            Blend::Additive => BlendMode::Additive,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Backend {
+    /// The GPU when an adapter is available, otherwise the CPU
+    Auto,
+    Gpu,
+    Cpu,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    match Cli::parse().command {
+        Command::Single { render, width, height, output } => {
+            let stock = render.load_stock()?;
+            let mut renderer = render.renderer(&stock, width, height)?;
+            export_still(&mut renderer, &render.params(&stock, width, height), None, &stock.damage, &output)
+                .with_context(|| format!("Rendering {}", output.display()))?;
+            println!("Wrote {}", output.display());
+        }
+        Command::Sequence { render, width, height, fps, duration, coherence, start_frame, separate_damage, output } => {
+            let stock = render.load_stock()?;
+            let mut renderer = render.renderer(&stock, width, height)?;
+            let settings = SequenceSettings {
+                fps,
+                duration,
+                coherence,
+                start_frame,
+                damage: stock.damage.clone(),
+                separate_damage,
+            };
+            let params = render.params(&stock, width, height);
+            let outcome = export_sequence(&mut renderer, &params, None, &settings, &output, &AtomicBool::new(false), |p| {
+                println!("Wrote {} ({}/{})", p.path.display(), p.frames_done, p.frame_count);
+            })
+            .with_context(|| format!("Rendering {}", output.display()))?;
+            if let SequenceOutcome::Cancelled { frames } = outcome {
+                anyhow::bail!("Cancelled after {frames} frames");
+            }
+        }
+        Command::Apply { render, input, blend, output } => {
+            let stock = render.load_stock()?;
+            let plate = import::load_plate(&input).with_context(|| format!("Loading plate {}", input.display()))?;
+            let (width, height) = plate.dimensions();
+            let mut renderer = render.renderer(&stock, width, height)?;
+            let params = render.params(&stock, width, height).with_blend_mode(blend.into());
+            export_still(&mut renderer, &params, Some(&plate), &stock.damage, &output)
+                .with_context(|| format!("Rendering {}", output.display()))?;
+            println!("Wrote {}", output.display());
+        }
+        Command::Batch { batch } => {
+            let report = batch.job()?.run(&AtomicBool::new(false), print_file)?;
+            println!("{}", report.summary());
+            if report.failures().next().is_some() {
+                anyhow::bail!("Some files failed");
+            }
+        }
+        Command::Watch { batch, interval, log } => {
+            let job = batch.job()?;
+            let mut watch = match log {
+                Some(log) => WatchFolder::with_log(job, log)?,
+                None => WatchFolder::new(job)?,
+            };
+            println!("Watching {} (log: {})", watch.job().input, watch.log_path().display());
+            // Runs until the process is stopped; outputs are renamed into place when complete,
+            // so stopping mid-render never leaves a truncated output
+            watch.run(Duration::from_secs_f32(interval), &AtomicBool::new(false), print_file, |e| eprintln!("{e}"));
+        }
+    }
+    Ok(())
+}
+
+fn print_file(file: &FileReport) {
+    match &file.result {
+        Ok(FileOutcome::Written) => println!("Wrote {}", file.output.display()),
+        Ok(FileOutcome::Skipped) => println!("Skipped {} (exists)", file.output.display()),
+        Err(e) => eprintln!("Failed {}: {e}", file.input.display()),
+    }
+}
+
+fn load_stock(path: Option<&Path>) -> anyhow::Result<FilmStock> {
+    match path {
+        Some(path) => import::load_film_stock(path).with_context(|| format!("Loading stock {}", path.display())),
+        None => Ok(FilmStock::default()),
+    }
+}
+
+impl BatchArgs {
+    fn job(&self) -> anyhow::Result<BatchJob> {
+        let mut stock = load_stock(self.stock.as_deref())?;
+        if let Some(seed) = self.seed {
+            stock.grain.seed = seed;
+        }
+        Ok(BatchJob {
+            naming: self.naming.clone(),
+            format: self.format.into(),
+            blend_mode: self.blend.into(),
+            samples: self.samples,
+            force: self.force,
+            threads: self.threads,
+            ..BatchJob::new(self.input.clone(), stock, self.output_dir.clone())
+        })
+    }
+}
+
+impl RenderArgs {
+    fn load_stock(&self) -> anyhow::Result<FilmStock> {
+        load_stock(self.stock.as_deref())
+    }
+
+    fn params(&self, stock: &FilmStock, width: u32, height: u32) -> GrainParams {
+        let params = GrainParams::from_film_stock(stock, width, height)
+            .with_samples(self.samples)
+            .with_tileable(self.tileable);
+        match self.seed {
+            Some(seed) => params.with_seed(seed),
+            None => params,
+        }
+    }
+
+    /// Tiled renderer with the stock's post-processing, on the requested backend
+    fn renderer(&self, stock: &FilmStock, width: u32, height: u32) -> anyhow::Result<TiledRenderer> {
+        let overlap = DEFAULT_TILE_OVERLAP.max(stock.post_process.reach());
+        let format = self.format.into();
+        let gpu = match self.backend {
+            Backend::Auto => gpu_adapter_available(),
+            Backend::Gpu => true,
+            Backend::Cpu => false,
+        };
+
+        let renderer = if gpu {
+            let context = GpuContext::new_headless()?;
+            let max_tile = context.device.limits().max_texture_dimension_2d.saturating_sub(2 * overlap);
+            let plan = TilePlan::new(width, height, self.tile_size.min(max_tile), overlap)?;
+            TiledRenderer::gpu(&context, plan, format)?
+        } else {
+            TiledRenderer::cpu(TilePlan::new(width, height, self.tile_size, overlap)?, format)
+        };
+        Ok(renderer.with_post_process(stock.post_process.clone())?)
+    }
+}
@@ -5,14 +5,26 @@ pub enum GrainError {
     #[error("GPU initialization failed: {0}")]
     GpuInit(String), // wgpu::Error isn't easily clonable/displayable sometimes, treating as string for now implies generic
     
+    #[error("GPU readback failed: {0}")]
+    Readback(String),
+
     #[error("Invalid parameter '{name}': {reason}")]
     InvalidParameter { name: String, reason: String },
     
+    #[error("Import failed: {0}")]
+    Import(String),
+
     #[error("Export failed: {0}")]
     Export(#[from] ExportError),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("Shader error: {0}")]
+    Shader(#[from] ShaderError),
+
+    #[error("Node graph error: {0}")]
+    Graph(#[from] GraphError),
 }
 
 #[derive(Debug, Error)]
@@ -29,3 +41,49 @@ pub enum ExportError {
     #[error("Write failed: {0}")]
     WriteFailed(String),
 }
+
+/// Shader build failures, located in the original `.wgsl` files rather than the
+/// preprocessed output
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("{file}:{line}: {message}")]
+    Preprocess { file: String, line: u32, message: String },
+
+    #[error("{location}: {message}")]
+    Compile { location: String, message: String },
+}
+
+/// Node graphs that cannot be built or evaluated. Nodes are named by type and the start of
+/// their id, e.g. `math.add [3f2a9c1e]`.
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("Unknown node type '{0}'")]
+    UnknownNodeType(String),
+
+    #[error("No node with id {0}")]
+    UnknownNode(uuid::Uuid),
+
+    #[error("{node} has no {direction} named '{socket}'")]
+    UnknownSocket { node: String, direction: &'static str, socket: String },
+
+    #[error("Cannot connect {from} ({from_type}) to {to} ({to_type})")]
+    TypeMismatch { from: String, from_type: &'static str, to: String, to_type: &'static str },
+
+    #[error("Input {input} is connected more than once")]
+    DuplicateInput { input: String },
+
+    #[error("Input {input} must be connected")]
+    UnconnectedInput { input: String },
+
+    #[error("Graph has no grain output")]
+    MissingOutput,
+
+    #[error("Connections form a cycle: {path}")]
+    Cycle { path: String },
+
+    #[error("{node} failed: {reason}")]
+    Evaluation { node: String, reason: String },
+
+    #[error("Parameter '{parameter}' of {node}: {reason}")]
+    Parameter { node: String, parameter: String, reason: String },
+}
@@ -1,5 +1,5 @@
 use std::path::Path;
-use image::{ImageBuffer, Rgba};
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
 use crate::core::error::{GrainError, ExportError};
 use crate::utils::validation::validate_export_path;
 
@@ -34,6 +34,67 @@ pub fn export_png(
     Ok(())
 }
 
+/// Export an RGBA image, picking the format from the file extension
+pub fn export_image(image: &RgbaImage, output_path: &Path) -> Result<(), GrainError> {
+    let validated_path = validate_export_path(output_path)?;
+
+    let format = validated_path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(ExportFormat::from_extension)
+        .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+
+    let image_format = match format {
+        ExportFormat::Png => image::ImageFormat::Png,
+        ExportFormat::Tiff => image::ImageFormat::Tiff,
+        #[cfg(feature = "exr")]
+        ExportFormat::Exr => return write_exr(&DynamicImage::ImageRgba8(image.clone()).into_rgba32f(), &validated_path),
+    };
+
+    image.save_with_format(&validated_path, image_format)
+        .map_err(|e| GrainError::Export(ExportError::WriteFailed(e.to_string())))?;
+
+    Ok(())
+}
+
+/// Export a float render at full precision, picking the format from the file extension.
+///
+/// PNG and TIFF are written with 16 bits per channel (sRGB encoded, as rendered);
+/// EXR is written as 32-bit float in linear light.
+pub fn export_image_f32(image: &Rgba32FImage, output_path: &Path) -> Result<(), GrainError> {
+    let validated_path = validate_export_path(output_path)?;
+
+    let format = validated_path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(ExportFormat::from_extension)
+        .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+
+    let image_format = match format {
+        ExportFormat::Png => image::ImageFormat::Png,
+        ExportFormat::Tiff => image::ImageFormat::Tiff,
+        #[cfg(feature = "exr")]
+        ExportFormat::Exr => return write_exr(image, &validated_path),
+    };
+
+    DynamicImage::ImageRgba32F(image.clone())
+        .into_rgba16()
+        .save_with_format(&validated_path, image_format)
+        .map_err(|e| GrainError::Export(ExportError::WriteFailed(e.to_string())))?;
+
+    Ok(())
+}
+
+/// Scene-linear RGBA EXR; alpha stays linear coverage
+#[cfg(feature = "exr")]
+fn write_exr(image: &Rgba32FImage, path: &Path) -> Result<(), GrainError> {
+    use crate::utils::color::srgb_to_linear;
+
+    exr::prelude::write_rgba_file(path, image.width() as usize, image.height() as usize, |x, y| {
+        let [r, g, b, a] = image.get_pixel(x as u32, y as u32).0;
+        (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    })
+    .map_err(|e| GrainError::Export(ExportError::WriteFailed(e.to_string())))
+}
+
 /// Export formats supported
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExportFormat {
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use crate::utils::math::mix;
 use crate::utils::validation::{BoundedFloat, validate_name};
 
 /// Represents a complete film stock definition
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct FilmStock {
     pub meta: FilmMeta,
@@ -10,18 +11,10 @@ pub struct FilmStock {
     pub response: ResponseCurve,
     pub color: ColorParameters,
     pub texture: TextureParameters,
-}
-
-impl Default for FilmStock {
-    fn default() -> Self {
-        Self {
-            meta: FilmMeta::default(),
-            grain: GrainParameters::default(),
-            response: ResponseCurve::default(),
-            color: ColorParameters::default(),
-            texture: TextureParameters::default(),
-        }
-    }
+    #[serde(default)]
+    pub post_process: PostProcessChain,
+    #[serde(default)]
+    pub damage: DamageParameters,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -52,6 +45,13 @@ pub struct GrainParameters {
     
     #[serde(default = "default_sharpness")]
     pub sharpness: BoundedFloat, // 0.0 - 1.0
+
+    #[serde(default)]
+    pub synthesis: GrainSynthesis,
+
+    /// Grain pattern; a seed reproduces the same grain on every run and renderer
+    #[serde(default)]
+    pub seed: u64,
 }
 
 impl Default for GrainParameters {
@@ -62,6 +62,8 @@ impl Default for GrainParameters {
             size_variation: default_variation(),
             crystal_type: CrystalType::default(),
             sharpness: default_sharpness(),
+            synthesis: GrainSynthesis::default(),
+            seed: 0,
         }
     }
 }
@@ -83,6 +85,32 @@ pub enum CrystalType {
     Custom { sides: u32 },
 }
 
+/// How individual grains are synthesized
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GrainSynthesis {
+    /// Noise fields shaped by the crystal type
+    #[default]
+    Noise,
+    /// Individually rasterized crystals placed by a seeded point process
+    Particle,
+    /// Inhomogeneous Boolean model of random discs (Newson et al.), rendered by
+    /// Monte Carlo filtering; grain density follows the image intensity
+    Boolean,
+}
+
+impl GrainSynthesis {
+    pub const ALL: [GrainSynthesis; 3] = [Self::Noise, Self::Particle, Self::Boolean];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Noise => "Noise",
+            Self::Particle => "Particle",
+            Self::Boolean => "Boolean Model",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ResponseCurve {
     #[serde(default = "default_response_param")]
@@ -202,3 +230,223 @@ pub enum ClusteringType {
     Voronoi,
     Hybrid,
 }
+
+/// Procedural film damage for period looks; everything is off by default
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DamageParameters {
+    #[serde(default = "default_dust")]
+    pub dust: BoundedFloat, // 0.0 - 1.0, specks per frame relative to the frame area
+    #[serde(default = "default_dust_size")]
+    pub dust_size: BoundedFloat, // 0.5 - 16.0 pixels
+    #[serde(default = "default_hairs")]
+    pub hairs: BoundedFloat, // 0.0 - 1.0
+    #[serde(default = "default_scratches")]
+    pub scratches: BoundedFloat, // 0.0 - 1.0
+    #[serde(default)]
+    pub polarity: DamagePolarity,
+    /// Frame-to-frame wander of the film in the gate
+    #[serde(default = "default_gate_weave")]
+    pub gate_weave: BoundedFloat, // 0.0 - 4.0 pixels
+    /// Frame-to-frame exposure variation
+    #[serde(default = "default_flicker")]
+    pub flicker: BoundedFloat, // 0.0 - 1.0 stops
+}
+
+fn default_dust() -> BoundedFloat { BoundedFloat::new(0.0, 0.0, 1.0) }
+fn default_dust_size() -> BoundedFloat { BoundedFloat::new(3.0, 0.5, 16.0) }
+fn default_hairs() -> BoundedFloat { BoundedFloat::new(0.0, 0.0, 1.0) }
+fn default_scratches() -> BoundedFloat { BoundedFloat::new(0.0, 0.0, 1.0) }
+fn default_gate_weave() -> BoundedFloat { BoundedFloat::new(0.0, 0.0, 4.0) }
+fn default_flicker() -> BoundedFloat { BoundedFloat::new(0.0, 0.0, 1.0) }
+
+impl Default for DamageParameters {
+    fn default() -> Self {
+        Self {
+            dust: default_dust(),
+            dust_size: default_dust_size(),
+            hairs: default_hairs(),
+            scratches: default_scratches(),
+            polarity: DamagePolarity::default(),
+            gate_weave: default_gate_weave(),
+            flicker: default_flicker(),
+        }
+    }
+}
+
+impl DamageParameters {
+    pub fn is_enabled(&self) -> bool {
+        [&self.dust, &self.hairs, &self.scratches, &self.gate_weave, &self.flicker]
+            .iter()
+            .any(|amount| amount.get() > 0.0)
+    }
+
+    /// The same damage for an image `factor` times the size, e.g. a downscaled preview
+    pub fn scaled(&self, factor: f32) -> Self {
+        let mut damage = self.clone();
+        damage.dust_size.set(self.dust_size.get() * factor);
+        damage.gate_weave.set(self.gate_weave.get() * factor);
+        damage
+    }
+}
+
+/// Which way damage prints. Dirt on a negative blocks light and prints white;
+/// dirt on a positive or reversal stock prints black.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DamagePolarity {
+    #[default]
+    Negative,
+    Positive,
+}
+
+impl DamagePolarity {
+    pub const ALL: [DamagePolarity; 2] = [Self::Negative, Self::Positive];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Negative => "White (negative)",
+            Self::Positive => "Black (positive)",
+        }
+    }
+}
+
+/// Effects run over the rendered grain, in order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(transparent)]
+pub struct PostProcessChain {
+    pub effects: Vec<PostEffect>,
+}
+
+impl PostProcessChain {
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// How far, in pixels, an output pixel can be affected by its neighbours; tiled
+    /// renders need at least this much overlap
+    pub fn reach(&self) -> u32 {
+        self.effects.iter().map(PostEffect::reach).sum()
+    }
+
+    /// The same chain for an image `factor` times the size, e.g. a downscaled preview
+    pub fn scaled(&self, factor: f32) -> Self {
+        let mut chain = self.clone();
+        for effect in &mut chain.effects {
+            match effect {
+                PostEffect::Blur { radius } | PostEffect::UnsharpMask { radius, .. } | PostEffect::Halation { radius, .. } => {
+                    radius.set(radius.get() * factor);
+                }
+            }
+        }
+        chain
+    }
+}
+
+/// One post-processing effect; radii are Gaussian standard deviations in output pixels
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum PostEffect {
+    /// Separable Gaussian blur; softens grain beyond what `dye_softness` does per layer
+    Blur {
+        #[serde(default = "default_blur_radius")]
+        radius: BoundedFloat, // 0.0 - 16.0
+    },
+    /// Red-tinted glow around highlights, from light scattered back through the emulsion
+    Halation {
+        #[serde(default = "default_halation_threshold")]
+        threshold: BoundedFloat, // 0.0 - 1.0
+        #[serde(default = "default_halation_radius")]
+        radius: BoundedFloat, // 0.0 - 64.0
+        #[serde(default = "default_halation_strength")]
+        strength: BoundedFloat, // 0.0 - 2.0
+        #[serde(default = "default_halation_tint")]
+        tint: [BoundedFloat; 3], // 0.0 - 1.0
+    },
+    /// Unsharp mask; crisps grain edges beyond what `sharpness` does per grain
+    UnsharpMask {
+        #[serde(default = "default_unsharp_radius")]
+        radius: BoundedFloat, // 0.0 - 8.0
+        #[serde(default = "default_unsharp_amount")]
+        amount: BoundedFloat, // 0.0 - 3.0
+    },
+}
+
+fn default_blur_radius() -> BoundedFloat { BoundedFloat::new(1.0, 0.0, 16.0) }
+fn default_halation_threshold() -> BoundedFloat { BoundedFloat::new(0.8, 0.0, 1.0) }
+fn default_halation_radius() -> BoundedFloat { BoundedFloat::new(8.0, 0.0, 64.0) }
+fn default_halation_strength() -> BoundedFloat { BoundedFloat::new(0.5, 0.0, 2.0) }
+fn default_halation_tint() -> [BoundedFloat; 3] {
+    [
+        BoundedFloat::new(1.0, 0.0, 1.0),
+        BoundedFloat::new(0.25, 0.0, 1.0),
+        BoundedFloat::new(0.1, 0.0, 1.0),
+    ]
+}
+fn default_unsharp_radius() -> BoundedFloat { BoundedFloat::new(1.5, 0.0, 8.0) }
+fn default_unsharp_amount() -> BoundedFloat { BoundedFloat::new(0.5, 0.0, 3.0) }
+
+impl PostEffect {
+    /// Each kind of effect with its default settings
+    pub fn defaults() -> [PostEffect; 3] {
+        [
+            Self::Blur { radius: default_blur_radius() },
+            Self::Halation {
+                threshold: default_halation_threshold(),
+                radius: default_halation_radius(),
+                strength: default_halation_strength(),
+                tint: default_halation_tint(),
+            },
+            Self::UnsharpMask { radius: default_unsharp_radius(), amount: default_unsharp_amount() },
+        ]
+    }
+
+    /// Each kind of effect set up to continue what `stock` already does per grain: the blur
+    /// spreads like its dye clouds and the unsharp mask is as strong as its grain edges are hard
+    pub fn defaults_for(stock: &FilmStock) -> [PostEffect; 3] {
+        let size = stock.grain.size.get();
+        Self::defaults().map(|mut effect| {
+            match &mut effect {
+                Self::Blur { radius } => radius.set(mix(0.5, 2.0, stock.color.dye_softness.get()) * size),
+                Self::UnsharpMask { radius, amount } => {
+                    radius.set(radius.get() * size);
+                    amount.set(mix(0.2, 1.5, stock.grain.sharpness.get()));
+                }
+                Self::Halation { .. } => {}
+            }
+            effect
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blur { .. } => "Blur",
+            Self::Halation { .. } => "Halation",
+            Self::UnsharpMask { .. } => "Unsharp Mask",
+        }
+    }
+
+    /// Gaussian standard deviation in pixels
+    pub fn radius(&self) -> f32 {
+        match self {
+            Self::Blur { radius } | Self::Halation { radius, .. } | Self::UnsharpMask { radius, .. } => radius.get(),
+        }
+    }
+
+    /// Kernel taps on each side of a pixel
+    pub fn reach(&self) -> u32 {
+        gaussian_taps(self.radius())
+    }
+}
+
+/// Taps on each side of a Gaussian kernel of standard deviation `sigma`: three sigma
+/// holds all but 0.3% of its weight. Zero taps means the blur does nothing.
+pub fn gaussian_taps(sigma: f32) -> u32 {
+    if sigma < MIN_BLUR_SIGMA {
+        0
+    } else {
+        (3.0 * sigma).ceil() as u32
+    }
+}
+
+/// Radii below this change no pixel
+const MIN_BLUR_SIGMA: f32 = 0.05;
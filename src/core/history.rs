@@ -36,7 +36,7 @@ impl HistoryManager {
         self.redo_stack.clear(); // Clear redo on new action
     }
 
-    pub fn undo(&mut self, params: &mut Vec<Parameter>) -> Option<()> {
+    pub fn undo(&mut self, params: &mut [Parameter]) -> Option<()> {
         let command = self.undo_stack.pop_back()?;
         
         match &command {
@@ -51,7 +51,7 @@ impl HistoryManager {
         Some(())
     }
 
-    pub fn redo(&mut self, params: &mut Vec<Parameter>) -> Option<()> {
+    pub fn redo(&mut self, params: &mut [Parameter]) -> Option<()> {
         let command = self.redo_stack.pop_back()?;
         
         match &command {
@@ -0,0 +1,29 @@
+use std::path::Path;
+use image::Rgba32FImage;
+use crate::core::error::GrainError;
+use crate::core::film_stock::FilmStock;
+
+/// Load a photograph (PNG or TIFF) to composite grain onto, at its full bit depth
+pub fn load_plate(path: &Path) -> Result<Rgba32FImage, GrainError> {
+    let ext = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .ok_or_else(|| GrainError::Import("Missing file extension".to_string()))?;
+
+    if !matches!(ext.as_str(), "png" | "tif" | "tiff") {
+        return Err(GrainError::Import(format!("Unsupported extension '{}' (allowed: png, tif)", ext)));
+    }
+
+    let image = image::open(path)
+        .map_err(|e| GrainError::Import(e.to_string()))?;
+
+    // Float keeps 16-bit plates exact, so deep exports carry the plate's own precision
+    Ok(image.into_rgba32f())
+}
+
+/// Load a film stock saved as JSON
+pub fn load_film_stock(path: &Path) -> Result<FilmStock, GrainError> {
+    let json = std::fs::read_to_string(path)?;
+    serde_json::from_str(&json)
+        .map_err(|e| GrainError::Import(format!("{}: {e}", path.display())))
+}
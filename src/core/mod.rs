@@ -4,4 +4,5 @@ pub mod parameter;
 pub mod history;
 pub mod error;
 pub mod export;
+pub mod import;
 pub mod presets;
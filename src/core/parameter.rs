@@ -1,20 +1,36 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParameterValue {
     Float(f32),
     Int(i32),
     Bool(bool),
     Selection(String),
+    Vec2([f32; 2]),
+    Color([f32; 4]),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ParameterValue {
+    /// Name of the variant, for error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParameterValue::Float(_) => "float",
+            ParameterValue::Int(_) => "int",
+            ParameterValue::Bool(_) => "bool",
+            ParameterValue::Selection(_) => "selection",
+            ParameterValue::Vec2(_) => "vec2",
+            ParameterValue::Color(_) => "color",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ParameterCurve {
     // Placeholder for curve data (e.g., control points)
     pub points: Vec<(f32, f32)>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParameterRange {
     Float { min: f32, max: f32 },
     Int { min: i32, max: i32 },
@@ -22,7 +38,7 @@ pub enum ParameterRange {
     Curve(ParameterCurve),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Parameter {
     pub id: String,
     pub display_name: String,
@@ -44,6 +60,32 @@ impl Parameter {
         }
     }
     
+    /// Vector whose components each lie in `min..=max`
+    pub fn new_vec2(id: &str, name: &str, desc: &str, val: [f32; 2], min: f32, max: f32) -> Self {
+        Self {
+            value: ParameterValue::Vec2(val),
+            default_value: ParameterValue::Vec2(val),
+            ..Self::new_float(id, name, desc, 0.0, min, max)
+        }
+    }
+
+    /// Linear RGBA color with components in 0 - 1
+    pub fn new_color(id: &str, name: &str, desc: &str, val: [f32; 4]) -> Self {
+        Self {
+            value: ParameterValue::Color(val),
+            default_value: ParameterValue::Color(val),
+            ..Self::new_float(id, name, desc, 0.0, 0.0, 1.0)
+        }
+    }
+
+    pub fn new_bool(id: &str, name: &str, desc: &str, val: bool) -> Self {
+        Self {
+            value: ParameterValue::Bool(val),
+            default_value: ParameterValue::Bool(val),
+            ..Self::new_float(id, name, desc, 0.0, 0.0, 1.0)
+        }
+    }
+
     // Helper to reset to default
     pub fn reset(&mut self) {
         self.value = self.default_value.clone();
@@ -1,6 +1,7 @@
 use crate::core::film_stock::{
     FilmStock, FilmMeta, GrainParameters, ResponseCurve, ColorParameters,
-    TextureParameters, CrystalType, ResponseMode, ClusteringType,
+    TextureParameters, CrystalType, ResponseMode, ClusteringType, GrainSynthesis, PostProcessChain,
+    DamageParameters,
 };
 use crate::utils::validation::BoundedFloat;
 
@@ -30,6 +31,8 @@ fn fine_grain_preset() -> FilmStock {
             size_variation: BoundedFloat::new(0.3, 0.0, 2.0),
             crystal_type: CrystalType::Tabular,
             sharpness: BoundedFloat::new(0.7, 0.0, 1.0),
+            synthesis: GrainSynthesis::Noise,
+            seed: 0,
         },
         response: ResponseCurve {
             shadows: BoundedFloat::new(0.4, 0.0, 2.0),
@@ -59,6 +62,8 @@ fn fine_grain_preset() -> FilmStock {
             detail: BoundedFloat::new(6.0, 1.0, 8.0),
             swirl: BoundedFloat::new(0.0, 0.0, 5.0),
         },
+        post_process: PostProcessChain::default(),
+        damage: DamageParameters::default(),
     }
 }
 
@@ -79,6 +84,8 @@ fn medium_grain_preset() -> FilmStock {
             size_variation: BoundedFloat::new(0.5, 0.0, 2.0),
             crystal_type: CrystalType::Cubic,
             sharpness: BoundedFloat::new(0.5, 0.0, 1.0),
+            synthesis: GrainSynthesis::Noise,
+            seed: 0,
         },
         response: ResponseCurve {
             shadows: BoundedFloat::new(0.6, 0.0, 2.0),
@@ -108,6 +115,8 @@ fn medium_grain_preset() -> FilmStock {
             detail: BoundedFloat::new(4.0, 1.0, 8.0),
             swirl: BoundedFloat::new(0.5, 0.0, 5.0),
         },
+        post_process: PostProcessChain::default(),
+        damage: DamageParameters::default(),
     }
 }
 
@@ -128,6 +137,8 @@ fn coarse_grain_preset() -> FilmStock {
             size_variation: BoundedFloat::new(1.0, 0.0, 2.0),
             crystal_type: CrystalType::Cellular,
             sharpness: BoundedFloat::new(0.3, 0.0, 1.0),
+            synthesis: GrainSynthesis::Noise,
+            seed: 0,
         },
         response: ResponseCurve {
             shadows: BoundedFloat::new(0.8, 0.0, 2.0),
@@ -157,5 +168,7 @@ fn coarse_grain_preset() -> FilmStock {
             detail: BoundedFloat::new(3.0, 1.0, 8.0),
             swirl: BoundedFloat::new(1.5, 0.0, 5.0),
         },
+        post_process: PostProcessChain::default(),
+        damage: DamageParameters::default(),
     }
 }
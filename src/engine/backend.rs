@@ -0,0 +1,33 @@
+use wgpu::{Instance, InstanceDescriptor, PowerPreference, RequestAdapterOptions};
+
+/// Which implementation produces grain pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderBackend {
+    /// wgpu compute pipeline (`GrainRenderer`)
+    #[default]
+    Gpu,
+    /// Pure-Rust reference implementation (`CpuGrainRenderer`)
+    Cpu,
+}
+
+impl RenderBackend {
+    pub const ALL: [RenderBackend; 2] = [RenderBackend::Gpu, RenderBackend::Cpu];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gpu => "GPU",
+            Self::Cpu => "CPU",
+        }
+    }
+}
+
+/// Probe wgpu for any usable adapter without creating a device
+pub fn gpu_adapter_available() -> bool {
+    let instance = Instance::new(&InstanceDescriptor::default());
+    pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    }))
+    .is_some()
+}
@@ -1,32 +1,86 @@
-use wgpu::{ComputePipeline, Device, ShaderModuleDescriptor, ShaderSource};
-use crate::core::error::GrainError;
+use wgpu::{BindGroupLayout, ComputePipeline, Device, ErrorFilter, PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource};
+use crate::core::error::{GrainError, ShaderError};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::shaders::{ProcessedShader, ShaderLibrary};
+use crate::nodes::codegen::{GraphShader, GRAPH_SHADER};
 
 pub struct GrainComputePipeline {
     pub pipeline: ComputePipeline,
 }
 
 impl GrainComputePipeline {
-    pub fn new(device: &Device) -> Result<Self, GrainError> {
-        // Load shaders
-        // Concatenate noise library with grain shader
-        let noise_src = include_str!("shaders/noise.wgsl");
-        let grain_src = include_str!("shaders/grain.wgsl");
-        let combined_src = format!("{}\n{}", noise_src, grain_src);
+    pub fn new(
+        device: &Device,
+        bind_group_layout: &BindGroupLayout,
+        format: OutputFormat,
+    ) -> Result<Self, GrainError> {
+        Self::from_library(device, bind_group_layout, format, &ShaderLibrary::builtin())
+    }
+
+    /// Build the pipeline from `library`'s `grain.wgsl`, e.g. sources reloaded from disk.
+    /// Invalid WGSL is reported as an error rather than reaching the device.
+    pub fn from_library(
+        device: &Device,
+        bind_group_layout: &BindGroupLayout,
+        format: OutputFormat,
+        library: &ShaderLibrary,
+    ) -> Result<Self, GrainError> {
+        let source = grain_shader(library, format)?;
+        Self::from_shader(device, bind_group_layout, &source, "grain.wgsl")
+    }
+
+    /// Build the pipeline from a compiled node graph. The layout binds the output texture,
+    /// the graph uniforms and the parameter buffer, as set up by
+    /// [`GraphRenderer`](crate::engine::graph_renderer::GraphRenderer).
+    pub fn from_graph(device: &Device, bind_group_layout: &BindGroupLayout, graph: &GraphShader) -> Result<Self, GrainError> {
+        Self::from_shader(device, bind_group_layout, &graph.shader, GRAPH_SHADER)
+    }
 
+    fn from_shader(
+        device: &Device,
+        bind_group_layout: &BindGroupLayout,
+        source: &ProcessedShader,
+        location: &str,
+    ) -> Result<Self, GrainError> {
+        // naga has already validated the module, but the backend can still reject it
+        device.push_error_scope(ErrorFilter::Validation);
         let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Grain Compute Shader"),
-            source: ShaderSource::Wgsl(combined_src.into()),
+            source: ShaderSource::Wgsl(source.code.as_str().into()),
+        });
+
+        // Explicit layout: bind groups created from an auto-inferred layout's twin are incompatible
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Grain Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
         });
 
         let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: Some("Grain Pipeline"),
-            layout: None, // Auto-infer layout
+            layout: Some(&layout),
             module: &shader,
             entry_point: Some("main"),
             compilation_options: Default::default(),
             cache: None,
         });
 
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            return Err(GrainError::Shader(ShaderError::Compile {
+                location: location.to_string(),
+                message: error.to_string(),
+            }));
+        }
+
         Ok(Self { pipeline })
     }
 }
+
+/// Preprocess and validate the grain shader for one output format.
+///
+/// The storage texel format is part of the shader, so each output format gets its own module.
+pub fn grain_shader(library: &ShaderLibrary, format: OutputFormat) -> Result<ProcessedShader, GrainError> {
+    let shader = library.preprocess("grain.wgsl", &[("OUTPUT_FORMAT", format.wgsl_format())])?;
+    shader.validate()?;
+    Ok(shader)
+}
@@ -0,0 +1,289 @@
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAINFORGE NOISE LIBRARY (CPU)
+// Line-for-line port of shaders/noise.wgsl and shaders/random.wgsl. Keep the files in sync: every
+// function here must produce the same result as its WGSL counterpart.
+//
+// Every lattice noise takes a `period` in lattice cells: lattice points are
+// wrapped into [0, period) before hashing so the noise tiles. A zero period
+// leaves the lattice unbounded.
+//
+// Every hash is keyed on the calling thread's noise key and evolves with its
+// noise time; call `seed_noise` before sampling, as the shader does once per
+// invocation.
+// ═══════════════════════════════════════════════════════════════════════════
+
+use std::cell::Cell;
+
+use crate::utils::math::{mix, Vec2};
+
+thread_local! {
+    /// Mirror of the shader's private `noise_key`
+    static NOISE_KEY: Cell<[u32; 2]> = const { Cell::new([0, 0]) };
+    /// Mirror of the shader's private `noise_time`
+    static NOISE_TIME: Cell<f32> = const { Cell::new(0.0) };
+}
+
+/// PCG Random Number Generator (matches `pcg` in random.wgsl)
+pub fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// 2D PCG hash (matches `pcg2d` in random.wgsl)
+pub fn pcg2d(v: [u32; 2]) -> [u32; 2] {
+    let mut x = v[0].wrapping_mul(1664525);
+    let mut y = v[1].wrapping_mul(1013904223);
+    x = x.wrapping_add(y.wrapping_mul(1664525));
+    y = y.wrapping_add(x.wrapping_mul(1664525));
+    x ^= x >> 16;
+    y ^= y >> 16;
+    x = x.wrapping_add(y.wrapping_mul(1664525));
+    y = y.wrapping_add(x.wrapping_mul(1664525));
+    x ^= x >> 16;
+    y ^= y >> 16;
+    [x, y]
+}
+
+/// Key all noise sampled on this thread on a 64-bit seed given as (low, high) words
+/// (matches `seed_noise` in random.wgsl); seed 0 leaves the hashes unkeyed. Time starts at 0.
+pub fn seed_noise(seed: [u32; 2]) {
+    NOISE_KEY.with(|key| key.set(pcg2d(seed)));
+    NOISE_TIME.with(|time| time.set(0.0));
+}
+
+/// Move all noise sampled on this thread to `time`, in re-rolls of each lattice point
+/// (matches `set_noise_time` in random.wgsl); the pattern at time 0 is the still one
+pub fn set_noise_time(time: f32) {
+    NOISE_TIME.with(|t| t.set(time));
+}
+
+/// Number of times the lattice point hashed to `h` has re-rolled; each point re-rolls at
+/// its own phase, so the pattern changes point by point rather than all at once
+fn noise_epoch(h: u32) -> u32 {
+    (NOISE_TIME.with(Cell::get) + uint_to_float(pcg(h))).floor() as u32
+}
+
+/// A lattice hash at the current noise time (matches `evolve` in random.wgsl)
+pub fn evolve(h: u32) -> u32 {
+    match noise_epoch(h) {
+        0 => h,
+        epoch => pcg(h ^ pcg(epoch)),
+    }
+}
+
+/// Two-word [`evolve`] (matches `evolve2` in random.wgsl)
+pub fn evolve2(h: [u32; 2]) -> [u32; 2] {
+    match noise_epoch(h[0]) {
+        0 => h,
+        epoch => pcg2d([h[0] ^ epoch, h[1] ^ pcg(epoch)]),
+    }
+}
+
+/// Key mixed into every noise hash on this thread
+pub fn noise_key() -> [u32; 2] {
+    NOISE_KEY.with(Cell::get)
+}
+
+/// Convert uint to float in [0, 1)
+pub fn uint_to_float(x: u32) -> f32 {
+    x as f32 * (1.0 / 4294967296.0)
+}
+
+/// Box-Muller transform: uniform -> gaussian
+pub fn box_muller(u1: f32, u2: f32) -> Vec2 {
+    let r = (-2.0 * u1.max(1e-10).ln()).sqrt();
+    let theta = std::f32::consts::TAU * u2;
+    Vec2::new(r * theta.cos(), r * theta.sin())
+}
+
+/// Wrap a lattice point into [0, period); zero period components are left unbounded
+pub fn wrap_cell(cell: Vec2, period: Vec2) -> Vec2 {
+    let wrap = |c: f32, n: f32| if n > 0.0 { c - n * (c / n).floor() } else { c };
+    Vec2::new(wrap(cell.x, period.x), wrap(cell.y, period.y))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// VALUE NOISE
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn hash21(p: Vec2) -> f32 {
+    let key = noise_key();
+    let h = pcg(p.x.to_bits() ^ key[0] ^ (p.y.to_bits() ^ key[1]).wrapping_mul(747796405));
+    uint_to_float(evolve(h))
+}
+
+pub fn value_noise(p: Vec2, period: Vec2) -> f32 {
+    let i = p.floor();
+    let f = p.fract();
+
+    // Quintic interpolation (smoother than cubic)
+    let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    let a = hash21(wrap_cell(i, period));
+    let b = hash21(wrap_cell(i + Vec2::new(1.0, 0.0), period));
+    let c = hash21(wrap_cell(i + Vec2::new(0.0, 1.0), period));
+    let d = hash21(wrap_cell(i + Vec2::new(1.0, 1.0), period));
+
+    mix(mix(a, b, u.x), mix(c, d, u.x), u.y)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// SIMPLEX NOISE (2D)
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn simplex_noise(p: Vec2, period: Vec2) -> f32 {
+    // The skewed simplex lattice never repeats along the axes; tile on a sheared one instead
+    if period.x > 0.0 || period.y > 0.0 {
+        return periodic_simplex_noise(p, period);
+    }
+
+    const K1: f32 = 0.366_025_42; // (sqrt(3)-1)/2
+    const K2: f32 = 0.211_324_87; // (3-sqrt(3))/6
+
+    let i = (p + (p.x + p.y) * K1).floor();
+    let a = p - i + (i.x + i.y) * K2;
+
+    let o = if a.x > a.y { Vec2::new(1.0, 0.0) } else { Vec2::new(0.0, 1.0) };
+
+    let b = a - o + K2;
+    let c = a - 1.0 + 2.0 * K2;
+
+    let mut h = [
+        (0.5 - a.dot(a)).max(0.0),
+        (0.5 - b.dot(b)).max(0.0),
+        (0.5 - c.dot(c)).max(0.0),
+    ];
+    for v in &mut h {
+        *v = *v * *v * *v * *v;
+    }
+
+    let n = [
+        a.dot(hash22(i) - 0.5),
+        b.dot(hash22(i + o) - 0.5),
+        c.dot(hash22(i + 1.0) - 0.5),
+    ];
+
+    (n[0] * h[0] + n[1] * h[1] + n[2] * h[2]) * 70.0
+}
+
+/// Matches the output range of the unbounded simplex noise
+const PERIODIC_SIMPLEX_SCALE: f32 = 8.8;
+
+/// Simplex noise on a sheared lattice whose triangles tile axis-aligned periods
+/// (after Gustavson & McEwan's psrdnoise). `period.y` must be even.
+pub fn periodic_simplex_noise(p: Vec2, period: Vec2) -> f32 {
+    let uv = Vec2::new(p.x + p.y * 0.5, p.y);
+    let i = uv.floor();
+    let f = uv.fract();
+
+    let o = if f.x > f.y { Vec2::new(1.0, 0.0) } else { Vec2::new(0.0, 1.0) };
+
+    let v0 = Vec2::new(i.x - i.y * 0.5, i.y);
+    let v1 = v0 + Vec2::new(o.x - o.y * 0.5, o.y);
+    let v2 = v0 + Vec2::new(0.5, 1.0);
+
+    let a = p - v0;
+    let b = p - v1;
+    let c = p - v2;
+
+    let mut h = [
+        (0.8 - a.dot(a)).max(0.0),
+        (0.8 - b.dot(b)).max(0.0),
+        (0.8 - c.dot(c)).max(0.0),
+    ];
+    for v in &mut h {
+        *v = *v * *v * *v * *v;
+    }
+
+    let n = [
+        a.dot(sheared_gradient(v0, period)),
+        b.dot(sheared_gradient(v1, period)),
+        c.dot(sheared_gradient(v2, period)),
+    ];
+
+    (n[0] * h[0] + n[1] * h[1] + n[2] * h[2]) * PERIODIC_SIMPLEX_SCALE
+}
+
+/// Gradient at a sheared lattice vertex, hashed by its wrapped lattice index
+fn sheared_gradient(v: Vec2, period: Vec2) -> Vec2 {
+    let w = wrap_cell(v, period);
+    hash22((Vec2::new(w.x + w.y * 0.5, w.y) + 0.5).floor()) - 0.5
+}
+
+pub fn hash22(p: Vec2) -> Vec2 {
+    let key = noise_key();
+    let h = evolve2(pcg2d([p.x.to_bits() ^ key[0], p.y.to_bits() ^ key[1]]));
+    Vec2::new(uint_to_float(h[0]), uint_to_float(h[1]))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// VORONOI / WORLEY NOISE
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoronoiResult {
+    pub distance: f32, // Distance to nearest cell
+    pub cell_id: Vec2, // ID of nearest cell (for coloring); wrap it before hashing when tiling
+}
+
+pub fn voronoi(p: Vec2, jitter: f32, period: Vec2) -> VoronoiResult {
+    let n = p.floor();
+    let f = p.fract();
+
+    let mut min_dist = 8.0;
+    let mut min_cell = Vec2::ZERO;
+
+    for j in -1..=1 {
+        for i in -1..=1 {
+            let g = Vec2::new(i as f32, j as f32);
+            let o = hash22(wrap_cell(n + g, period)) * jitter;
+            let r = g + o - f;
+            let d = r.dot(r);
+
+            if d < min_dist {
+                min_dist = d;
+                min_cell = n + g;
+            }
+        }
+    }
+
+    VoronoiResult { distance: min_dist.sqrt(), cell_id: min_cell }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// FRACTAL BROWNIAN MOTION
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn fbm(p: Vec2, octaves: i32, lacunarity: f32, persistence: f32, period: Vec2) -> f32 {
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    let pos = p;
+
+    for _ in 0..octaves {
+        value += amplitude * simplex_noise(pos * frequency, period * frequency);
+        frequency *= lacunarity;
+        amplitude *= persistence;
+    }
+
+    value
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// DOMAIN WARPING
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn domain_warp(p: Vec2, strength: f32, octaves: i32, period: Vec2) -> f32 {
+    let q = Vec2::new(
+        fbm(p + Vec2::new(0.0, 0.0), octaves, 2.0, 0.5, period),
+        fbm(p + Vec2::new(5.2, 1.3), octaves, 2.0, 0.5, period),
+    );
+
+    let r = Vec2::new(
+        fbm(p + strength * q + Vec2::new(1.7, 9.2), octaves, 2.0, 0.5, period),
+        fbm(p + strength * q + Vec2::new(8.3, 2.8), octaves, 2.0, 0.5, period),
+    );
+
+    fbm(p + strength * r, octaves, 2.0, 0.5, period)
+}
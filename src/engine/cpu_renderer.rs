@@ -0,0 +1,700 @@
+use image::{Rgba32FImage, RgbaImage};
+
+use crate::core::error::GrainError;
+use crate::core::film_stock::PostProcessChain;
+use crate::engine::cpu_noise::{
+    box_muller, domain_warp, evolve, fbm, hash21, hash22, noise_key, pcg, seed_noise, set_noise_time,
+    simplex_noise, uint_to_float, voronoi, wrap_cell,
+};
+use crate::engine::grain_renderer::{
+    GrainParams, BLEND_ADDITIVE, BLEND_SOFT_LIGHT, CLUSTER_FRACTAL, CLUSTER_HYBRID, CLUSTER_POISSON,
+    CLUSTER_VORONOI, CRYSTAL_CELLULAR,
+    CRYSTAL_CORE_SHELL, CRYSTAL_CUBIC, CRYSTAL_CUSTOM, CRYSTAL_NEEDLE, CRYSTAL_TABULAR,
+    RESPONSE_PRINT, RESPONSE_REVERSAL, SYNTHESIS_BOOLEAN, SYNTHESIS_PARTICLE,
+};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::post_process;
+use crate::utils::color::{linear_to_srgb, luminance, srgb_to_linear};
+use crate::utils::math::{mix, smoothstep, Vec2};
+
+/// Pure-Rust reference renderer that mirrors `grain.wgsl` pixel for pixel.
+///
+/// Used when no GPU adapter is available (headless build boxes, CI) and as the
+/// ground truth for deterministic tests of the noise library.
+pub struct CpuGrainRenderer {
+    output: Vec<u8>,
+    input: Option<Rgba32FImage>,
+    format: OutputFormat,
+    width: u32,
+    height: u32,
+}
+
+impl CpuGrainRenderer {
+    pub fn new(width: u32, height: u32) -> Result<Self, GrainError> {
+        Self::with_format(width, height, OutputFormat::default())
+    }
+
+    /// Renderer whose output buffer matches a GPU render target of the given format
+    pub fn with_format(width: u32, height: u32, format: OutputFormat) -> Result<Self, GrainError> {
+        if width == 0 || height == 0 {
+            return Err(GrainError::InvalidParameter {
+                name: "size".to_string(),
+                reason: format!("Output must not be empty, got {width}x{height}"),
+            });
+        }
+        Ok(Self {
+            output: vec![0; width as usize * height as usize * format.bytes_per_pixel()],
+            input: None,
+            format,
+            width,
+            height,
+        })
+    }
+
+    /// Composite grain onto a photograph; it must match the output size
+    pub fn set_input_image(&mut self, image: Rgba32FImage) -> Result<(), GrainError> {
+        if image.dimensions() != (self.width, self.height) {
+            return Err(GrainError::InvalidParameter {
+                name: "input_image".to_string(),
+                reason: format!(
+                    "Image is {}x{} but the renderer is {}x{}",
+                    image.width(), image.height(), self.width, self.height
+                ),
+            });
+        }
+        self.input = Some(image);
+        Ok(())
+    }
+
+    /// Go back to rendering a standalone grain texture
+    pub fn clear_input(&mut self) {
+        self.input = None;
+    }
+
+    pub fn has_input(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Render grain with the given parameters into the output buffer
+    pub fn render(&mut self, params: &GrainParams) {
+        let params = GrainParams { width: self.width as f32, height: self.height as f32, ..*params };
+        self.render_tile(&params, [0, 0]);
+    }
+
+    /// Render the region of a larger output whose top-left pixel is `origin`,
+    /// like [`GrainRenderer::render_tile`](crate::engine::grain_renderer::GrainRenderer::render_tile)
+    pub fn render_tile(&mut self, params: &GrainParams, origin: [u32; 2]) {
+        let params = GrainParams { use_input: self.has_input() as u32, tile_origin: origin, ..*params };
+        let dimensions = [params.width as u32, params.height as u32];
+        let format = self.format;
+        let pixel_bytes = format.bytes_per_pixel();
+        let row_bytes = self.width as usize * pixel_bytes;
+        let input = self.input.as_ref();
+
+        // Rows are independent, so split them across threads
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let rows_per_chunk = (self.height as usize).div_ceil(threads).max(1);
+
+        std::thread::scope(|scope| {
+            for (chunk_index, chunk) in self.output.chunks_mut(rows_per_chunk * row_bytes).enumerate() {
+                let params = &params;
+                scope.spawn(move || {
+                    let first_row = chunk_index * rows_per_chunk;
+                    for (row_offset, row) in chunk.chunks_exact_mut(row_bytes).enumerate() {
+                        let y = (first_row + row_offset) as u32;
+                        for (x, pixel) in row.chunks_exact_mut(pixel_bytes).enumerate() {
+                            let coords = [x as u32 + origin[0], y + origin[1]];
+                            let base = input.map(|image| image.get_pixel(x as u32, y).0);
+                            let color = shade_pixel(params, coords, dimensions, base);
+                            for (channel, value) in pixel.chunks_exact_mut(format.bytes_per_channel()).zip(color) {
+                                format.store(value, channel);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Run a post-process chain over the last render, like
+    /// [`GrainRenderer::post_process`](crate::engine::grain_renderer::GrainRenderer::post_process)
+    pub fn post_process(&mut self, chain: &PostProcessChain) {
+        self.post_process_tile(chain, [self.width, self.height]);
+    }
+
+    /// Run a post-process chain over a tile; pixels past `valid` lie beyond the output
+    /// edge and are never sampled
+    pub fn post_process_tile(&mut self, chain: &PostProcessChain, valid: [u32; 2]) {
+        if chain.is_empty() {
+            return;
+        }
+        let mut pixels = self.format.decode(&self.output);
+        post_process::apply_chain(chain, &mut pixels, self.width, self.height, valid);
+        for (channel, value) in self.output.chunks_exact_mut(self.format.bytes_per_channel()).zip(pixels) {
+            self.format.store(value, channel);
+        }
+    }
+
+    /// The last render as an 8-bit image, ready for `core::export`
+    pub fn output_image(&self) -> RgbaImage {
+        self.format.to_rgba8_image(self.width, self.height, self.output.clone())
+            .expect("output buffer always matches the renderer size")
+    }
+
+    /// The last render at full precision, for deep exports
+    pub fn output_image_f32(&self) -> Rgba32FImage {
+        self.format.to_rgba32f_image(self.width, self.height, &self.output)
+            .expect("output buffer always matches the renderer size")
+    }
+
+    /// Tightly packed pixels of the last render in the renderer's [`OutputFormat`],
+    /// byte for byte what a GPU readback of the same format returns
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// TILING
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Lattice cells across the output; tiling snaps it to an even count so the lattice wraps
+fn noise_scale(params: &GrainParams) -> f32 {
+    let scale = 100.0 / params.grain_size.max(0.1);
+    if params.tileable != 0 {
+        return (2.0 * (scale * 0.5 + 0.5).floor()).max(2.0);
+    }
+    scale
+}
+
+/// Factor for sampling a lattice of `period` cells at `scale` times the frequency.
+/// While tiling, it is snapped so the scaled period is still a whole, even number of cells.
+fn tile_scale(period: Vec2, scale: Vec2) -> Vec2 {
+    if period == Vec2::ZERO {
+        return scale;
+    }
+    let snap = |cells: f32| (2.0 * (cells * 0.5 + 0.5).floor()).max(2.0);
+    Vec2::new(snap(period.x * scale.x), snap(period.y * scale.y)) / period
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CRYSTAL SHAPES
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Distance from the centre of a regular n-gon with unit circumradius; edges are at 1
+fn polygon_radius(q: Vec2, sides: u32) -> f32 {
+    let sector = std::f32::consts::TAU / sides as f32;
+    let angle = q.y.atan2(q.x);
+    let a = angle - sector * (angle / sector).floor() - sector * 0.5;
+    q.length() * a.cos() / (sector * 0.5).cos()
+}
+
+/// Distance from a Voronoi feature point measured against a regular n-gon
+fn polygon_distance(p: Vec2, sides: u32, period: Vec2) -> f32 {
+    let cell = voronoi(p, 0.8, period);
+    polygon_radius(p - (cell.cell_id + hash22(wrap_cell(cell.cell_id, period)) * 0.8), sides)
+}
+
+/// Base grain field in [-1, 1] shaped by the crystal type
+fn crystal_noise(params: &GrainParams, p: Vec2, period: Vec2) -> f32 {
+    match params.crystal_type {
+        CRYSTAL_TABULAR => {
+            // Flat plates: wide, thin grains
+            let s = tile_scale(period, Vec2::new(0.5, 1.0));
+            simplex_noise(p * s, period * s)
+        }
+        CRYSTAL_CORE_SHELL => {
+            // Dense core surrounded by a fainter shell
+            let d = voronoi(p, 0.8, period).distance;
+            let core = 1.0 - smoothstep(0.0, 0.2, d);
+            let shell = 1.0 - smoothstep(0.0, 0.15, (d - 0.4).abs());
+            (core + 0.6 * shell).clamp(0.0, 1.0) * 2.0 - 1.0
+        }
+        CRYSTAL_CELLULAR => 1.0 - 2.0 * voronoi(p, 1.0, period).distance.clamp(0.0, 1.0),
+        CRYSTAL_NEEDLE => {
+            // Strongly elongated along x
+            let s = tile_scale(period, Vec2::new(0.2, 2.0));
+            simplex_noise(p * s, period * s)
+        }
+        CRYSTAL_CUSTOM => {
+            let sides = params.crystal_sides.clamp(3, 12);
+            1.0 - 2.0 * polygon_distance(p, sides, period).clamp(0.0, 1.0)
+        }
+        _ => simplex_noise(p, period),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PARTICLE GRAINS
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Grain slots per lattice cell; each slot is occupied with probability `PARTICLE_DENSITY`
+const PARTICLES_PER_CELL: u32 = 2;
+const PARTICLE_DENSITY: f32 = 0.75;
+
+fn hash_cell(cell: Vec2, index: u32) -> u32 {
+    let (cx, cy) = (cell.x as i32 as u32, cell.y as i32 as u32);
+    let key = noise_key();
+    evolve(pcg(cx ^ pcg(cy ^ pcg(index ^ key[0]) ^ key[1])))
+}
+
+/// Independent uniform draw `k` from a grain hash
+fn grain_random(h: u32, k: u32) -> f32 {
+    uint_to_float(pcg(h.wrapping_add(k.wrapping_mul(2654435761))))
+}
+
+/// Normalised distance from the grain centre in grain-local coordinates; the outline is at 1
+fn crystal_shape_distance(params: &GrainParams, q: Vec2) -> f32 {
+    match params.crystal_type {
+        CRYSTAL_CUBIC => polygon_radius(q, 4),
+        // Flat disc seen at an angle
+        CRYSTAL_TABULAR => Vec2::new(q.x, q.y / 0.5).length(),
+        CRYSTAL_CELLULAR => polygon_radius(q, 6),
+        CRYSTAL_NEEDLE => Vec2::new(q.x, q.y / 0.15).length(),
+        CRYSTAL_CUSTOM => polygon_radius(q, params.crystal_sides.clamp(3, 12)),
+        _ => q.length(),
+    }
+}
+
+/// Opacity of a grain at normalised distance `d`; sharpness narrows the edge falloff
+fn grain_coverage(params: &GrainParams, d: f32) -> f32 {
+    let falloff = mix(0.6, 0.02, params.sharpness);
+    let edge = 1.0 - smoothstep(1.0 - falloff, 1.0, d);
+
+    if params.crystal_type == CRYSTAL_CORE_SHELL {
+        // Dense core inside a fainter shell
+        let core = 1.0 - smoothstep(0.45 - 0.5 * falloff, 0.45, d);
+        let shell = edge * smoothstep(0.7 - 0.5 * falloff, 0.7, d);
+        return core.max(0.6 * shell);
+    }
+    edge
+}
+
+/// Coverage in [0, 1] from grains seeded in this and the eight neighbouring cells
+fn particle_field(params: &GrainParams, p: Vec2, period: Vec2) -> f32 {
+    let cell = p.floor();
+    let mut coverage = 0.0;
+
+    for j in -1..=1 {
+        for i in -1..=1 {
+            let neighbor = cell + Vec2::new(i as f32, j as f32);
+            for k in 0..PARTICLES_PER_CELL {
+                let h = hash_cell(wrap_cell(neighbor, period), k);
+                if grain_random(h, 0) > PARTICLE_DENSITY {
+                    continue;
+                }
+
+                let center = neighbor + Vec2::new(grain_random(h, 1), grain_random(h, 2));
+                // Log-normal sizes; capped at one cell so the 3x3 search stays exact
+                let g = box_muller(grain_random(h, 3), grain_random(h, 4)).x;
+                let radius = (0.45 * (0.5 * params.size_variation * g).exp()).min(1.0);
+                let angle = grain_random(h, 5) * std::f32::consts::TAU;
+
+                let offset = p - center;
+                let (sin, cos) = angle.sin_cos();
+                let q = Vec2::new(offset.x * cos + offset.y * sin, offset.y * cos - offset.x * sin) / radius;
+                coverage += grain_coverage(params, crystal_shape_distance(params, q));
+            }
+        }
+    }
+
+    coverage.min(1.0)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// BOOLEAN MODEL
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Mean disc radius in lattice units; the lattice cell bounds the largest disc
+const BOOLEAN_MEAN_RADIUS: f32 = 0.35;
+/// Caps the Poisson draw per cell so near-white pixels stay bounded
+const BOOLEAN_MAX_DISCS: u32 = 48;
+/// Hash stream for the per-pixel filter samples, kept apart from the disc streams
+const BOOLEAN_SAMPLE_STREAM: u32 = 0x2545_f491;
+
+/// Disc intensity giving expected coverage u, from `1 - exp(-lambda * pi * E[r^2]) = u`
+fn boolean_lambda(params: &GrainParams, u: f32) -> f32 {
+    let s = 0.5 * params.size_variation;
+    let mean_r2 = BOOLEAN_MEAN_RADIUS * BOOLEAN_MEAN_RADIUS * (2.0 * s * s).exp();
+    -(1.0 - u.clamp(0.0, 0.995)).ln() / (std::f32::consts::PI * mean_r2)
+}
+
+/// Whether any disc of the Boolean model with intensity `lambda` covers p
+fn boolean_covered(params: &GrainParams, p: Vec2, period: Vec2, lambda: f32, layer: u32) -> bool {
+    let cell = p.floor();
+    let threshold = (-lambda).exp();
+    let s = 0.5 * params.size_variation;
+
+    for j in -1..=1 {
+        for i in -1..=1 {
+            let neighbor = cell + Vec2::new(i as f32, j as f32);
+            let h = hash_cell(wrap_cell(neighbor, period), layer);
+
+            // Knuth's Poisson sampler; reusing the uniforms at every lambda keeps the
+            // disc sets nested, so brighter pixels only ever add discs
+            let mut product = 1.0;
+            for k in 0..BOOLEAN_MAX_DISCS {
+                let draw = 5 * k;
+                product *= grain_random(h, draw);
+                if product <= threshold {
+                    break;
+                }
+
+                let center = neighbor + Vec2::new(grain_random(h, draw + 1), grain_random(h, draw + 2));
+                let g = box_muller(grain_random(h, draw + 3), grain_random(h, draw + 4)).x;
+                let radius = (BOOLEAN_MEAN_RADIUS * (s * g).exp()).min(1.0);
+                let offset = p - center;
+                if offset.dot(offset) < radius * radius {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Monte Carlo estimate of the Gaussian-filtered Boolean model at intensity u.
+/// `pixel` is the size of one output pixel in lattice units.
+fn boolean_grain(
+    params: &GrainParams,
+    p: Vec2,
+    period: Vec2,
+    pixel: f32,
+    coords: [u32; 2],
+    u: f32,
+    layer: u32,
+) -> f32 {
+    let lambda = boolean_lambda(params, u);
+    // Sharper stocks use a narrower reconstruction filter
+    let sigma = mix(1.2, 0.4, params.sharpness) * pixel;
+    let h = hash_cell(Vec2::new(coords[0] as f32, coords[1] as f32), BOOLEAN_SAMPLE_STREAM);
+    let samples = params.samples.max(1);
+
+    let mut hits = 0u32;
+    for n in 0..samples {
+        let offset = box_muller(grain_random(h, 2 * n), grain_random(h, 2 * n + 1)) * sigma;
+        if boolean_covered(params, p + offset, period, lambda, layer) {
+            hits += 1;
+        }
+    }
+
+    hits as f32 / samples as f32
+}
+
+/// Mix in a finer octave of crystals to spread the size distribution
+fn grain_field(params: &GrainParams, p: Vec2, period: Vec2) -> f32 {
+    if params.synthesis == SYNTHESIS_PARTICLE {
+        return particle_field(params, p, period) * 2.0 - 1.0;
+    }
+
+    let primary = crystal_noise(params, p, period);
+    let s = tile_scale(period, Vec2::splat(2.0));
+    let secondary = crystal_noise(params, p * s + Vec2::new(17.0, 31.0), period * s);
+    let w = params.size_variation * 0.5;
+    (primary + secondary * w) / (1.0 + w)
+}
+
+/// Hard-edged grains for high sharpness, soft for low
+fn apply_sharpness(params: &GrainParams, n: f32) -> f32 {
+    // Particle grains already carry their edge falloff
+    if params.synthesis == SYNTHESIS_PARTICLE {
+        return n;
+    }
+    let exponent = mix(2.0, 0.5, params.sharpness);
+    wgsl_sign(n) * n.abs().powf(exponent)
+}
+
+/// WGSL `sign` returns 0 for 0, unlike `f32::signum`
+fn wgsl_sign(x: f32) -> f32 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// DYE CLOUDS (COLOR)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Larger dye clouds diffuse further, so softness scales with the layer size
+fn layer_softness(params: &GrainParams, c: usize) -> f32 {
+    (params.dye_softness * params.channel_size[c]).clamp(0.0, 1.0)
+}
+
+/// One dye layer: crystal grain diffused into a smooth cloud by `softness`
+fn dye_cloud(params: &GrainParams, p: Vec2, period: Vec2, softness: f32) -> f32 {
+    let s = tile_scale(period, Vec2::splat(0.5));
+    let cloud = simplex_noise(p * s + Vec2::new(101.0, 53.0), period * s);
+    let mixed = mix(grain_field(params, p, period), cloud, softness);
+    // Mixing two independent fields lowers the variance; restore it
+    mixed / ((1.0 - softness) * (1.0 - softness) + softness * softness).sqrt()
+}
+
+/// Correlate the three dye layers so each pair has correlation `params.correlation`
+/// (negative values bottom out at -0.5, the minimum for three equal layers)
+fn correlate_layers(params: &GrainParams, shared_layer: f32, own: [f32; 3]) -> [f32; 3] {
+    let rho = params.correlation;
+    if rho >= 0.0 {
+        return own.map(|o| rho.sqrt() * shared_layer + (1.0 - rho).sqrt() * o);
+    }
+
+    // Subtract k times the layer mean; k solves (k^2 - 2k) / (3 - 2k + k^2) = rho / 2
+    let t = 0.5 * rho;
+    let k = 1.0 - (1.0 + 3.0 * t / (1.0 - t)).sqrt();
+    let mean = (own[0] + own[1] + own[2]) / 3.0;
+    let norm = (1.0 - 2.0 * k / 3.0 + k * k / 3.0).sqrt();
+    own.map(|o| (o - k * mean) / norm)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// RESPONSE CURVE
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Grain strength at a given density; 0.5 is neutral for every band
+fn response_weight(params: &GrainParams, density: f32) -> f32 {
+    let mut luma = density;
+    // Positive-working stocks build density inversely to exposure
+    if params.response_mode == RESPONSE_PRINT || params.response_mode == RESPONSE_REVERSAL {
+        luma = 1.0 - luma;
+    }
+
+    let ws = 1.0 - smoothstep(0.0, 0.5, luma);
+    let wh = smoothstep(0.5, 1.0, luma);
+    let wm = 1.0 - ws - wh;
+
+    (ws * params.response_shadows + wm * params.response_midtones + wh * params.response_highlights) * 2.0
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CLUSTERING
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Octaves of cluster detail; `detail` is a float parameter in [1, 8]
+fn cluster_octaves(params: &GrainParams) -> i32 {
+    (params.detail as i32).clamp(1, 8)
+}
+
+/// Swirl the cluster lattice with a low-frequency fbm flow
+fn cluster_warp(params: &GrainParams, q: Vec2, period: Vec2) -> Vec2 {
+    let octaves = cluster_octaves(params);
+    let s = tile_scale(period, Vec2::splat(0.5));
+    let flow = Vec2::new(
+        fbm(q * s, octaves, 2.0, 0.5, period * s),
+        fbm(q * s + Vec2::new(3.1, 7.4), octaves, 2.0, 0.5, period * s),
+    );
+    q + params.swirl * flow
+}
+
+/// Neyman-Scott process: grains gather around randomly placed parent points
+fn poisson_clusters(params: &GrainParams, q: Vec2, period: Vec2) -> f32 {
+    let parent = voronoi(cluster_warp(params, q, period), 1.0, period);
+    1.0 - 2.0 * smoothstep(0.0, 0.8, parent.distance)
+}
+
+/// Self-similar clumping at every scale from cluster_size down
+fn fractal_clusters(params: &GrainParams, q: Vec2, period: Vec2) -> f32 {
+    domain_warp(q, params.swirl, cluster_octaves(params), period) * 1.5
+}
+
+/// Emulsion cells with their own grain density, softened towards the cell borders
+fn voronoi_clusters(params: &GrainParams, q: Vec2, period: Vec2) -> f32 {
+    let cell = voronoi(cluster_warp(params, q, period), 1.0, period);
+    let cell_density = hash21(wrap_cell(cell.cell_id, period)) * 2.0 - 1.0;
+    cell_density * (1.0 - 0.5 * smoothstep(0.3, 0.9, cell.distance))
+}
+
+/// Low-frequency density modulation so grains clump instead of spreading uniformly
+fn cluster_density(params: &GrainParams, p: Vec2, period: Vec2) -> f32 {
+    let scale = tile_scale(period, Vec2::splat(1.0 / params.cluster_size));
+    let q = p * scale;
+    let q_period = period * scale;
+
+    let c = match params.clustering {
+        CLUSTER_POISSON => poisson_clusters(params, q, q_period),
+        CLUSTER_FRACTAL => fractal_clusters(params, q, q_period),
+        CLUSTER_VORONOI => voronoi_clusters(params, q, q_period),
+        CLUSTER_HYBRID => {
+            // Cells broken up by fractal detail
+            let s = tile_scale(q_period, Vec2::splat(2.0));
+            0.6 * voronoi_clusters(params, q, q_period) + 0.4 * fractal_clusters(params, q * s, q_period * s)
+        }
+        _ => return 1.0,
+    };
+
+    // organic in [1, 2] scales how strongly clusters modulate density
+    (1.0 + 0.5 * c * params.organic).clamp(0.0, 2.0)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// COMPOSITING
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Blend signed grain onto an sRGB-encoded base value
+fn blend_grain(params: &GrainParams, base: f32, grain: f32) -> f32 {
+    // Grain as a layer centred on mid-grey, the neutral value for overlay and soft light
+    let layer = (0.5 + 0.5 * grain).clamp(0.0, 1.0);
+
+    match params.blend_mode {
+        BLEND_SOFT_LIGHT => {
+            // W3C soft light
+            if layer <= 0.5 {
+                return base - (1.0 - 2.0 * layer) * base * (1.0 - base);
+            }
+            let d = if base <= 0.25 { ((16.0 * base - 12.0) * base + 4.0) * base } else { base.sqrt() };
+            base + (2.0 * layer - 1.0) * (d - base)
+        }
+        BLEND_ADDITIVE => {
+            // Add in linear light so grain energy is preserved across the tonal range
+            linear_to_srgb((srgb_to_linear(base) + 0.5 * grain).clamp(0.0, 1.0))
+        }
+        _ => {
+            if base < 0.5 {
+                2.0 * base * layer
+            } else {
+                1.0 - 2.0 * (1.0 - base) * (1.0 - layer)
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// MAIN
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn channel_offset(c: usize) -> Vec2 {
+    Vec2::new(c as f32 * 53.7, c as f32 * 19.3)
+}
+
+fn shade_channel(params: &GrainParams, n: f32, density: f32, intensity: f32) -> f32 {
+    let value = apply_sharpness(params, n) * 0.5 + 0.5;
+    value * params.grain_amount * intensity * density * response_weight(params, value)
+}
+
+/// Signed grain for compositing; the response curve follows the plate luminance instead of the grain
+fn composite_channel(params: &GrainParams, base: f32, n: f32, density: f32, intensity: f32, weight: f32) -> f32 {
+    let grain = apply_sharpness(params, n) * params.grain_amount * intensity * density * weight;
+    blend_grain(params, base, grain)
+}
+
+/// Mirrors `boolean_pixel` in `grain.wgsl`. Boolean synthesis re-renders the plate from
+/// grains instead of blending grain onto it; standalone renders use a mid-grey plate
+fn boolean_pixel(
+    params: &GrainParams,
+    uv: Vec2,
+    coords: [u32; 2],
+    pixel: f32,
+    period: Vec2,
+    input: Option<[f32; 4]>,
+) -> [f32; 4] {
+    let base = match input {
+        Some(base) if params.use_input != 0 => base,
+        _ => [0.5, 0.5, 0.5, 1.0],
+    };
+    let noise_scale = noise_scale(params);
+
+    let mut color = base;
+    for c in 0..3 {
+        // Colour stocks render an independent disc layer per dye; monochrome shares one
+        let (v, intensity) = if params.is_color != 0 {
+            let s = tile_scale(period, Vec2::splat(1.0 / params.channel_size[c]));
+            let pc = uv * (noise_scale * s) + channel_offset(c);
+            let v = boolean_grain(params, pc, period * s, pixel * s.x, coords, base[c], c as u32);
+            (v, params.channel_intensity[c])
+        } else {
+            let p = uv * noise_scale;
+            (boolean_grain(params, p, period, pixel, coords, base[c], 0), 1.0)
+        };
+        color[c] = mix(base[c], v, (params.grain_amount * intensity).clamp(0.0, 1.0));
+    }
+
+    color
+}
+
+/// CPU equivalent of the `main` entry point in `grain.wgsl` for a single pixel.
+///
+/// `input` is the sRGB-encoded photograph pixel, used when `params.use_input` is set.
+pub fn shade_pixel(
+    params: &GrainParams,
+    coords: [u32; 2],
+    dimensions: [u32; 2],
+    input: Option<[f32; 4]>,
+) -> [f32; 4] {
+    seed_noise(params.seed);
+    set_noise_time(params.time);
+
+    let uv = Vec2::new(
+        coords[0] as f32 / dimensions[0] as f32,
+        coords[1] as f32 / dimensions[1] as f32,
+    );
+
+    let noise_scale = noise_scale(params);
+    // Tiling wraps the lattice after one output width and height
+    let period = if params.tileable != 0 { Vec2::splat(noise_scale) } else { Vec2::ZERO };
+    let p = uv * noise_scale;
+
+    if params.synthesis == SYNTHESIS_BOOLEAN {
+        let pixel = noise_scale / dimensions[0] as f32;
+        // Filter samples are hashed per pixel, so tiling wraps them along with the lattice
+        let sample_coords = if params.tileable != 0 { [coords[0] % dimensions[0], coords[1] % dimensions[1]] } else { coords };
+        return boolean_pixel(params, uv, sample_coords, pixel, period, input);
+    }
+
+    let density = cluster_density(params, p, period);
+    let shared_grain = grain_field(params, p, period);
+
+    // Monochrome stocks have a single silver layer; colour stocks three dye layers
+    let mut grain = [shared_grain; 3];
+    if params.is_color != 0 {
+        let mut own = [0.0; 3];
+        for (c, value) in own.iter_mut().enumerate() {
+            let s = tile_scale(period, Vec2::splat(1.0 / params.channel_size[c]));
+            let pc = uv * (noise_scale * s) + channel_offset(c);
+            *value = dye_cloud(params, pc, period * s, layer_softness(params, c));
+        }
+        grain = correlate_layers(params, shared_grain, own);
+    }
+    let intensity = if params.is_color != 0 {
+        [params.channel_intensity[0], params.channel_intensity[1], params.channel_intensity[2]]
+    } else {
+        [1.0; 3]
+    };
+
+    let mut color = [0.0, 0.0, 0.0, 1.0];
+    match input {
+        Some(base) if params.use_input != 0 => {
+            let luma = linear_to_srgb(luminance(
+                srgb_to_linear(base[0]),
+                srgb_to_linear(base[1]),
+                srgb_to_linear(base[2]),
+            ));
+            let weight = response_weight(params, luma);
+            for c in 0..3 {
+                color[c] = composite_channel(params, base[c], grain[c], density, intensity[c], weight);
+            }
+            color[3] = base[3];
+        }
+        _ => {
+            for c in 0..3 {
+                color[c] = shade_channel(params, grain[c], density, intensity[c]);
+            }
+        }
+    }
+
+    color
+}
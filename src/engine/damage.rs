@@ -0,0 +1,399 @@
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAINFORGE FILM DAMAGE
+// Procedural dust, hairs and scratches, plus the gate weave and exposure flicker
+// of a worn print. Everything is derived from the seed and the frame index, so a
+// frame always renders the same damage and sequences animate smoothly.
+// ═══════════════════════════════════════════════════════════════════════════
+
+use std::f32::consts::TAU;
+
+use image::Rgba32FImage;
+
+use crate::core::film_stock::{DamageParameters, DamagePolarity};
+use crate::utils::color::{linear_to_srgb, srgb_to_linear};
+use crate::utils::math::{mix, mix64, smoothstep, SPLITMIX64_GAMMA};
+
+/// Frame pixels per dust speck at full dust
+const DUST_AREA: f32 = 20_000.0;
+/// Hairs per frame at full hairs
+const MAX_HAIRS: f32 = 2.0;
+/// Scratches running through the sequence at full scratches
+const MAX_SCRATCHES: f32 = 6.0;
+/// Frames a scratch typically stays visible or hidden for
+const SCRATCH_LIFETIME: f32 = 24.0;
+/// Vertical weave relative to horizontal; pull-down registration holds the film better vertically
+const VERTICAL_WEAVE: f32 = 0.5;
+
+// Independent random streams, so e.g. more dust does not move the hairs
+const STREAM_DUST: u64 = 1;
+const STREAM_HAIRS: u64 = 2;
+const STREAM_WEAVE_X: u64 = 4;
+const STREAM_WEAVE_Y: u64 = 5;
+const STREAM_ROTATION: u64 = 6;
+const STREAM_FLICKER: u64 = 7;
+/// Scratch `n` uses the block of 256 streams from `STREAM_SCRATCHES + (n << 8)`,
+/// clear of the single streams above
+const STREAM_SCRATCHES: u64 = 0x100;
+
+/// Per-frame damage for a sequence of `width` x `height` frames
+#[derive(Debug, Clone)]
+pub struct DamageGenerator {
+    damage: DamageParameters,
+    seed: u64,
+    width: u32,
+    height: u32,
+}
+
+impl DamageGenerator {
+    pub fn new(damage: &DamageParameters, seed: u64, width: u32, height: u32) -> Self {
+        Self { damage: damage.clone(), seed, width, height }
+    }
+
+    /// Damage for the `index`th frame of the sequence (counting from 0)
+    pub fn frame(&self, index: u32) -> DamageFrame {
+        let mut coverage = vec![0.0; self.width as usize * self.height as usize];
+        let mut canvas = Canvas { coverage: &mut coverage, width: self.width, height: self.height };
+        self.dust(&mut canvas, index);
+        self.hairs(&mut canvas, index);
+        self.scratches(&mut canvas, index);
+
+        DamageFrame {
+            weave: self.gate_weave(index),
+            exposure: self.exposure(index),
+            polarity: self.damage.polarity,
+            coverage,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Where the film sits in the gate for a frame: a wander of up to `gate_weave` pixels
+    /// that is smooth from frame to frame, with a little jitter on top
+    pub fn gate_weave(&self, index: u32) -> GateWeave {
+        let amplitude = self.damage.gate_weave.get();
+        if amplitude == 0.0 {
+            return GateWeave::default();
+        }
+        let wander = |stream| {
+            let t = index as f32;
+            0.7 * noise1(self.seed, stream, t * 0.15) + 0.3 * noise1(self.seed, stream, t * 0.9)
+        };
+        // Small enough that the corners move no more than the weave itself
+        let half_diagonal = 0.5 * (self.width as f32).hypot(self.height as f32);
+        GateWeave {
+            offset: [amplitude * wander(STREAM_WEAVE_X), VERTICAL_WEAVE * amplitude * wander(STREAM_WEAVE_Y)],
+            rotation: VERTICAL_WEAVE * amplitude * wander(STREAM_ROTATION) / half_diagonal.max(1.0),
+        }
+    }
+
+    /// Exposure multiplier for a frame; `flicker` is the largest change in stops
+    pub fn exposure(&self, index: u32) -> f32 {
+        let flicker = self.damage.flicker.get();
+        if flicker == 0.0 {
+            return 1.0;
+        }
+        let t = index as f32;
+        let jitter = Rng::new(self.seed, STREAM_FLICKER, index).range(-1.0, 1.0);
+        let stops = flicker * (0.5 * noise1(self.seed, STREAM_FLICKER, t * 0.4) + 0.5 * jitter);
+        stops.exp2()
+    }
+
+    /// Specks of dirt, new every frame
+    fn dust(&self, canvas: &mut Canvas, index: u32) {
+        let mut rng = Rng::new(self.seed, STREAM_DUST, index);
+        let area = self.width as f32 * self.height as f32;
+        let count = rng.count(self.damage.dust.get() * area / DUST_AREA);
+        let size = self.damage.dust_size.get();
+
+        for _ in 0..count {
+            let center = [rng.range(0.0, self.width as f32), rng.range(0.0, self.height as f32)];
+            let radius = 0.5 * size * rng.range(0.3, 1.0);
+            let opacity = rng.range(0.5, 1.0);
+            // Lumpy outline rather than a perfect disc
+            let wobble = rng.range(0.0, 0.35);
+            let phase = rng.range(0.0, TAU);
+
+            let reach = radius * (1.0 + wobble) + 1.0;
+            canvas.stamp(center, [reach, reach], |dx, dy| {
+                let edge = radius * (1.0 + wobble * (3.0 * dy.atan2(dx) + phase).sin());
+                opacity * (1.0 - smoothstep(edge - 0.75, edge + 0.75, dx.hypot(dy)))
+            });
+        }
+    }
+
+    /// Curling hairs, new every frame
+    fn hairs(&self, canvas: &mut Canvas, index: u32) {
+        const SEGMENTS: usize = 8;
+
+        let mut rng = Rng::new(self.seed, STREAM_HAIRS, index);
+        let count = rng.count(self.damage.hairs.get() * MAX_HAIRS);
+        let long_edge = self.width.max(self.height) as f32;
+
+        for _ in 0..count {
+            let mut point = [rng.range(0.0, self.width as f32), rng.range(0.0, self.height as f32)];
+            let mut angle = rng.range(0.0, TAU);
+            let step = long_edge * rng.range(0.04, 0.15) / SEGMENTS as f32;
+            let thickness = 0.3 * self.damage.dust_size.get() * rng.range(0.6, 1.4);
+            let opacity = rng.range(0.6, 1.0);
+            let curl = rng.range(-0.4, 0.4);
+
+            for _ in 0..SEGMENTS {
+                angle += curl + rng.range(-0.3, 0.3);
+                let next = [point[0] + step * angle.cos(), point[1] + step * angle.sin()];
+                let half = 0.5 * thickness;
+                let reach = [0.5 * (next[0] - point[0]).abs() + half + 1.0, 0.5 * (next[1] - point[1]).abs() + half + 1.0];
+                let mid = [0.5 * (point[0] + next[0]), 0.5 * (point[1] + next[1])];
+                let (a, b) = ([point[0] - mid[0], point[1] - mid[1]], [next[0] - mid[0], next[1] - mid[1]]);
+                canvas.stamp(mid, reach, |dx, dy| {
+                    let distance = segment_distance([dx, dy], a, b);
+                    opacity * (1.0 - smoothstep(half - 0.5, half + 0.5, distance))
+                });
+                point = next;
+            }
+        }
+    }
+
+    /// Vertical scratches that persist through the sequence, wandering sideways and
+    /// coming and going as the print was run through different projectors
+    fn scratches(&self, canvas: &mut Canvas, index: u32) {
+        let count = (self.damage.scratches.get() * MAX_SCRATCHES).round() as u64;
+        let t = index as f32;
+
+        for scratch in 0..count {
+            let stream = STREAM_SCRATCHES + (scratch << 8);
+            if noise1(self.seed, stream, t / SCRATCH_LIFETIME) < -0.25 {
+                continue;
+            }
+            // Fixed for the sequence, so the same scratch returns in the same place
+            let mut rng = Rng::new(self.seed, stream, 0);
+            let base = rng.range(0.05, 0.95) * self.width as f32;
+            let wander = rng.range(0.001, 0.004) * self.width as f32;
+            let half = 0.5 * self.damage.dust_size.get() * rng.range(0.15, 0.4);
+            let opacity = rng.range(0.3, 0.8);
+
+            let x = base + wander * noise1(self.seed, stream + 1, t * 0.15);
+            for y in 0..self.height {
+                let along = y as f32 / 60.0;
+                // The film advances between frames, so the pattern along the scratch changes
+                let center = x + 0.5 * noise1(self.seed, stream + 2, along + t * 37.0);
+                let strength = opacity * (0.55 + 0.45 * noise1(self.seed, stream + 3, 1.5 * along + t * 37.0));
+                canvas.stamp_row(y, center, half + 1.0, |dx| {
+                    strength * (1.0 - smoothstep(half - 0.5, half + 0.5, dx.abs()))
+                });
+            }
+        }
+    }
+}
+
+/// Sub-pixel movement of the film in the gate
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GateWeave {
+    /// Translation in pixels
+    pub offset: [f32; 2],
+    /// Rotation about the frame centre, in radians
+    pub rotation: f32,
+}
+
+impl GateWeave {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The damage of one frame: marks on the film, and how the frame moved and flickered
+#[derive(Debug, Clone)]
+pub struct DamageFrame {
+    pub weave: GateWeave,
+    /// Exposure multiplier in linear light
+    pub exposure: f32,
+    pub polarity: DamagePolarity,
+    // Opacity of dust, hairs and scratches per pixel, row-major
+    coverage: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
+impl DamageFrame {
+    /// Opacity of the marks per pixel, row-major
+    pub fn coverage(&self) -> &[f32] {
+        &self.coverage
+    }
+
+    /// True when the frame would come out unchanged
+    pub fn is_clean(&self) -> bool {
+        self.weave.is_identity() && self.exposure == 1.0 && self.coverage.iter().all(|&c| c == 0.0)
+    }
+
+    /// The marks on their own, white or black with the coverage as straight alpha
+    pub fn layer(&self) -> Rgba32FImage {
+        let ink = self.ink();
+        Rgba32FImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.coverage[(y * self.width + x) as usize];
+            image::Rgba([ink, ink, ink, c])
+        })
+    }
+
+    /// Weave and flicker `image`, then print the marks over it
+    pub fn composite(&self, image: &Rgba32FImage) -> Rgba32FImage {
+        let mut image = self.apply_motion(image);
+        self.overlay(&mut image);
+        image
+    }
+
+    /// Move `image` by the gate weave and scale its exposure; the marks are left out,
+    /// for when they are exported as their own layer
+    pub fn apply_motion(&self, image: &Rgba32FImage) -> Rgba32FImage {
+        let (width, height) = image.dimensions();
+        let center = [0.5 * width as f32, 0.5 * height as f32];
+        let (sin, cos) = (-self.weave.rotation).sin_cos();
+        let exposure = self.exposure;
+
+        map_rows(width, height, |x, y| {
+            // Inverse transform: where this output pixel was before the weave
+            let p = [x as f32 + 0.5 - center[0] - self.weave.offset[0], y as f32 + 0.5 - center[1] - self.weave.offset[1]];
+            let source = [cos * p[0] - sin * p[1] + center[0], sin * p[0] + cos * p[1] + center[1]];
+            let mut pixel = if self.weave.is_identity() { image.get_pixel(x, y).0 } else { bilinear(image, source) };
+            if exposure != 1.0 {
+                for c in &mut pixel[..3] {
+                    *c = linear_to_srgb(srgb_to_linear(*c) * exposure);
+                }
+            }
+            pixel
+        })
+    }
+
+    /// Print the marks over `image`, which must be the frame size
+    pub fn overlay(&self, image: &mut Rgba32FImage) {
+        let ink = self.ink();
+        for (pixel, &c) in image.pixels_mut().zip(&self.coverage) {
+            if c > 0.0 {
+                for channel in &mut pixel.0[..3] {
+                    *channel = mix(*channel, ink, c);
+                }
+                pixel.0[3] += c * (1.0 - pixel.0[3]);
+            }
+        }
+    }
+
+    fn ink(&self) -> f32 {
+        match self.polarity {
+            DamagePolarity::Negative => 1.0,
+            DamagePolarity::Positive => 0.0,
+        }
+    }
+}
+
+/// Coverage buffer the marks are drawn into; overlapping marks keep the densest
+struct Canvas<'a> {
+    coverage: &'a mut [f32],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    /// Draw `shade(dx, dy)` over the pixels within `reach` of `center`, where (dx, dy)
+    /// is the pixel centre relative to `center`
+    fn stamp(&mut self, center: [f32; 2], reach: [f32; 2], shade: impl Fn(f32, f32) -> f32) {
+        let y0 = (center[1] - reach[1]).floor().max(0.0) as u32;
+        let y1 = ((center[1] + reach[1]).ceil().max(0.0) as u32).min(self.height);
+        for y in y0..y1 {
+            let dy = y as f32 + 0.5 - center[1];
+            self.stamp_row(y, center[0], reach[0], |dx| shade(dx, dy));
+        }
+    }
+
+    fn stamp_row(&mut self, y: u32, center: f32, reach: f32, shade: impl Fn(f32) -> f32) {
+        let x0 = (center - reach).floor().max(0.0) as u32;
+        let x1 = ((center + reach).ceil().max(0.0) as u32).min(self.width);
+        let row = (y * self.width) as usize;
+        for x in x0..x1 {
+            let c = &mut self.coverage[row + x as usize];
+            *c = c.max(shade(x as f32 + 0.5 - center).clamp(0.0, 1.0));
+        }
+    }
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let length2 = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length2 > 0.0 { ((ap[0] * ab[0] + ap[1] * ab[1]) / length2).clamp(0.0, 1.0) } else { 0.0 };
+    (ap[0] - t * ab[0]).hypot(ap[1] - t * ab[1])
+}
+
+/// Bilinear sample at a continuous position (pixel centres at +0.5), clamped to the edge
+fn bilinear(image: &Rgba32FImage, position: [f32; 2]) -> [f32; 4] {
+    let (width, height) = image.dimensions();
+    let x = (position[0] - 0.5).clamp(0.0, (width - 1) as f32);
+    let y = (position[1] - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let (p00, p10) = (image.get_pixel(x0, y0).0, image.get_pixel(x1, y0).0);
+    let (p01, p11) = (image.get_pixel(x0, y1).0, image.get_pixel(x1, y1).0);
+    std::array::from_fn(|c| mix(mix(p00[c], p10[c], fx), mix(p01[c], p11[c], fx), fy))
+}
+
+/// A new image computed pixel by pixel across threads
+fn map_rows(width: u32, height: u32, shade: impl Fn(u32, u32) -> [f32; 4] + Sync) -> Rgba32FImage {
+    let mut pixels = vec![0.0; width as usize * height as usize * 4];
+    let row_len = width as usize * 4;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_chunk = (height as usize).div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        for (chunk_index, chunk) in pixels.chunks_mut((rows_per_chunk * row_len).max(1)).enumerate() {
+            let shade = &shade;
+            scope.spawn(move || {
+                let first_row = chunk_index * rows_per_chunk;
+                for (row_offset, row) in chunk.chunks_exact_mut(row_len).enumerate() {
+                    let y = (first_row + row_offset) as u32;
+                    for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                        pixel.copy_from_slice(&shade(x as u32, y));
+                    }
+                }
+            });
+        }
+    });
+
+    Rgba32FImage::from_raw(width, height, pixels).expect("buffer sized for the image")
+}
+
+fn hash(seed: u64, stream: u64, index: i64) -> u64 {
+    mix64(seed ^ mix64(stream.wrapping_mul(SPLITMIX64_GAMMA) ^ mix64(index as u64)))
+}
+
+/// Smooth 1D value noise in [-1, 1] with features one unit apart
+fn noise1(seed: u64, stream: u64, t: f32) -> f32 {
+    let cell = t.floor();
+    let value = |i: i64| (hash(seed, stream, i) >> 40) as f32 / (1u64 << 23) as f32 - 1.0;
+    let a = value(cell as i64);
+    let b = value(cell as i64 + 1);
+    mix(a, b, smoothstep(0.0, 1.0, t - cell))
+}
+
+/// SplitMix64 sequence for placing the marks of one frame
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64, stream: u64, frame: u32) -> Self {
+        Self(hash(seed, stream, frame as i64))
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(SPLITMIX64_GAMMA);
+        (mix64(self.0) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// A whole number of marks averaging `expected`
+    fn count(&mut self, expected: f32) -> u32 {
+        let whole = expected.floor();
+        whole as u32 + u32::from(self.next_f32() < expected - whole)
+    }
+}
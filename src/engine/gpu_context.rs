@@ -20,4 +20,32 @@ impl GpuContext {
             format: wgpu_state.target_format,
         })
     }
+
+    /// Create a standalone device for headless rendering (CLI, tests, render farm nodes)
+    pub fn new_headless() -> Result<Self, GrainError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| GrainError::GpuInit("No GPU adapter available".to_string()))?;
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("GrainForge Headless Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::default(),
+            },
+            None,
+        ))
+        .map_err(|e| GrainError::GpuInit(e.to_string()))?;
+
+        Ok(Self {
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+            format: TextureFormat::Rgba8Unorm,
+        })
+    }
 }
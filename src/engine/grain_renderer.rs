@@ -5,12 +5,83 @@ use wgpu::{
     TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
 };
 use bytemuck::{Pod, Zeroable};
-use std::sync::Arc;
+use image::{Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
 
+use crate::core::film_stock::{ClusteringType, CrystalType, FilmStock, GrainSynthesis, PostProcessChain, ResponseMode};
 use crate::engine::compute_pipeline::GrainComputePipeline;
+use crate::engine::output_format::OutputFormat;
+use crate::engine::readback;
+use crate::engine::render_pipeline::RenderPipeline;
+use crate::engine::shaders::ShaderLibrary;
+use crate::engine::texture_manager;
 use crate::core::error::GrainError;
 
-/// GPU parameters passed to the compute shader
+// Shader-side enum identifiers; must match the constants in grain.wgsl
+pub const CRYSTAL_CUBIC: u32 = 0;
+pub const CRYSTAL_TABULAR: u32 = 1;
+pub const CRYSTAL_CORE_SHELL: u32 = 2;
+pub const CRYSTAL_CELLULAR: u32 = 3;
+pub const CRYSTAL_NEEDLE: u32 = 4;
+pub const CRYSTAL_CUSTOM: u32 = 5;
+
+pub const SYNTHESIS_NOISE: u32 = 0;
+pub const SYNTHESIS_PARTICLE: u32 = 1;
+pub const SYNTHESIS_BOOLEAN: u32 = 2;
+
+/// Monte Carlo samples per pixel for Boolean-model synthesis
+pub const DEFAULT_BOOLEAN_SAMPLES: u32 = 32;
+
+pub const RESPONSE_NEGATIVE: u32 = 0;
+pub const RESPONSE_PRINT: u32 = 1;
+pub const RESPONSE_REVERSAL: u32 = 2;
+pub const RESPONSE_CUSTOM: u32 = 3;
+
+pub const CLUSTER_NONE: u32 = 0;
+pub const CLUSTER_POISSON: u32 = 1;
+pub const CLUSTER_FRACTAL: u32 = 2;
+pub const CLUSTER_VORONOI: u32 = 3;
+pub const CLUSTER_HYBRID: u32 = 4;
+
+pub const BLEND_OVERLAY: u32 = 0;
+pub const BLEND_SOFT_LIGHT: u32 = 1;
+pub const BLEND_ADDITIVE: u32 = 2;
+
+/// How grain is composited onto an input photograph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    #[default]
+    Overlay,
+    SoftLight,
+    /// Additive in linear light
+    Additive,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 3] = [Self::Overlay, Self::SoftLight, Self::Additive];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Overlay => "Overlay",
+            Self::SoftLight => "Soft Light",
+            Self::Additive => "Additive (Linear)",
+        }
+    }
+
+    pub fn shader_id(&self) -> u32 {
+        match self {
+            Self::Overlay => BLEND_OVERLAY,
+            Self::SoftLight => BLEND_SOFT_LIGHT,
+            Self::Additive => BLEND_ADDITIVE,
+        }
+    }
+}
+
+/// GPU parameters passed to the compute shader.
+///
+/// Scalars are grouped in 16-byte rows so the layout matches the WGSL uniform
+/// block without implicit padding.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct GrainParams {
@@ -18,38 +89,211 @@ pub struct GrainParams {
     pub grain_size: f32,
     pub width: f32,
     pub height: f32,
-    pub seed: f32,
-    pub _padding: [f32; 3], // Align to 32 bytes
+
+    pub size_variation: f32,
+    pub sharpness: f32,
+    pub crystal_type: u32,
+    pub crystal_sides: u32,
+
+    pub response_shadows: f32,
+    pub response_midtones: f32,
+    pub response_highlights: f32,
+    pub response_mode: u32,
+
+    pub is_color: u32,
+    pub correlation: f32,
+    pub dye_softness: f32,
+    pub synthesis: u32,
+
+    pub channel_intensity: [f32; 4],
+    pub channel_size: [f32; 4],
+
+    pub clustering: u32,
+    pub cluster_size: f32,
+    pub organic: f32,
+    pub detail: f32,
+
+    pub swirl: f32,
+    pub blend_mode: u32,
+    pub use_input: u32, // Set by the renderer when an input image is bound
+    pub samples: u32, // Monte Carlo samples per pixel (Boolean synthesis)
+
+    pub seed: [u32; 2], // Low and high words of the 64-bit seed; see `with_seed`
+    pub tile_origin: [u32; 2], // Top-left of this render within a tiled output; see `render_tile`
+
+    pub time: f32, // Noise time in re-rolls of each lattice point, advanced by sequences
+    pub tileable: u32,
+    pub _padding0: u32,
+    pub _padding1: u32,
 }
 
 impl Default for GrainParams {
     fn default() -> Self {
+        Self::from_film_stock(&FilmStock::default(), 512, 512)
+    }
+}
+
+impl GrainParams {
+    /// Build the uniform block for a film stock rendered at the given size
+    pub fn from_film_stock(stock: &FilmStock, width: u32, height: u32) -> Self {
+        let (crystal_type, crystal_sides) = match stock.grain.crystal_type {
+            CrystalType::Cubic => (CRYSTAL_CUBIC, 0),
+            CrystalType::Tabular => (CRYSTAL_TABULAR, 0),
+            CrystalType::CoreShell => (CRYSTAL_CORE_SHELL, 0),
+            CrystalType::Cellular => (CRYSTAL_CELLULAR, 0),
+            CrystalType::Needle => (CRYSTAL_NEEDLE, 0),
+            CrystalType::Custom { sides } => (CRYSTAL_CUSTOM, sides),
+        };
+
+        let synthesis = match stock.grain.synthesis {
+            GrainSynthesis::Noise => SYNTHESIS_NOISE,
+            GrainSynthesis::Particle => SYNTHESIS_PARTICLE,
+            GrainSynthesis::Boolean => SYNTHESIS_BOOLEAN,
+        };
+
+        let response_mode = match stock.response.mode {
+            ResponseMode::Negative => RESPONSE_NEGATIVE,
+            ResponseMode::Print => RESPONSE_PRINT,
+            ResponseMode::Reversal => RESPONSE_REVERSAL,
+            ResponseMode::Custom => RESPONSE_CUSTOM,
+        };
+
+        let clustering = match stock.texture.clustering {
+            ClusteringType::None => CLUSTER_NONE,
+            ClusteringType::Poisson => CLUSTER_POISSON,
+            ClusteringType::Fractal => CLUSTER_FRACTAL,
+            ClusteringType::Voronoi => CLUSTER_VORONOI,
+            ClusteringType::Hybrid => CLUSTER_HYBRID,
+        };
+
+        let color = &stock.color;
+
         Self {
-            grain_amount: 0.5,
-            grain_size: 1.0,
-            width: 512.0,
-            height: 512.0,
-            seed: 0.0,
-            _padding: [0.0; 3],
+            grain_amount: stock.grain.intensity.get(),
+            grain_size: stock.grain.size.get(),
+            width: width as f32,
+            height: height as f32,
+
+            size_variation: stock.grain.size_variation.get(),
+            sharpness: stock.grain.sharpness.get(),
+            crystal_type,
+            crystal_sides,
+
+            response_shadows: stock.response.shadows.get(),
+            response_midtones: stock.response.midtones.get(),
+            response_highlights: stock.response.highlights.get(),
+            response_mode,
+
+            is_color: color.is_color as u32,
+            correlation: color.correlation.get(),
+            dye_softness: color.dye_softness.get(),
+            synthesis,
+
+            channel_intensity: [
+                color.channel_intensity[0].get(),
+                color.channel_intensity[1].get(),
+                color.channel_intensity[2].get(),
+                0.0,
+            ],
+            channel_size: [
+                color.channel_size[0].get(),
+                color.channel_size[1].get(),
+                color.channel_size[2].get(),
+                0.0,
+            ],
+
+            clustering,
+            cluster_size: stock.texture.cluster_size.get(),
+            organic: stock.texture.organic.get(),
+            detail: stock.texture.detail.get(),
+
+            swirl: stock.texture.swirl.get(),
+            blend_mode: BlendMode::default().shader_id(),
+            use_input: 0,
+            samples: DEFAULT_BOOLEAN_SAMPLES,
+
+            seed: split_seed(stock.grain.seed),
+            tile_origin: [0, 0],
+
+            time: 0.0,
+            tileable: 0,
+            _padding0: 0,
+            _padding1: 0,
         }
     }
+
+    /// Composite with the given blend mode when an input image is bound
+    pub fn with_blend_mode(mut self, mode: BlendMode) -> Self {
+        self.blend_mode = mode.shader_id();
+        self
+    }
+
+    /// Wrap every noise lattice at the output edges so the texture tiles seamlessly.
+    /// Grain size snaps to a whole number of lattice cells across the output.
+    pub fn with_tileable(mut self, tileable: bool) -> Self {
+        self.tileable = tileable as u32;
+        self
+    }
+
+    /// Quality of Boolean-model synthesis; render time grows linearly with samples
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Replace the stock's seed. Every noise hash is keyed on all 64 bits, so any two
+    /// seeds give unrelated grain.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = split_seed(seed);
+        self
+    }
+
+    pub fn seed_u64(&self) -> u64 {
+        self.seed[0] as u64 | (self.seed[1] as u64) << 32
+    }
+}
+
+/// WGSL has no 64-bit integers, so seeds travel as (low, high) words
+fn split_seed(seed: u64) -> [u32; 2] {
+    [seed as u32, (seed >> 32) as u32]
 }
 
 /// Renders grain texture using the compute pipeline
 pub struct GrainRenderer {
     pipeline: GrainComputePipeline,
     output_texture: Texture,
+    input_texture: Option<Texture>,
+    placeholder_input: Texture,
     bind_group_layout: BindGroupLayout,
     bind_group: BindGroup,
     params_buffer: Buffer,
+    // Created on first use, since most renders have no post-processing
+    post_process: Option<RenderPipeline>,
+    // Sources the pipelines were built from, so the post-process stage matches the grain shader
+    library: ShaderLibrary,
+    format: OutputFormat,
     width: u32,
     height: u32,
 }
 
 impl GrainRenderer {
     pub fn new(device: &Device, width: u32, height: u32) -> Result<Self, GrainError> {
-        let pipeline = GrainComputePipeline::new(device)?;
+        Self::with_format(device, width, height, OutputFormat::default())
+    }
+
+    /// Renderer with a higher precision render target, for linear-light compositing and deep exports
+    pub fn with_format(device: &Device, width: u32, height: u32, format: OutputFormat) -> Result<Self, GrainError> {
+        Self::with_shaders(device, width, height, format, &ShaderLibrary::builtin())
+    }
 
+    /// Renderer compiled from `library` rather than the built-in shaders
+    pub fn with_shaders(
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: OutputFormat,
+        library: &ShaderLibrary,
+    ) -> Result<Self, GrainError> {
         // Create output texture
         let output_texture = device.create_texture(&TextureDescriptor {
             label: Some("Grain Output Texture"),
@@ -57,7 +301,7 @@ impl GrainRenderer {
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
+            format: format.texture_format(),
             usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
             view_formats: &[],
         });
@@ -79,7 +323,7 @@ impl GrainRenderer {
                     visibility: ShaderStages::COMPUTE,
                     ty: BindingType::StorageTexture {
                         access: StorageTextureAccess::WriteOnly,
-                        format: TextureFormat::Rgba8Unorm,
+                        format: format.texture_format(),
                         view_dimension: wgpu::TextureViewDimension::D2,
                     },
                     count: None,
@@ -94,41 +338,125 @@ impl GrainRenderer {
                     },
                     count: None,
                 },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
             ],
         });
 
-        let texture_view = output_texture.create_view(&TextureViewDescriptor::default());
+        let pipeline = GrainComputePipeline::from_library(device, &bind_group_layout, format, library)?;
 
-        let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Grain Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: params_buffer.as_entire_binding(),
-                },
-            ],
+        // The input binding must always be filled; a 1x1 texture stands in when no photo is loaded
+        let placeholder_input = device.create_texture(&TextureDescriptor {
+            label: Some("Grain Placeholder Input"),
+            size: Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba32Float,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
         });
 
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &output_texture,
+            &placeholder_input,
+            &params_buffer,
+        );
+
         Ok(Self {
             pipeline,
             output_texture,
+            input_texture: None,
+            placeholder_input,
             bind_group_layout,
             bind_group,
             params_buffer,
+            post_process: None,
+            library: library.clone(),
+            format,
             width,
             height,
         })
     }
 
+    /// Bind a photograph to composite the grain onto; it must match the output size
+    pub fn set_input_image(&mut self, device: &Device, queue: &Queue, image: &Rgba32FImage) -> Result<(), GrainError> {
+        if image.dimensions() != (self.width, self.height) {
+            return Err(GrainError::InvalidParameter {
+                name: "input_image".to_string(),
+                reason: format!(
+                    "Image is {}x{} but the renderer is {}x{}",
+                    image.width(), image.height(), self.width, self.height
+                ),
+            });
+        }
+
+        let texture = texture_manager::upload_rgba32f(device, queue, image, "Grain Input Texture");
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.output_texture,
+            &texture,
+            &self.params_buffer,
+        );
+        self.input_texture = Some(texture);
+        Ok(())
+    }
+
+    /// Go back to rendering a standalone grain texture
+    pub fn clear_input(&mut self, device: &Device) {
+        self.input_texture = None;
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.output_texture,
+            &self.placeholder_input,
+            &self.params_buffer,
+        );
+    }
+
+    /// Recompile the grain and post-process pipelines from edited shaders. On error the current
+    /// pipelines are kept, so the renderer keeps working with the last shaders that compiled.
+    pub fn reload_shaders(&mut self, device: &Device, library: &ShaderLibrary) -> Result<(), GrainError> {
+        let pipeline = GrainComputePipeline::from_library(device, &self.bind_group_layout, self.format, library)?;
+        let post_process = match self.post_process {
+            Some(_) => Some(RenderPipeline::from_library(device, self.width, self.height, self.format, library)?),
+            None => None,
+        };
+        self.pipeline = pipeline;
+        self.post_process = post_process;
+        self.library = library.clone();
+        Ok(())
+    }
+
+    pub fn has_input(&self) -> bool {
+        self.input_texture.is_some()
+    }
+
     /// Render grain with the given parameters
     pub fn render(&self, device: &Device, queue: &Queue, params: &GrainParams) {
+        let params = GrainParams { width: self.width as f32, height: self.height as f32, ..*params };
+        self.render_tile(device, queue, &params, [0, 0]);
+    }
+
+    /// Render the region of a larger output whose top-left pixel is `origin`.
+    ///
+    /// `params.width` and `params.height` give the full output size, so every tile samples
+    /// the same noise coordinates a single render would. An input image covers just this region.
+    pub fn render_tile(&self, device: &Device, queue: &Queue, params: &GrainParams, origin: [u32; 2]) {
         // Update params buffer
-        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[*params]));
+        let params = GrainParams { use_input: self.has_input() as u32, tile_origin: origin, ..*params };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
 
         // Create command encoder
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
@@ -145,19 +473,73 @@ impl GrainRenderer {
             compute_pass.set_bind_group(0, &self.bind_group, &[]);
 
             // Dispatch: workgroup size is 8x8, so we need ceil(width/8) x ceil(height/8)
-            let workgroups_x = (self.width + 7) / 8;
-            let workgroups_y = (self.height + 7) / 8;
+            let workgroups_x = self.width.div_ceil(8);
+            let workgroups_y = self.height.div_ceil(8);
             compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
         }
 
         queue.submit(std::iter::once(encoder.finish()));
     }
 
+    /// Run a post-process chain over the last render
+    pub fn post_process(&mut self, device: &Device, queue: &Queue, chain: &PostProcessChain) -> Result<(), GrainError> {
+        self.post_process_tile(device, queue, chain, [self.width, self.height])
+    }
+
+    /// Run a post-process chain over a tile; pixels past `valid` lie beyond the output
+    /// edge and are never sampled
+    pub fn post_process_tile(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        chain: &PostProcessChain,
+        valid: [u32; 2],
+    ) -> Result<(), GrainError> {
+        if chain.is_empty() {
+            return Ok(());
+        }
+        let stage = match &mut self.post_process {
+            Some(stage) => stage,
+            slot => slot.insert(RenderPipeline::from_library(device, self.width, self.height, self.format, &self.library)?),
+        };
+        stage.apply(device, queue, &self.output_texture, chain, valid);
+        Ok(())
+    }
+
     /// Get the output texture for display
     pub fn output_texture(&self) -> &Texture {
         &self.output_texture
     }
 
+    /// Copy the last render back to the CPU as tightly packed pixels in the renderer's
+    /// [`OutputFormat`] (blocks until done)
+    pub fn read_pixels(&self, device: &Device, queue: &Queue) -> Result<Vec<u8>, GrainError> {
+        readback::read_texture(device, queue, &self.output_texture)
+    }
+
+    /// Async variant of [`Self::read_pixels`]; resolves once the device has been polled
+    pub async fn read_pixels_async(&self, device: &Device, queue: &Queue) -> Result<Vec<u8>, GrainError> {
+        readback::read_texture_async(device, queue, &self.output_texture).await
+    }
+
+    /// Copy the last render back to the CPU as an 8-bit image, ready for `core::export`
+    pub fn read_image(&self, device: &Device, queue: &Queue) -> Result<RgbaImage, GrainError> {
+        let pixels = self.read_pixels(device, queue)?;
+        self.format.to_rgba8_image(self.width, self.height, pixels)
+            .ok_or_else(|| GrainError::Readback("Pixel buffer does not match texture size".to_string()))
+    }
+
+    /// Copy the last render back to the CPU at full precision, for deep exports
+    pub fn read_image_f32(&self, device: &Device, queue: &Queue) -> Result<Rgba32FImage, GrainError> {
+        let pixels = self.read_pixels(device, queue)?;
+        self.format.to_rgba32f_image(self.width, self.height, &pixels)
+            .ok_or_else(|| GrainError::Readback("Pixel buffer does not match texture size".to_string()))
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -166,3 +548,33 @@ impl GrainRenderer {
         self.height
     }
 }
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    output_texture: &Texture,
+    input_texture: &Texture,
+    params_buffer: &Buffer,
+) -> BindGroup {
+    let output_view = output_texture.create_view(&TextureViewDescriptor::default());
+    let input_view = input_texture.create_view(&TextureViewDescriptor::default());
+
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Grain Bind Group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&output_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: params_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&input_view),
+            },
+        ],
+    })
+}
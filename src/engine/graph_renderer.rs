@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use image::Rgba32FImage;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BindingType, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, Device,
+    Extent3d, Queue, ShaderStages, StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+    TextureUsages, TextureViewDescriptor,
+};
+
+use crate::core::error::GrainError;
+use crate::engine::compute_pipeline::GrainComputePipeline;
+use crate::engine::output_format::OutputFormat;
+use crate::engine::readback;
+use crate::engine::shaders::ShaderLibrary;
+use crate::nodes::codegen;
+use crate::nodes::node_graph::NodeGraph;
+use crate::nodes::node_types::NodeRegistry;
+
+/// Uniforms of a generated graph shader, matching `GraphUniforms` in the codegen prelude
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GraphUniforms {
+    size: [u32; 2],
+    tile_origin: [u32; 2],
+}
+
+/// Renders node graphs on the GPU, one fused dispatch per frame.
+///
+/// Compiled pipelines are kept by [structure hash](codegen::structure_hash): moving a
+/// slider only uploads new parameter values, and switching back to an earlier wiring of the
+/// graph reuses its pipeline.
+pub struct GraphRenderer {
+    output_texture: Texture,
+    bind_group_layout: BindGroupLayout,
+    uniform_buffer: Buffer,
+    params_buffer: Buffer,
+    pipelines: HashMap<u64, GrainComputePipeline>,
+    library: ShaderLibrary,
+    format: OutputFormat,
+    width: u32,
+    height: u32,
+}
+
+impl GraphRenderer {
+    pub fn new(device: &Device, width: u32, height: u32, format: OutputFormat) -> Self {
+        let output_texture = device.create_texture(&TextureDescriptor {
+            label: Some("Graph Output Texture"),
+            size: Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: format.texture_format(),
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Graph Uniform Buffer"),
+            size: std::mem::size_of::<GraphUniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Graph Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: format.texture_format(),
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        Self {
+            output_texture,
+            bind_group_layout,
+            uniform_buffer,
+            params_buffer: params_buffer(device, 1),
+            pipelines: HashMap::new(),
+            library: ShaderLibrary::builtin(),
+            format,
+            width,
+            height,
+        }
+    }
+
+    /// Render `graph`, compiling its shader first unless a graph of the same structure has
+    /// been rendered before
+    pub fn render(&mut self, device: &Device, queue: &Queue, graph: &NodeGraph, registry: &NodeRegistry) -> Result<(), GrainError> {
+        self.render_tile(device, queue, graph, registry, [self.width, self.height], [0, 0])
+    }
+
+    /// Render the region of a `size` output whose top-left pixel is `origin`, so tiles of a
+    /// large render sample the graph at the same coordinates a single render would
+    pub fn render_tile(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        graph: &NodeGraph,
+        registry: &NodeRegistry,
+        size: [u32; 2],
+        origin: [u32; 2],
+    ) -> Result<(), GrainError> {
+        let hash = codegen::structure_hash(graph, registry, self.format)?;
+        if !self.pipelines.contains_key(&hash) {
+            let shader = codegen::compile(graph, registry, self.format, &self.library)?;
+            let pipeline = GrainComputePipeline::from_graph(device, &self.bind_group_layout, &shader)?;
+            self.pipelines.insert(hash, pipeline);
+        }
+
+        let parameters = codegen::parameter_block(graph, registry)?;
+        let bytes: &[u8] = bytemuck::cast_slice(&parameters);
+        if self.params_buffer.size() < bytes.len() as u64 {
+            self.params_buffer = params_buffer(device, parameters.len());
+        }
+        queue.write_buffer(&self.params_buffer, 0, bytes);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&GraphUniforms { size, tile_origin: origin }));
+
+        let output_view = self.output_texture.create_view(&TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Graph Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&output_view) },
+                BindGroupEntry { binding: 1, resource: self.uniform_buffer.as_entire_binding() },
+                BindGroupEntry { binding: 2, resource: self.params_buffer.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Graph Compute Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Graph Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipelines[&hash].pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(self.width.div_ceil(8), self.height.div_ceil(8), 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    /// Number of pipelines compiled so far
+    pub fn cached_pipelines(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    /// Compile graphs against `library` from now on, e.g. sources reloaded from disk
+    pub fn set_library(&mut self, library: ShaderLibrary) {
+        self.library = library;
+        self.pipelines.clear();
+    }
+
+    pub fn output_texture(&self) -> &Texture {
+        &self.output_texture
+    }
+
+    /// Copy the last render back to the CPU at full precision
+    pub fn read_image_f32(&self, device: &Device, queue: &Queue) -> Result<Rgba32FImage, GrainError> {
+        let pixels = readback::read_texture(device, queue, &self.output_texture)?;
+        self.format.to_rgba32f_image(self.width, self.height, &pixels)
+            .ok_or_else(|| GrainError::Readback("Pixel buffer does not match texture size".to_string()))
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+fn params_buffer(device: &Device, elements: usize) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Graph Params Buffer"),
+        size: (elements.max(1) * std::mem::size_of::<[f32; 4]>()) as u64,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
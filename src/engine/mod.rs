@@ -1,6 +1,15 @@
 pub mod gpu_context;
 pub mod compute_pipeline;
 pub mod grain_renderer;
+pub mod readback;
+pub mod output_format;
+pub mod cpu_noise;
+pub mod cpu_renderer;
+pub mod tiled_renderer;
+pub mod backend;
 pub mod render_pipeline;
+pub mod post_process;
+pub mod damage;
+pub mod graph_renderer;
 pub mod texture_manager;
 pub mod shaders;
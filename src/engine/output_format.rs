@@ -0,0 +1,98 @@
+use half::f16;
+use image::{Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use wgpu::TextureFormat;
+
+use crate::utils::math::unorm8;
+
+/// Pixel format of the grain render target.
+///
+/// Float targets hold the same sRGB-encoded values as `Rgba8`, just without
+/// 8-bit quantization; EXR export converts them to linear light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Rgba8,
+    Rgba16Float,
+    Rgba32Float,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 3] = [Self::Rgba8, Self::Rgba16Float, Self::Rgba32Float];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rgba8 => "8-bit",
+            Self::Rgba16Float => "16-bit Float",
+            Self::Rgba32Float => "32-bit Float",
+        }
+    }
+
+    pub fn texture_format(&self) -> TextureFormat {
+        match self {
+            Self::Rgba8 => TextureFormat::Rgba8Unorm,
+            Self::Rgba16Float => TextureFormat::Rgba16Float,
+            Self::Rgba32Float => TextureFormat::Rgba32Float,
+        }
+    }
+
+    /// Texel format of the `texture_storage_2d` the shader writes to
+    pub fn wgsl_format(&self) -> &'static str {
+        match self {
+            Self::Rgba8 => "rgba8unorm",
+            Self::Rgba16Float => "rgba16float",
+            Self::Rgba32Float => "rgba32float",
+        }
+    }
+
+    pub fn bytes_per_channel(&self) -> usize {
+        match self {
+            Self::Rgba8 => 1,
+            Self::Rgba16Float => 2,
+            Self::Rgba32Float => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        4 * self.bytes_per_channel()
+    }
+
+    /// Store one channel the way a shader storage write to this format does
+    pub fn store(&self, value: f32, out: &mut [u8]) {
+        match self {
+            Self::Rgba8 => out[0] = unorm8(value),
+            Self::Rgba16Float => out.copy_from_slice(&f16::from_f32(value).to_le_bytes()),
+            Self::Rgba32Float => out.copy_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    /// Decode tightly packed pixels in this format, one float per channel
+    pub fn decode(&self, bytes: &[u8]) -> Vec<f32> {
+        match self {
+            Self::Rgba8 => bytes.iter().map(|&b| b as f32 / 255.0).collect(),
+            Self::Rgba16Float => bytes
+                .chunks_exact(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            Self::Rgba32Float => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        }
+    }
+
+    /// Pixels in this format as an 8-bit image; float formats are quantized
+    pub fn to_rgba8_image(&self, width: u32, height: u32, bytes: Vec<u8>) -> Option<RgbaImage> {
+        let pixels = match self {
+            Self::Rgba8 => bytes,
+            _ => self.decode(&bytes).into_iter().map(unorm8).collect(),
+        };
+        RgbaImage::from_raw(width, height, pixels)
+    }
+
+    /// Pixels in this format as a float image at full precision
+    pub fn to_rgba32f_image(&self, width: u32, height: u32, bytes: &[u8]) -> Option<Rgba32FImage> {
+        Rgba32FImage::from_raw(width, height, self.decode(bytes))
+    }
+}
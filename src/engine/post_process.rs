@@ -0,0 +1,146 @@
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAINFORGE POST-PROCESSING (CPU)
+// Mirrors the passes in shaders/postprocess.wgsl, which `RenderPipeline` runs on
+// the GPU. Keep the files in sync: each pass here must match its entry point.
+// ═══════════════════════════════════════════════════════════════════════════
+
+use crate::core::film_stock::{gaussian_taps, PostEffect, PostProcessChain};
+
+/// Weights for highlight extraction; Rec. 709 luma of the encoded values
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// RGBA pixels, one float per channel, row-major
+struct Image {
+    pixels: Vec<f32>,
+    width: u32,
+    height: u32,
+    // Pixels past this extent are padding and are never sampled
+    valid: [u32; 2],
+}
+
+impl Image {
+    fn get(&self, x: u32, y: u32) -> [f32; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// A new image of the same size, computed pixel by pixel across threads
+    fn map(&self, shade: impl Fn(u32, u32) -> [f32; 4] + Sync) -> Image {
+        let mut pixels = vec![0.0; self.pixels.len()];
+        let row_len = self.width as usize * 4;
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let rows_per_chunk = (self.height as usize).div_ceil(threads).max(1);
+
+        std::thread::scope(|scope| {
+            for (chunk_index, chunk) in pixels.chunks_mut(rows_per_chunk * row_len).enumerate() {
+                let shade = &shade;
+                scope.spawn(move || {
+                    let first_row = chunk_index * rows_per_chunk;
+                    for (row_offset, row) in chunk.chunks_exact_mut(row_len).enumerate() {
+                        let y = (first_row + row_offset) as u32;
+                        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                            pixel.copy_from_slice(&shade(x as u32, y));
+                        }
+                    }
+                });
+            }
+        });
+
+        Image { pixels, width: self.width, height: self.height, valid: self.valid }
+    }
+}
+
+/// Run `chain` over `pixels` (`width` x `height`, RGBA floats) in place.
+///
+/// Blurs clamp to the edge of the `valid` region, so a tile padded past the output edge
+/// filters exactly like the full output.
+pub fn apply_chain(chain: &PostProcessChain, pixels: &mut Vec<f32>, width: u32, height: u32, valid: [u32; 2]) {
+    let mut image = Image { pixels: std::mem::take(pixels), width, height, valid };
+    for effect in &chain.effects {
+        image = apply_effect(effect, image);
+    }
+    *pixels = image.pixels;
+}
+
+fn apply_effect(effect: &PostEffect, image: Image) -> Image {
+    let sigma = effect.radius();
+    if gaussian_taps(sigma) == 0 {
+        return image;
+    }
+
+    match effect {
+        PostEffect::Blur { .. } => gaussian_blur(&image, sigma),
+        PostEffect::Halation { threshold, strength, tint, .. } => {
+            let tint = [tint[0].get(), tint[1].get(), tint[2].get()];
+            let glow = gaussian_blur(&highlights(&image, threshold.get(), tint), sigma);
+            screen(&image, &glow, strength.get())
+        }
+        PostEffect::UnsharpMask { amount, .. } => {
+            let blurred = gaussian_blur(&image, sigma);
+            unsharp(&image, &blurred, amount.get())
+        }
+    }
+}
+
+fn gaussian_blur(image: &Image, sigma: f32) -> Image {
+    let horizontal = blur_pass(image, sigma, [1, 0]);
+    blur_pass(&horizontal, sigma, [0, 1])
+}
+
+/// One direction of a separable Gaussian (`blur` in postprocess.wgsl)
+fn blur_pass(image: &Image, sigma: f32, direction: [i32; 2]) -> Image {
+    let taps = gaussian_taps(sigma) as i32;
+    let last = [image.valid[0] as i32 - 1, image.valid[1] as i32 - 1];
+
+    image.map(|x, y| {
+        let mut sum = [0.0f32; 4];
+        let mut total = 0.0;
+        for i in -taps..=taps {
+            let w = (-((i * i) as f32) / (2.0 * sigma * sigma)).exp();
+            let px = (x as i32 + direction[0] * i).clamp(0, last[0]);
+            let py = (y as i32 + direction[1] * i).clamp(0, last[1]);
+            let sample = image.get(px as u32, py as u32);
+            for c in 0..4 {
+                sum[c] += w * sample[c];
+            }
+            total += w;
+        }
+        sum.map(|s| s / total)
+    })
+}
+
+/// Highlights above `threshold`, tinted (`highlights` in postprocess.wgsl)
+fn highlights(image: &Image, threshold: f32, tint: [f32; 3]) -> Image {
+    image.map(|x, y| {
+        let c = image.get(x, y);
+        let luma = LUMA[0] * c[0] + LUMA[1] * c[1] + LUMA[2] * c[2];
+        let k = (luma - threshold).max(0.0) / (1.0 - threshold).max(1e-4);
+        [tint[0] * k, tint[1] * k, tint[2] * k, 0.0]
+    })
+}
+
+/// Screen a glow over the image (`screen` in postprocess.wgsl)
+fn screen(image: &Image, glow: &Image, strength: f32) -> Image {
+    image.map(|x, y| {
+        let base = image.get(x, y);
+        let g = glow.get(x, y);
+        let mut out = base;
+        for c in 0..3 {
+            out[c] = 1.0 - (1.0 - base[c]) * (1.0 - (g[c] * strength).clamp(0.0, 1.0));
+        }
+        out
+    })
+}
+
+/// Push each pixel away from its blurred surroundings (`unsharp` in postprocess.wgsl)
+fn unsharp(image: &Image, blurred: &Image, amount: f32) -> Image {
+    image.map(|x, y| {
+        let base = image.get(x, y);
+        let b = blurred.get(x, y);
+        let mut out = base;
+        for c in 0..3 {
+            out[c] = base[c] + amount * (base[c] - b[c]);
+        }
+        out
+    })
+}
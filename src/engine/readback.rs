@@ -0,0 +1,161 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use parking_lot::Mutex;
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    Extent3d, Maintain, MapMode, Origin3d, Queue, TexelCopyBufferInfo, TexelCopyBufferLayout,
+    TexelCopyTextureInfo, Texture, TextureAspect, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::core::error::GrainError;
+
+/// Staging buffer holding a texture copy whose rows are padded to 256 bytes
+struct StagingCopy {
+    buffer: Buffer,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+    height: u32,
+}
+
+/// Copy a texture into a mappable buffer and block until the pixels are on the CPU.
+///
+/// Returns tightly packed rows (no 256-byte padding) in the texture's native format.
+pub fn read_texture(device: &Device, queue: &Queue, texture: &Texture) -> Result<Vec<u8>, GrainError> {
+    let copy = encode_copy(device, queue, texture)?;
+    let slice = copy.buffer.slice(..);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+
+    receiver
+        .recv()
+        .map_err(|_| GrainError::Readback("Map callback was dropped".to_string()))?
+        .map_err(|e| GrainError::Readback(e.to_string()))?;
+
+    Ok(unpad_rows(&copy))
+}
+
+/// Async variant of [`read_texture`].
+///
+/// The returned future only resolves once the device is polled, which eframe does every
+/// frame; outside an event loop use [`read_texture`] instead.
+pub async fn read_texture_async(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+) -> Result<Vec<u8>, GrainError> {
+    let copy = encode_copy(device, queue, texture)?;
+    let slice = copy.buffer.slice(..);
+
+    let state = Arc::new(Mutex::new(MapState::default()));
+    let callback_state = state.clone();
+    slice.map_async(MapMode::Read, move |result| {
+        let mut state = callback_state.lock();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+
+    MapFuture { state }
+        .await
+        .map_err(|e| GrainError::Readback(e.to_string()))?;
+
+    Ok(unpad_rows(&copy))
+}
+
+fn encode_copy(device: &Device, queue: &Queue, texture: &Texture) -> Result<StagingCopy, GrainError> {
+    let bytes_per_pixel = texture
+        .format()
+        .block_copy_size(None)
+        .ok_or_else(|| GrainError::Readback(format!("Format {:?} cannot be copied", texture.format())))?;
+
+    let width = texture.width();
+    let height = texture.height();
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("Readback Staging Buffer"),
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    Ok(StagingCopy {
+        buffer,
+        unpadded_bytes_per_row,
+        padded_bytes_per_row,
+        height,
+    })
+}
+
+/// Strip the row padding from a mapped staging buffer and unmap it
+fn unpad_rows(copy: &StagingCopy) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((copy.unpadded_bytes_per_row * copy.height) as usize);
+    {
+        let mapped = copy.buffer.slice(..).get_mapped_range();
+        for row in mapped.chunks_exact(copy.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..copy.unpadded_bytes_per_row as usize]);
+        }
+    }
+    copy.buffer.unmap();
+    pixels
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Resolves when the `map_async` callback fires
+struct MapFuture {
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture {
+    type Output = Result<(), BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
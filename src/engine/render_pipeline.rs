@@ -1,2 +1,316 @@
-// Placeholder for render pipeline
-pub struct RenderPipeline {}
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BindingResource, BindingType, BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline,
+    Device, Extent3d, PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StorageTextureAccess, Texture, TextureDescriptor, TextureDimension, TextureUsages, TextureView,
+    TextureViewDescriptor,
+};
+
+use crate::core::error::GrainError;
+use crate::core::film_stock::{gaussian_taps, PostEffect, PostProcessChain};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::shaders::ShaderLibrary;
+
+/// Intermediate results keep full precision whatever the output format
+const SCRATCH_FORMAT: OutputFormat = OutputFormat::Rgba32Float;
+/// The current image, two temporaries for a blur and the effect's result
+const SCRATCH_TEXTURES: usize = 4;
+
+/// Uniforms for one pass, matching `PostParams` in postprocess.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, Pod, Zeroable)]
+struct PostParams {
+    bounds: [u32; 2],
+    direction: [u32; 2],
+    tint: [f32; 4],
+    sigma: f32,
+    taps: u32,
+    amount: f32,
+    threshold: f32,
+}
+
+/// Entry points of postprocess.wgsl
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Copy,
+    Blur,
+    Highlights,
+    Screen,
+    Unsharp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Target,
+    Scratch(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pass {
+    entry: Entry,
+    source: Slot,
+    aux: Slot,
+    output: Slot,
+    params: PostParams,
+}
+
+/// Every pass of postprocess.wgsl compiled for one storage format
+struct PassPipelines {
+    layout: BindGroupLayout,
+    copy: ComputePipeline,
+    blur: ComputePipeline,
+    highlights: ComputePipeline,
+    screen: ComputePipeline,
+    unsharp: ComputePipeline,
+}
+
+impl PassPipelines {
+    fn new(device: &Device, library: &ShaderLibrary, format: OutputFormat) -> Result<Self, GrainError> {
+        let source = library.preprocess("postprocess.wgsl", &[("OUTPUT_FORMAT", format.wgsl_format())])?;
+        source.validate()?;
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Post-process Shader"),
+            source: ShaderSource::Wgsl(source.code.into()),
+        });
+
+        let sampled = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Post-process Bind Group Layout"),
+            entries: &[
+                sampled(0),
+                sampled(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: format.texture_format(),
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Post-process Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Self {
+            copy: pipeline("copy"),
+            blur: pipeline("blur"),
+            highlights: pipeline("highlights"),
+            screen: pipeline("screen"),
+            unsharp: pipeline("unsharp"),
+            layout,
+        })
+    }
+
+    fn pipeline(&self, entry: Entry) -> &ComputePipeline {
+        match entry {
+            Entry::Copy => &self.copy,
+            Entry::Blur => &self.blur,
+            Entry::Highlights => &self.highlights,
+            Entry::Screen => &self.screen,
+            Entry::Unsharp => &self.unsharp,
+        }
+    }
+}
+
+/// Post-process stage run over a rendered grain target: an ordered chain of blur,
+/// halation and unsharp-mask effects, each built from a few compute passes.
+///
+/// The target is copied into float scratch textures, processed there and written back,
+/// so 8-bit outputs are only quantized once. `post_process.rs` is the CPU equivalent.
+pub struct RenderPipeline {
+    passes: PassPipelines,
+    // The same passes writing the target's format; only `copy` is used, to write back
+    store: PassPipelines,
+    scratch: Vec<Texture>,
+    format: OutputFormat,
+    width: u32,
+    height: u32,
+}
+
+impl RenderPipeline {
+    /// Stage for targets of the given size and format
+    pub fn new(device: &Device, width: u32, height: u32, format: OutputFormat) -> Result<Self, GrainError> {
+        Self::from_library(device, width, height, format, &ShaderLibrary::builtin())
+    }
+
+    /// Stage built from `library`'s `postprocess.wgsl`, e.g. sources reloaded from disk
+    pub fn from_library(
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: OutputFormat,
+        library: &ShaderLibrary,
+    ) -> Result<Self, GrainError> {
+        let scratch = (0..SCRATCH_TEXTURES)
+            .map(|_| {
+                device.create_texture(&TextureDescriptor {
+                    label: Some("Post-process Scratch Texture"),
+                    size: Extent3d { width, height, depth_or_array_layers: 1 },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: SCRATCH_FORMAT.texture_format(),
+                    usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            passes: PassPipelines::new(device, library, SCRATCH_FORMAT)?,
+            store: PassPipelines::new(device, library, format)?,
+            scratch,
+            format,
+            width,
+            height,
+        })
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Run `chain` over `target` in place. Pixels past `valid` are padding beyond the
+    /// output edge (see [`GrainRenderer::render_tile`](crate::engine::grain_renderer::GrainRenderer::render_tile));
+    /// blurs clamp to the valid region instead of sampling them.
+    pub fn apply(&self, device: &Device, queue: &Queue, target: &Texture, chain: &PostProcessChain, valid: [u32; 2]) {
+        let passes = plan_passes(chain, valid);
+        if passes.is_empty() {
+            return;
+        }
+
+        let target_view = target.create_view(&TextureViewDescriptor::default());
+        let scratch_views: Vec<TextureView> = self.scratch.iter()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()))
+            .collect();
+        let view = |slot| match slot {
+            Slot::Target => &target_view,
+            Slot::Scratch(i) => &scratch_views[i],
+        };
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Post-process Encoder"),
+        });
+
+        for pass in passes {
+            let pipelines = if pass.output == Slot::Target { &self.store } else { &self.passes };
+            let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Post-process Params Buffer"),
+                contents: bytemuck::bytes_of(&pass.params),
+                usage: BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Post-process Bind Group"),
+                layout: &pipelines.layout,
+                entries: &[
+                    BindGroupEntry { binding: 0, resource: BindingResource::TextureView(view(pass.source)) },
+                    BindGroupEntry { binding: 1, resource: BindingResource::TextureView(view(pass.aux)) },
+                    BindGroupEntry { binding: 2, resource: BindingResource::TextureView(view(pass.output)) },
+                    BindGroupEntry { binding: 3, resource: params_buffer.as_entire_binding() },
+                ],
+            });
+
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Post-process Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(pipelines.pipeline(pass.entry));
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(self.width.div_ceil(8), self.height.div_ceil(8), 1);
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// The passes for a chain: copy the target into scratch, run each effect between
+/// scratch textures, copy the result back. Empty when no effect changes a pixel.
+fn plan_passes(chain: &PostProcessChain, valid: [u32; 2]) -> Vec<Pass> {
+    let base = PostParams { bounds: valid, ..Default::default() };
+    let pass = |entry, source, aux, output, params| Pass { entry, source, aux, output, params };
+    let blur = |passes: &mut Vec<Pass>, from, via, to, params: PostParams| {
+        passes.push(pass(Entry::Blur, from, from, via, PostParams { direction: [1, 0], ..params }));
+        passes.push(pass(Entry::Blur, via, via, to, PostParams { direction: [0, 1], ..params }));
+    };
+
+    let mut passes = vec![pass(Entry::Copy, Slot::Target, Slot::Target, Slot::Scratch(0), base)];
+    let mut current = 0;
+    for effect in &chain.effects {
+        let sigma = effect.radius();
+        let taps = gaussian_taps(sigma);
+        if taps == 0 {
+            continue;
+        }
+
+        // Two temporaries and the result, all distinct from the current image
+        let free: Vec<usize> = (0..SCRATCH_TEXTURES).filter(|&i| i != current).collect();
+        let (t1, t2, result) = (Slot::Scratch(free[0]), Slot::Scratch(free[1]), Slot::Scratch(free[2]));
+        let image = Slot::Scratch(current);
+        let params = PostParams { sigma, taps, ..base };
+
+        match effect {
+            PostEffect::Blur { .. } => blur(&mut passes, image, t1, result, params),
+            PostEffect::Halation { threshold, strength, tint, .. } => {
+                let params = PostParams {
+                    tint: [tint[0].get(), tint[1].get(), tint[2].get(), 0.0],
+                    amount: strength.get(),
+                    threshold: threshold.get(),
+                    ..params
+                };
+                passes.push(pass(Entry::Highlights, image, image, t1, params));
+                blur(&mut passes, t1, t2, t1, params);
+                passes.push(pass(Entry::Screen, image, t1, result, params));
+            }
+            PostEffect::UnsharpMask { amount, .. } => {
+                let params = PostParams { amount: amount.get(), ..params };
+                blur(&mut passes, image, t1, t2, params);
+                passes.push(pass(Entry::Unsharp, image, t2, result, params));
+            }
+        }
+        current = free[2];
+    }
+
+    if passes.len() == 1 {
+        return Vec::new();
+    }
+    passes.push(pass(Entry::Copy, Slot::Scratch(current), Slot::Scratch(current), Slot::Target, base));
+    passes
+}
@@ -1,44 +1,627 @@
-// Basic Grain Compute Shader
-// Uses the Noise Library to generate film grain
+// Film Grain Compute Shader
+// Uses the Noise Library to generate film grain from a full FilmStock definition
+// Every function here is mirrored by engine/cpu_renderer.rs; keep both in sync.
 
-// Note: We need to include noise.wgsl. In WGPU we typically concatenate or use imports if supported (not standard WGSL yet).
-// For this implementation, we assume we append this file to noise.wgsl when creating the shader module, OR we just assume functions are available if we do that in Rust.
-// The Rust side will handle concatenation.
+#include "noise.wgsl"
 
-@group(0) @binding(0) var output_texture: texture_storage_2d<rgba8unorm, write>;
+// Texel format of the render target; the pipeline defines it from the renderer's OutputFormat
+#ifndef OUTPUT_FORMAT
+#define OUTPUT_FORMAT rgba8unorm
+#endif
 
+@group(0) @binding(0) var output_texture: texture_storage_2d<OUTPUT_FORMAT, write>;
+// Photograph to composite onto (sRGB encoded, same size as the render target); unused unless use_input is set
+@group(0) @binding(2) var input_texture: texture_2d<f32>;
+
+// Layout must match GrainParams in grain_renderer.rs (16-byte rows)
 struct Params {
     grain_amount: f32,
     grain_size: f32,
     width: f32,
     height: f32,
-    seed: f32,
+
+    size_variation: f32,
+    sharpness: f32,
+    crystal_type: u32,
+    crystal_sides: u32,
+
+    response_shadows: f32,
+    response_midtones: f32,
+    response_highlights: f32,
+    response_mode: u32,
+
+    is_color: u32,
+    correlation: f32,
+    dye_softness: f32,
+    synthesis: u32,
+
+    channel_intensity: vec4<f32>,
+    channel_size: vec4<f32>,
+
+    clustering: u32,
+    cluster_size: f32,
+    organic: f32,
+    detail: f32,
+
+    swirl: f32,
+    blend_mode: u32,
+    use_input: u32,
+    samples: u32,
+
+    seed: vec2<u32>, // (low, high) words of the 64-bit seed
+    tile_origin: vec2<u32>,
+
+    time: f32, // Noise time in re-rolls of each lattice point, advanced by sequences
+    tileable: u32,
+    _padding0: u32,
+    _padding1: u32,
 }
 
 @group(0) @binding(1) var<uniform> params: Params;
 
+// Crystal types (CrystalType)
+const CRYSTAL_CUBIC: u32 = 0u;
+const CRYSTAL_TABULAR: u32 = 1u;
+const CRYSTAL_CORE_SHELL: u32 = 2u;
+const CRYSTAL_CELLULAR: u32 = 3u;
+const CRYSTAL_NEEDLE: u32 = 4u;
+const CRYSTAL_CUSTOM: u32 = 5u;
+
+// Grain synthesis (GrainSynthesis)
+const SYNTHESIS_NOISE: u32 = 0u;
+const SYNTHESIS_PARTICLE: u32 = 1u;
+const SYNTHESIS_BOOLEAN: u32 = 2u;
+
+// Response modes (ResponseMode)
+const RESPONSE_NEGATIVE: u32 = 0u;
+const RESPONSE_PRINT: u32 = 1u;
+const RESPONSE_REVERSAL: u32 = 2u;
+const RESPONSE_CUSTOM: u32 = 3u;
+
+// Clustering (ClusteringType)
+const CLUSTER_NONE: u32 = 0u;
+const CLUSTER_POISSON: u32 = 1u;
+const CLUSTER_FRACTAL: u32 = 2u;
+const CLUSTER_VORONOI: u32 = 3u;
+const CLUSTER_HYBRID: u32 = 4u;
+
+// Blend modes (BlendMode)
+const BLEND_OVERLAY: u32 = 0u;
+const BLEND_SOFT_LIGHT: u32 = 1u;
+const BLEND_ADDITIVE: u32 = 2u;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// TILING
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Lattice cells across the output; tiling snaps it to an even count so the lattice wraps
+fn noise_scale() -> f32 {
+    let scale = 100.0 / max(0.1, params.grain_size);
+    if (params.tileable != 0u) {
+        return max(2.0, 2.0 * floor(scale * 0.5 + 0.5));
+    }
+    return scale;
+}
+
+// Factor for sampling a lattice of `period` cells at `scale` times the frequency.
+// While tiling, it is snapped so the scaled period is still a whole, even number of cells.
+fn tile_scale(period: vec2<f32>, scale: vec2<f32>) -> vec2<f32> {
+    if (all(period == vec2(0.0))) {
+        return scale;
+    }
+    return max(vec2(2.0), 2.0 * floor(period * scale * 0.5 + 0.5)) / period;
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CRYSTAL SHAPES
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Distance from the centre of a regular n-gon with unit circumradius; edges are at 1
+fn polygon_radius(q: vec2<f32>, sides: u32) -> f32 {
+    let sector = 6.283185307 / f32(sides);
+    let angle = atan2(q.y, q.x);
+    let a = angle - sector * floor(angle / sector) - sector * 0.5;
+    return length(q) * cos(a) / cos(sector * 0.5);
+}
+
+// Distance from a Voronoi feature point measured against a regular n-gon
+fn polygon_distance(p: vec2<f32>, sides: u32, period: vec2<f32>) -> f32 {
+    let cell = voronoi(p, 0.8, period);
+    return polygon_radius(p - (cell.cell_id + hash22(wrap_cell(cell.cell_id, period)) * 0.8), sides);
+}
+
+// Base grain field in [-1, 1] shaped by the crystal type
+fn crystal_noise(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    switch params.crystal_type {
+        case CRYSTAL_TABULAR: {
+            // Flat plates: wide, thin grains
+            let s = tile_scale(period, vec2(0.5, 1.0));
+            return simplex_noise(p * s, period * s);
+        }
+        case CRYSTAL_CORE_SHELL: {
+            // Dense core surrounded by a fainter shell
+            let d = voronoi(p, 0.8, period).distance;
+            let core = 1.0 - smoothstep(0.0, 0.2, d);
+            let shell = 1.0 - smoothstep(0.0, 0.15, abs(d - 0.4));
+            return clamp(core + 0.6 * shell, 0.0, 1.0) * 2.0 - 1.0;
+        }
+        case CRYSTAL_CELLULAR: {
+            return 1.0 - 2.0 * clamp(voronoi(p, 1.0, period).distance, 0.0, 1.0);
+        }
+        case CRYSTAL_NEEDLE: {
+            // Strongly elongated along x
+            let s = tile_scale(period, vec2(0.2, 2.0));
+            return simplex_noise(p * s, period * s);
+        }
+        case CRYSTAL_CUSTOM: {
+            let sides = clamp(params.crystal_sides, 3u, 12u);
+            return 1.0 - 2.0 * clamp(polygon_distance(p, sides, period), 0.0, 1.0);
+        }
+        default: {
+            return simplex_noise(p, period);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PARTICLE GRAINS
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Grain slots per lattice cell; each slot is occupied with probability PARTICLE_DENSITY
+const PARTICLES_PER_CELL: u32 = 2u;
+const PARTICLE_DENSITY: f32 = 0.75;
+
+fn hash_cell(cell: vec2<f32>, index: u32) -> u32 {
+    let ci = vec2<i32>(cell);
+    return evolve(pcg(bitcast<u32>(ci.x) ^ pcg(bitcast<u32>(ci.y) ^ pcg(index ^ noise_key.x) ^ noise_key.y)));
+}
+
+// Independent uniform draw `k` from a grain hash
+fn grain_random(h: u32, k: u32) -> f32 {
+    return uint_to_float(pcg(h + k * 2654435761u));
+}
+
+// Normalised distance from the grain centre in grain-local coordinates; the outline is at 1
+fn crystal_shape_distance(q: vec2<f32>) -> f32 {
+    switch params.crystal_type {
+        case CRYSTAL_CUBIC: {
+            return polygon_radius(q, 4u);
+        }
+        case CRYSTAL_TABULAR: {
+            // Flat disc seen at an angle
+            return length(vec2(q.x, q.y / 0.5));
+        }
+        case CRYSTAL_CELLULAR: {
+            return polygon_radius(q, 6u);
+        }
+        case CRYSTAL_NEEDLE: {
+            return length(vec2(q.x, q.y / 0.15));
+        }
+        case CRYSTAL_CUSTOM: {
+            return polygon_radius(q, clamp(params.crystal_sides, 3u, 12u));
+        }
+        default: {
+            return length(q);
+        }
+    }
+}
+
+// Opacity of a grain at normalised distance `d`; sharpness narrows the edge falloff
+fn grain_coverage(d: f32) -> f32 {
+    let falloff = mix(0.6, 0.02, params.sharpness);
+    let edge = 1.0 - smoothstep(1.0 - falloff, 1.0, d);
+
+    if (params.crystal_type == CRYSTAL_CORE_SHELL) {
+        // Dense core inside a fainter shell
+        let core = 1.0 - smoothstep(0.45 - 0.5 * falloff, 0.45, d);
+        let shell = edge * smoothstep(0.7 - 0.5 * falloff, 0.7, d);
+        return max(core, 0.6 * shell);
+    }
+    return edge;
+}
+
+// Coverage in [0, 1] from grains seeded in this and the eight neighbouring cells
+fn particle_field(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    var coverage = 0.0;
+
+    for (var j = -1; j <= 1; j++) {
+        for (var i = -1; i <= 1; i++) {
+            let neighbor = cell + vec2(f32(i), f32(j));
+            for (var k = 0u; k < PARTICLES_PER_CELL; k++) {
+                let h = hash_cell(wrap_cell(neighbor, period), k);
+                if (grain_random(h, 0u) > PARTICLE_DENSITY) {
+                    continue;
+                }
+
+                let center = neighbor + vec2(grain_random(h, 1u), grain_random(h, 2u));
+                // Log-normal sizes; capped at one cell so the 3x3 search stays exact
+                let g = box_muller(grain_random(h, 3u), grain_random(h, 4u)).x;
+                let radius = min(0.45 * exp(0.5 * params.size_variation * g), 1.0);
+                let angle = grain_random(h, 5u) * 6.283185307;
+
+                let offset = p - center;
+                let cs = vec2(cos(angle), sin(angle));
+                let q = vec2(offset.x * cs.x + offset.y * cs.y, offset.y * cs.x - offset.x * cs.y) / radius;
+                coverage += grain_coverage(crystal_shape_distance(q));
+            }
+        }
+    }
+
+    return min(coverage, 1.0);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// BOOLEAN MODEL
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Mean disc radius in lattice units; the lattice cell bounds the largest disc
+const BOOLEAN_MEAN_RADIUS: f32 = 0.35;
+// Caps the Poisson draw per cell so near-white pixels stay bounded
+const BOOLEAN_MAX_DISCS: u32 = 48u;
+// Hash stream for the per-pixel filter samples, kept apart from the disc streams
+const BOOLEAN_SAMPLE_STREAM: u32 = 0x2545f491u;
+
+// Disc intensity giving expected coverage u, from 1 - exp(-lambda * pi * E[r^2]) = u
+fn boolean_lambda(u: f32) -> f32 {
+    let s = 0.5 * params.size_variation;
+    let mean_r2 = BOOLEAN_MEAN_RADIUS * BOOLEAN_MEAN_RADIUS * exp(2.0 * s * s);
+    return -log(1.0 - clamp(u, 0.0, 0.995)) / (3.14159265 * mean_r2);
+}
+
+// Whether any disc of the Boolean model with intensity `lambda` covers p
+fn boolean_covered(p: vec2<f32>, period: vec2<f32>, lambda: f32, layer: u32) -> bool {
+    let cell = floor(p);
+    let threshold = exp(-lambda);
+    let s = 0.5 * params.size_variation;
+
+    for (var j = -1; j <= 1; j++) {
+        for (var i = -1; i <= 1; i++) {
+            let neighbor = cell + vec2(f32(i), f32(j));
+            let h = hash_cell(wrap_cell(neighbor, period), layer);
+
+            // Knuth's Poisson sampler; reusing the uniforms at every lambda keeps the
+            // disc sets nested, so brighter pixels only ever add discs
+            var product = 1.0;
+            for (var k = 0u; k < BOOLEAN_MAX_DISCS; k++) {
+                let draw = 5u * k;
+                product *= grain_random(h, draw);
+                if (product <= threshold) {
+                    break;
+                }
+
+                let center = neighbor + vec2(grain_random(h, draw + 1u), grain_random(h, draw + 2u));
+                let g = box_muller(grain_random(h, draw + 3u), grain_random(h, draw + 4u)).x;
+                let radius = min(BOOLEAN_MEAN_RADIUS * exp(s * g), 1.0);
+                let offset = p - center;
+                if (dot(offset, offset) < radius * radius) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    return false;
+}
+
+// Monte Carlo estimate of the Gaussian-filtered Boolean model at intensity u.
+// `pixel` is the size of one output pixel in lattice units.
+fn boolean_grain(p: vec2<f32>, period: vec2<f32>, pixel: f32, coords: vec2<u32>, u: f32, layer: u32) -> f32 {
+    let lambda = boolean_lambda(u);
+    // Sharper stocks use a narrower reconstruction filter
+    let sigma = mix(1.2, 0.4, params.sharpness) * pixel;
+    let h = hash_cell(vec2<f32>(coords), BOOLEAN_SAMPLE_STREAM);
+    let samples = max(params.samples, 1u);
+
+    var hits = 0u;
+    for (var n = 0u; n < samples; n++) {
+        let offset = box_muller(grain_random(h, 2u * n), grain_random(h, 2u * n + 1u)) * sigma;
+        if (boolean_covered(p + offset, period, lambda, layer)) {
+            hits++;
+        }
+    }
+
+    return f32(hits) / f32(samples);
+}
+
+// Mix in a finer octave of crystals to spread the size distribution
+fn grain_field(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    if (params.synthesis == SYNTHESIS_PARTICLE) {
+        return particle_field(p, period) * 2.0 - 1.0;
+    }
+
+    let primary = crystal_noise(p, period);
+    let s = tile_scale(period, vec2(2.0));
+    let secondary = crystal_noise(p * s + vec2(17.0, 31.0), period * s);
+    let w = params.size_variation * 0.5;
+    return (primary + secondary * w) / (1.0 + w);
+}
+
+// Hard-edged grains for high sharpness, soft for low
+fn apply_sharpness(n: f32) -> f32 {
+    // Particle grains already carry their edge falloff
+    if (params.synthesis == SYNTHESIS_PARTICLE) {
+        return n;
+    }
+    let exponent = mix(2.0, 0.5, params.sharpness);
+    return sign(n) * pow(abs(n), exponent);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// DYE CLOUDS (COLOR)
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Larger dye clouds diffuse further, so softness scales with the layer size
+fn layer_softness(c: u32) -> f32 {
+    return clamp(params.dye_softness * params.channel_size[c], 0.0, 1.0);
+}
+
+// One dye layer: crystal grain diffused into a smooth cloud by `softness`
+fn dye_cloud(p: vec2<f32>, period: vec2<f32>, softness: f32) -> f32 {
+    let s = tile_scale(period, vec2(0.5));
+    let cloud = simplex_noise(p * s + vec2(101.0, 53.0), period * s);
+    let mixed = mix(grain_field(p, period), cloud, softness);
+    // Mixing two independent fields lowers the variance; restore it
+    return mixed / sqrt((1.0 - softness) * (1.0 - softness) + softness * softness);
+}
+
+// Correlate the three dye layers so each pair has correlation `params.correlation`
+// (negative values bottom out at -0.5, the minimum for three equal layers)
+fn correlate_layers(shared_layer: f32, own: vec3<f32>) -> vec3<f32> {
+    let rho = params.correlation;
+    if (rho >= 0.0) {
+        return sqrt(rho) * shared_layer + sqrt(1.0 - rho) * own;
+    }
+
+    // Subtract k times the layer mean; k solves (k^2 - 2k) / (3 - 2k + k^2) = rho / 2
+    let t = 0.5 * rho;
+    let k = 1.0 - sqrt(1.0 + 3.0 * t / (1.0 - t));
+    let mean = (own.x + own.y + own.z) / 3.0;
+    return (own - k * mean) / sqrt(1.0 - 2.0 * k / 3.0 + k * k / 3.0);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// RESPONSE CURVE
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Grain strength at a given density; 0.5 is neutral for every band
+fn response_weight(density: f32) -> f32 {
+    var luma = density;
+    // Positive-working stocks build density inversely to exposure
+    if (params.response_mode == RESPONSE_PRINT || params.response_mode == RESPONSE_REVERSAL) {
+        luma = 1.0 - luma;
+    }
+
+    let ws = 1.0 - smoothstep(0.0, 0.5, luma);
+    let wh = smoothstep(0.5, 1.0, luma);
+    let wm = 1.0 - ws - wh;
+
+    return (ws * params.response_shadows + wm * params.response_midtones + wh * params.response_highlights) * 2.0;
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CLUSTERING
+// ─────────────────────────────────────────────────────────────────────────────
+
+// Octaves of cluster detail; `detail` is a float parameter in [1, 8]
+fn cluster_octaves() -> i32 {
+    return clamp(i32(params.detail), 1, 8);
+}
+
+// Swirl the cluster lattice with a low-frequency fbm flow
+fn cluster_warp(q: vec2<f32>, period: vec2<f32>) -> vec2<f32> {
+    let octaves = cluster_octaves();
+    let s = tile_scale(period, vec2(0.5));
+    let flow = vec2(
+        fbm(q * s, octaves, 2.0, 0.5, period * s),
+        fbm(q * s + vec2(3.1, 7.4), octaves, 2.0, 0.5, period * s)
+    );
+    return q + params.swirl * flow;
+}
+
+// Neyman-Scott process: grains gather around randomly placed parent points
+fn poisson_clusters(q: vec2<f32>, period: vec2<f32>) -> f32 {
+    let parent = voronoi(cluster_warp(q, period), 1.0, period);
+    return 1.0 - 2.0 * smoothstep(0.0, 0.8, parent.distance);
+}
+
+// Self-similar clumping at every scale from cluster_size down
+fn fractal_clusters(q: vec2<f32>, period: vec2<f32>) -> f32 {
+    return domain_warp(q, params.swirl, cluster_octaves(), period) * 1.5;
+}
+
+// Emulsion cells with their own grain density, softened towards the cell borders
+fn voronoi_clusters(q: vec2<f32>, period: vec2<f32>) -> f32 {
+    let cell = voronoi(cluster_warp(q, period), 1.0, period);
+    let cell_density = hash21(wrap_cell(cell.cell_id, period)) * 2.0 - 1.0;
+    return cell_density * (1.0 - 0.5 * smoothstep(0.3, 0.9, cell.distance));
+}
+
+// Low-frequency density modulation so grains clump instead of spreading uniformly
+fn cluster_density(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    let scale = tile_scale(period, vec2(1.0 / params.cluster_size));
+    let q = p * scale;
+    let q_period = period * scale;
+
+    var c = 0.0;
+    switch params.clustering {
+        case CLUSTER_POISSON: {
+            c = poisson_clusters(q, q_period);
+        }
+        case CLUSTER_FRACTAL: {
+            c = fractal_clusters(q, q_period);
+        }
+        case CLUSTER_VORONOI: {
+            c = voronoi_clusters(q, q_period);
+        }
+        case CLUSTER_HYBRID: {
+            // Cells broken up by fractal detail
+            let s = tile_scale(q_period, vec2(2.0));
+            c = 0.6 * voronoi_clusters(q, q_period) + 0.4 * fractal_clusters(q * s, q_period * s);
+        }
+        default: {
+            return 1.0;
+        }
+    }
+
+    // organic in [1, 2] scales how strongly clusters modulate density
+    return clamp(1.0 + 0.5 * c * params.organic, 0.0, 2.0);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// COMPOSITING
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn srgb_to_linear(c: f32) -> f32 {
+    return select(pow((c + 0.055) / 1.055, 2.4), c / 12.92, c <= 0.04045);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    return select(1.055 * pow(c, 1.0 / 2.4) - 0.055, c * 12.92, c <= 0.0031308);
+}
+
+// Blend signed grain onto an sRGB-encoded base value
+fn blend_grain(base: f32, grain: f32) -> f32 {
+    // Grain as a layer centred on mid-grey, the neutral value for overlay and soft light
+    let layer = clamp(0.5 + 0.5 * grain, 0.0, 1.0);
+
+    switch params.blend_mode {
+        case BLEND_SOFT_LIGHT: {
+            // W3C soft light
+            if (layer <= 0.5) {
+                return base - (1.0 - 2.0 * layer) * base * (1.0 - base);
+            }
+            let d = select(sqrt(base), ((16.0 * base - 12.0) * base + 4.0) * base, base <= 0.25);
+            return base + (2.0 * layer - 1.0) * (d - base);
+        }
+        case BLEND_ADDITIVE: {
+            // Add in linear light so grain energy is preserved across the tonal range
+            return linear_to_srgb(clamp(srgb_to_linear(base) + 0.5 * grain, 0.0, 1.0));
+        }
+        default: {
+            if (base < 0.5) {
+                return 2.0 * base * layer;
+            }
+            return 1.0 - 2.0 * (1.0 - base) * (1.0 - layer);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// MAIN
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn channel_offset(c: u32) -> vec2<f32> {
+    return vec2(f32(c) * 53.7, f32(c) * 19.3);
+}
+
+fn shade_channel(n: f32, density: f32, intensity: f32) -> f32 {
+    let value = apply_sharpness(n) * 0.5 + 0.5;
+    return value * params.grain_amount * intensity * density * response_weight(value);
+}
+
+// Signed grain for compositing; the response curve follows the plate luminance instead of the grain
+fn composite_channel(base: f32, n: f32, density: f32, intensity: f32, weight: f32) -> f32 {
+    let grain = apply_sharpness(n) * params.grain_amount * intensity * density * weight;
+    return blend_grain(base, grain);
+}
+
+// Boolean synthesis re-renders the plate from grains instead of blending grain onto it;
+// standalone renders use a mid-grey plate
+fn boolean_pixel(
+    uv: vec2<f32>,
+    coords: vec2<u32>,
+    texel: vec2<u32>,
+    pixel: f32,
+    period: vec2<f32>,
+) -> vec4<f32> {
+    var base = vec4(0.5, 0.5, 0.5, 1.0);
+    if (params.use_input != 0u) {
+        base = textureLoad(input_texture, texel, 0);
+    }
+    let noise_scale = noise_scale();
+
+    var color = base;
+    for (var c = 0u; c < 3u; c++) {
+        // Colour stocks render an independent disc layer per dye; monochrome shares one
+        var v = 0.0;
+        var intensity = 1.0;
+        if (params.is_color != 0u) {
+            let s = tile_scale(period, vec2(1.0 / params.channel_size[c]));
+            let pc = uv * (noise_scale * s) + channel_offset(c);
+            v = boolean_grain(pc, period * s, pixel * s.x, coords, base[c], c);
+            intensity = params.channel_intensity[c];
+        } else {
+            v = boolean_grain(uv * noise_scale, period, pixel, coords, base[c], 0u);
+        }
+        color[c] = mix(base[c], v, clamp(params.grain_amount * intensity, 0.0, 1.0));
+    }
+
+    return color;
+}
+
 @compute @workgroup_size(8, 8)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let dimensions = vec2<u32>(textureDimensions(output_texture));
-    let coords = vec2<u32>(global_id.xy);
+    let texel = vec2<u32>(global_id.xy);
+    let size = vec2<u32>(textureDimensions(output_texture));
 
-    if (coords.x >= dimensions.x || coords.y >= dimensions.y) {
+    if (texel.x >= size.x || texel.y >= size.y) {
         return;
     }
 
-    let uv = vec2<f32>(coords) / vec2<f32>(dimensions);
-    
-    // Use simplex noise for grain
-    let noise_scale = 100.0 / max(0.1, params.grain_size);
-    let p = uv * noise_scale + vec2(params.seed * 10.0, 0.0);
-    
-    let noise_val = simplex_noise(p);
-    
-    // Map noise [-1, 1] to [0, 1] and apply intensity
-    let grain = (noise_val * 0.5 + 0.5) * params.grain_amount;
-    
-    // Output grayscale for now
-    let color = vec4<f32>(grain, grain, grain, 1.0);
-    
-    textureStore(output_texture, coords, color);
+    seed_noise(params.seed);
+    set_noise_time(params.time);
+
+    // Noise is sampled in full-output coordinates, so tiles of a large render line up
+    let coords = texel + params.tile_origin;
+    let dimensions = vec2(params.width, params.height);
+    let uv = vec2<f32>(coords) / dimensions;
+
+    let noise_scale = noise_scale();
+    // Tiling wraps the lattice after one output width and height
+    let period = select(vec2(0.0), vec2(noise_scale), params.tileable != 0u);
+    let p = uv * noise_scale;
+
+    if (params.synthesis == SYNTHESIS_BOOLEAN) {
+        let pixel = noise_scale / dimensions.x;
+        // Filter samples are hashed per pixel, so tiling wraps them along with the lattice
+        let sample_coords = select(coords, coords % vec2<u32>(dimensions), params.tileable != 0u);
+        textureStore(output_texture, texel, boolean_pixel(uv, sample_coords, texel, pixel, period));
+        return;
+    }
+
+    let density = cluster_density(p, period);
+    let shared_grain = grain_field(p, period);
+
+    // Monochrome stocks have a single silver layer; colour stocks three dye layers
+    var grain = vec3(shared_grain);
+    if (params.is_color != 0u) {
+        var own = vec3(0.0);
+        for (var c = 0u; c < 3u; c++) {
+            let s = tile_scale(period, vec2(1.0 / params.channel_size[c]));
+            let pc = uv * (noise_scale * s) + channel_offset(c);
+            own[c] = dye_cloud(pc, period * s, layer_softness(c));
+        }
+        grain = correlate_layers(shared_grain, own);
+    }
+    let intensity = select(vec3(1.0), params.channel_intensity.rgb, params.is_color != 0u);
+
+    var color = vec4(0.0, 0.0, 0.0, 1.0);
+    if (params.use_input != 0u) {
+        let base = textureLoad(input_texture, texel, 0);
+        let luma = linear_to_srgb(
+            0.2126 * srgb_to_linear(base.r) + 0.7152 * srgb_to_linear(base.g) + 0.0722 * srgb_to_linear(base.b)
+        );
+        let weight = response_weight(luma);
+        for (var c = 0u; c < 3u; c++) {
+            color[c] = composite_channel(base[c], grain[c], density, intensity[c], weight);
+        }
+        color.a = base.a;
+    } else {
+        for (var c = 0u; c < 3u; c++) {
+            color[c] = shade_channel(grain[c], density, intensity[c]);
+        }
+    }
+
+    textureStore(output_texture, texel, color);
 }
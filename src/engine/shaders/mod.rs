@@ -1 +1,303 @@
-// Basic shader inclusions, potentially exporting constants or paths
+//! WGSL sources and the preprocessor that assembles them into shader modules.
+//!
+//! Directives start a line with `#`:
+//! - `#include "file.wgsl"` pastes another library file; each file is included once
+//! - `#define NAME [value]` defines a toggle; with a value, the identifier `NAME` is replaced by it
+//! - `#ifdef NAME` / `#ifndef NAME` / `#else` / `#endif` keep or drop lines by toggle
+//!
+//! Every output line remembers its file and line, so naga errors point at the original source.
+//!
+//! Shaders are normally compiled into the binary. For look development a [`ShaderWatcher`]
+//! reads them from a directory instead and re-reads them whenever a file is saved.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::SourceLocation;
+
+use crate::core::error::{GrainError, ShaderError};
+
+/// Shader files compiled into the binary
+pub const SHADER_FILES: [(&str, &str); 4] = [
+    ("random.wgsl", include_str!("random.wgsl")),
+    ("noise.wgsl", include_str!("noise.wgsl")),
+    ("grain.wgsl", include_str!("grain.wgsl")),
+    ("postprocess.wgsl", include_str!("postprocess.wgsl")),
+];
+
+/// Named WGSL sources that `#include` resolves against
+#[derive(Debug, Clone)]
+pub struct ShaderLibrary {
+    sources: BTreeMap<String, String>,
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ShaderLibrary {
+    /// The shaders compiled into the binary
+    pub fn builtin() -> Self {
+        Self {
+            sources: SHADER_FILES.iter().map(|(name, source)| (name.to_string(), source.to_string())).collect(),
+        }
+    }
+
+    /// The built-in shaders, with every file present in `dir` read from disk instead
+    pub fn load_dir(dir: &Path) -> Result<Self, GrainError> {
+        let mut library = Self::builtin();
+        for (name, _) in SHADER_FILES {
+            let path = dir.join(name);
+            if path.is_file() {
+                library.set_source(name, std::fs::read_to_string(&path)?);
+            }
+        }
+        Ok(library)
+    }
+
+    /// Add a file, or replace one with a newer version
+    pub fn set_source(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.sources.insert(name.into(), source.into());
+    }
+
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(String::as_str)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(String::as_str)
+    }
+
+    /// Expand `entry` and everything it includes into a single module;
+    /// `defines` behave like `#define` lines at the top of `entry`
+    pub fn preprocess(&self, entry: &str, defines: &[(&str, &str)]) -> Result<ProcessedShader, ShaderError> {
+        let mut expansion = Expansion {
+            library: self,
+            defines: defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            included: HashSet::new(),
+            output: ProcessedShader::default(),
+        };
+        expansion.expand(entry, None)?;
+        Ok(expansion.output)
+    }
+}
+
+/// Watches a directory of WGSL files for the development hot-reload mode
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    dir: PathBuf,
+    // Modification time of each of `SHADER_FILES` at the last poll
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl ShaderWatcher {
+    /// The first [`poll`](Self::poll) always loads the directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), modified: Vec::new() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Re-read the library if any shader file was saved, created or deleted since the
+    /// last poll; `None` when nothing changed
+    pub fn poll(&mut self) -> Option<Result<ShaderLibrary, GrainError>> {
+        let modified: Vec<Option<SystemTime>> = SHADER_FILES.iter()
+            .map(|(name, _)| std::fs::metadata(self.dir.join(name)).and_then(|m| m.modified()).ok())
+            .collect();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(ShaderLibrary::load_dir(&self.dir))
+    }
+}
+
+/// A preprocessed shader and where each of its lines came from
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedShader {
+    pub code: String,
+    files: Vec<String>,
+    // (index into `files`, 1-based line) for every line of `code`
+    lines: Vec<(usize, u32)>,
+}
+
+impl ProcessedShader {
+    /// Original file and line of a 1-based line of `code`
+    pub fn source_location(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, source_line) = *self.lines.get(line.checked_sub(1)? as usize)?;
+        Some((&self.files[file], source_line))
+    }
+
+    /// Parse and validate with naga, so broken WGSL is reported with its original
+    /// file and line rather than failing inside the device
+    pub fn validate(&self) -> Result<naga::Module, ShaderError> {
+        let module = naga::front::wgsl::parse_str(&self.code)
+            .map_err(|e| self.compile_error(e.location(&self.code), e.message().to_string()))?;
+
+        Validator::new(ValidationFlags::all(), Capabilities::default())
+            .validate(&module)
+            .map_err(|e| self.compile_error(e.location(&self.code), error_chain(e.as_inner())))?;
+
+        Ok(module)
+    }
+
+    fn compile_error(&self, location: Option<SourceLocation>, message: String) -> ShaderError {
+        let location = location
+            .and_then(|l| {
+                let (file, line) = self.source_location(l.line_number)?;
+                Some(format!("{file}:{line}:{}", l.line_position))
+            })
+            .unwrap_or_else(|| self.files.first().cloned().unwrap_or_default());
+        ShaderError::Compile { location, message }
+    }
+}
+
+/// naga nests the actual problem several errors deep
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// An open `#ifdef`/`#ifndef` block
+struct Condition {
+    active: bool,
+    seen_else: bool,
+    line: u32,
+}
+
+struct Expansion<'a> {
+    library: &'a ShaderLibrary,
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    output: ProcessedShader,
+}
+
+impl Expansion<'_> {
+    fn expand(&mut self, file: &str, included_from: Option<(&str, u32)>) -> Result<(), ShaderError> {
+        if !self.included.insert(file.to_string()) {
+            return Ok(());
+        }
+
+        let library = self.library;
+        let source = library.source(file).ok_or_else(|| {
+            let (from, line) = included_from.unwrap_or((file, 0));
+            ShaderError::Preprocess {
+                file: from.to_string(),
+                line,
+                message: format!("No shader named \"{file}\""),
+            }
+        })?;
+
+        let file_index = self.output.files.len();
+        self.output.files.push(file.to_string());
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index as u32 + 1;
+            let error = |message: String| ShaderError::Preprocess { file: file.to_string(), line, message };
+            let active = conditions.iter().all(|c| c.active);
+
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    self.emit(file_index, line, text);
+                }
+                continue;
+            };
+
+            let (keyword, argument) = split_word(directive);
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name = identifier(argument).ok_or_else(|| error(format!("#{keyword} needs a name")))?;
+                    let defined = self.defines.contains_key(name);
+                    conditions.push(Condition { active: defined == (keyword == "ifdef"), seen_else: false, line });
+                }
+                "else" => {
+                    let condition = conditions.last_mut()
+                        .filter(|c| !c.seen_else)
+                        .ok_or_else(|| error("#else without #ifdef".to_string()))?;
+                    condition.active = !condition.active;
+                    condition.seen_else = true;
+                }
+                "endif" => {
+                    conditions.pop().ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                }
+                // Anything else inside a disabled block is skipped unread
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = split_word(argument);
+                    let name = identifier(name).ok_or_else(|| error("#define needs a name".to_string()))?;
+                    self.defines.insert(name.to_string(), value.trim().to_string());
+                }
+                "include" => {
+                    let target = argument.trim().strip_prefix('"').and_then(|a| a.strip_suffix('"'))
+                        .ok_or_else(|| error("#include needs a quoted file name".to_string()))?;
+                    self.expand(target, Some((file, line)))?;
+                }
+                _ => return Err(error(format!("Unknown directive #{keyword}"))),
+            }
+        }
+
+        if let Some(open) = conditions.last() {
+            return Err(ShaderError::Preprocess {
+                file: file.to_string(),
+                line: open.line,
+                message: "#ifdef is never closed by #endif".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, file_index: usize, line: u32, text: &str) {
+        let text = self.substitute(text);
+        self.output.code.push_str(&text);
+        self.output.code.push('\n');
+        self.output.lines.push((file_index, line));
+    }
+
+    /// Replace every identifier that names a valued define
+    fn substitute(&self, text: &str) -> String {
+        if self.defines.values().all(String::is_empty) {
+            return text.to_string();
+        }
+
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            output.push_str(&rest[..start]);
+            let word = &rest[start..];
+            let end = word.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(word.len());
+            match self.defines.get(&word[..end]) {
+                Some(value) if !value.is_empty() => output.push_str(value),
+                _ => output.push_str(&word[..end]),
+            }
+            rest = &word[end..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// First whitespace-separated word and the remainder
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+fn identifier(text: &str) -> Option<&str> {
+    let name = text.trim();
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
@@ -1,36 +1,20 @@
 // ═══════════════════════════════════════════════════════════════════════════
 // GRAINFORGE NOISE LIBRARY
 // GPU-optimized noise functions for procedural grain generation
+//
+// Every lattice noise takes a `period` in lattice cells: lattice points are
+// wrapped into [0, period) before hashing so the noise tiles. A zero period
+// leaves the lattice unbounded.
+//
+// Every hash is keyed on `noise_key` and evolves with `noise_time`; call
+// `seed_noise` once per invocation before sampling any noise.
 // ═══════════════════════════════════════════════════════════════════════════
 
-// PCG Random Number Generator (high quality, GPU-optimized)
-fn pcg(v: u32) -> u32 {
-    var state = v * 747796405u + 2891336453u;
-    var word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
-    return (word >> 22u) ^ word;
-}
-
-fn pcg2d(v: vec2<u32>) -> vec2<u32> {
-    var p = v * vec2(1664525u, 1013904223u);
-    p.x += p.y * 1664525u;
-    p.y += p.x * 1664525u;
-    p ^= p >> vec2(16u);
-    p.x += p.y * 1664525u;
-    p.y += p.x * 1664525u;
-    p ^= p >> vec2(16u);
-    return p;
-}
+#include "random.wgsl"
 
-// Convert uint to float in [0, 1)
-fn uint_to_float(x: u32) -> f32 {
-    return f32(x) * (1.0 / 4294967296.0);
-}
-
-// Box-Muller transform: uniform -> gaussian
-fn box_muller(u1: f32, u2: f32) -> vec2<f32> {
-    let r = sqrt(-2.0 * log(max(u1, 1e-10)));
-    let theta = 6.283185307 * u2;
-    return vec2(r * cos(theta), r * sin(theta));
+// Wrap a lattice point into [0, period); zero period components are left unbounded
+fn wrap_cell(cell: vec2<f32>, period: vec2<f32>) -> vec2<f32> {
+    return select(cell, cell - period * floor(cell / period), period > vec2(0.0));
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -38,21 +22,21 @@ fn box_muller(u1: f32, u2: f32) -> vec2<f32> {
 // ─────────────────────────────────────────────────────────────────────────────
 
 fn hash21(p: vec2<f32>) -> f32 {
-    let h = pcg(bitcast<u32>(p.x) ^ (bitcast<u32>(p.y) * 747796405u));
-    return uint_to_float(h);
+    let h = pcg(bitcast<u32>(p.x) ^ noise_key.x ^ ((bitcast<u32>(p.y) ^ noise_key.y) * 747796405u));
+    return uint_to_float(evolve(h));
 }
 
-fn value_noise(p: vec2<f32>) -> f32 {
+fn value_noise(p: vec2<f32>, period: vec2<f32>) -> f32 {
     let i = floor(p);
     let f = fract(p);
     
     // Quintic interpolation (smoother than cubic)
     let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
     
-    let a = hash21(i);
-    let b = hash21(i + vec2(1.0, 0.0));
-    let c = hash21(i + vec2(0.0, 1.0));
-    let d = hash21(i + vec2(1.0, 1.0));
+    let a = hash21(wrap_cell(i, period));
+    let b = hash21(wrap_cell(i + vec2(1.0, 0.0), period));
+    let c = hash21(wrap_cell(i + vec2(0.0, 1.0), period));
+    let d = hash21(wrap_cell(i + vec2(1.0, 1.0), period));
     
     return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
 }
@@ -61,7 +45,12 @@ fn value_noise(p: vec2<f32>) -> f32 {
 // SIMPLEX NOISE (2D)
 // ─────────────────────────────────────────────────────────────────────────────
 
-fn simplex_noise(p: vec2<f32>) -> f32 {
+fn simplex_noise(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    // The skewed simplex lattice never repeats along the axes; tile on a sheared one instead
+    if (any(period > vec2(0.0))) {
+        return periodic_simplex_noise(p, period);
+    }
+
     const K1: f32 = 0.366025404;  // (sqrt(3)-1)/2
     const K2: f32 = 0.211324865;  // (3-sqrt(3))/6
     
@@ -85,8 +74,46 @@ fn simplex_noise(p: vec2<f32>) -> f32 {
     return dot(n, h) * 70.0;
 }
 
+// Matches the output range of the unbounded simplex noise
+const PERIODIC_SIMPLEX_SCALE: f32 = 8.8;
+
+// Simplex noise on a sheared lattice whose triangles tile axis-aligned periods
+// (after Gustavson & McEwan's psrdnoise). period.y must be even.
+fn periodic_simplex_noise(p: vec2<f32>, period: vec2<f32>) -> f32 {
+    let uv = vec2(p.x + p.y * 0.5, p.y);
+    let i = floor(uv);
+    let f = fract(uv);
+
+    let o = select(vec2(0.0, 1.0), vec2(1.0, 0.0), f.x > f.y);
+
+    let v0 = vec2(i.x - i.y * 0.5, i.y);
+    let v1 = v0 + vec2(o.x - o.y * 0.5, o.y);
+    let v2 = v0 + vec2(0.5, 1.0);
+
+    let a = p - v0;
+    let b = p - v1;
+    let c = p - v2;
+
+    var h = max(0.8 - vec3(dot(a, a), dot(b, b), dot(c, c)), vec3(0.0));
+    h = h * h * h * h;
+
+    let n = vec3(
+        dot(a, sheared_gradient(v0, period)),
+        dot(b, sheared_gradient(v1, period)),
+        dot(c, sheared_gradient(v2, period))
+    );
+
+    return dot(n, h) * PERIODIC_SIMPLEX_SCALE;
+}
+
+// Gradient at a sheared lattice vertex, hashed by its wrapped lattice index
+fn sheared_gradient(v: vec2<f32>, period: vec2<f32>) -> vec2<f32> {
+    let w = wrap_cell(v, period);
+    return hash22(floor(vec2(w.x + w.y * 0.5, w.y) + 0.5)) - 0.5;
+}
+
 fn hash22(p: vec2<f32>) -> vec2<f32> {
-    let h = pcg2d(vec2<u32>(bitcast<u32>(p.x), bitcast<u32>(p.y)));
+    let h = evolve2(pcg2d(vec2<u32>(bitcast<u32>(p.x), bitcast<u32>(p.y)) ^ noise_key));
     return vec2(uint_to_float(h.x), uint_to_float(h.y));
 }
 
@@ -96,10 +123,10 @@ fn hash22(p: vec2<f32>) -> vec2<f32> {
 
 struct VoronoiResult {
     distance: f32,      // Distance to nearest cell
-    cell_id: vec2<f32>, // ID of nearest cell (for coloring)
+    cell_id: vec2<f32>, // ID of nearest cell (for coloring); wrap it before hashing when tiling
 }
 
-fn voronoi(p: vec2<f32>, jitter: f32) -> VoronoiResult {
+fn voronoi(p: vec2<f32>, jitter: f32, period: vec2<f32>) -> VoronoiResult {
     let n = floor(p);
     let f = fract(p);
     
@@ -109,7 +136,7 @@ fn voronoi(p: vec2<f32>, jitter: f32) -> VoronoiResult {
     for (var j = -1; j <= 1; j++) {
         for (var i = -1; i <= 1; i++) {
             let g = vec2(f32(i), f32(j));
-            let o = hash22(n + g) * jitter;
+            let o = hash22(wrap_cell(n + g, period)) * jitter;
             let r = g + o - f;
             let d = dot(r, r);
             
@@ -132,6 +159,7 @@ fn fbm(
     octaves: i32,
     lacunarity: f32,
     persistence: f32,
+    period: vec2<f32>,
 ) -> f32 {
     var value = 0.0;
     var amplitude = 0.5;
@@ -139,7 +167,7 @@ fn fbm(
     var pos = p;
     
     for (var i = 0; i < octaves; i++) {
-        value += amplitude * simplex_noise(pos * frequency);
+        value += amplitude * simplex_noise(pos * frequency, period * frequency);
         frequency *= lacunarity;
         amplitude *= persistence;
     }
@@ -155,16 +183,17 @@ fn domain_warp(
     p: vec2<f32>,
     strength: f32,
     octaves: i32,
+    period: vec2<f32>,
 ) -> f32 {
     let q = vec2(
-        fbm(p + vec2(0.0, 0.0), octaves, 2.0, 0.5),
-        fbm(p + vec2(5.2, 1.3), octaves, 2.0, 0.5)
+        fbm(p + vec2(0.0, 0.0), octaves, 2.0, 0.5, period),
+        fbm(p + vec2(5.2, 1.3), octaves, 2.0, 0.5, period)
     );
     
     let r = vec2(
-        fbm(p + strength * q + vec2(1.7, 9.2), octaves, 2.0, 0.5),
-        fbm(p + strength * q + vec2(8.3, 2.8), octaves, 2.0, 0.5)
+        fbm(p + strength * q + vec2(1.7, 9.2), octaves, 2.0, 0.5, period),
+        fbm(p + strength * q + vec2(8.3, 2.8), octaves, 2.0, 0.5, period)
     );
     
-    return fbm(p + strength * r, octaves, 2.0, 0.5);
+    return fbm(p + strength * r, octaves, 2.0, 0.5, period);
 }
@@ -0,0 +1,103 @@
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAINFORGE POST-PROCESSING
+// Passes run by `RenderPipeline` after grain generation. Each effect in a chain
+// is a few of these passes; `engine/post_process.rs` mirrors them on the CPU.
+// ═══════════════════════════════════════════════════════════════════════════
+
+#ifndef OUTPUT_FORMAT
+#define OUTPUT_FORMAT rgba8unorm
+#endif
+
+struct PostParams {
+    // Extent of real pixels in the target; blurs clamp to it
+    bounds: vec2<u32>,
+    // Step between blur taps: (1, 0) or (0, 1)
+    direction: vec2<u32>,
+    tint: vec4<f32>,
+    sigma: f32,
+    taps: u32,
+    // Halation strength or unsharp amount
+    amount: f32,
+    threshold: f32,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+// Second input of the combining passes; the blurred image
+@group(0) @binding(1) var aux: texture_2d<f32>;
+@group(0) @binding(2) var output_texture: texture_storage_2d<OUTPUT_FORMAT, write>;
+@group(0) @binding(3) var<uniform> params: PostParams;
+
+const LUMA = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+fn in_target(id: vec3<u32>) -> bool {
+    let size = textureDimensions(output_texture);
+    return id.x < size.x && id.y < size.y;
+}
+
+@compute @workgroup_size(8, 8)
+fn copy(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (!in_target(id)) {
+        return;
+    }
+    let texel = vec2<i32>(id.xy);
+    textureStore(output_texture, texel, textureLoad(source, texel, 0));
+}
+
+// One direction of a separable Gaussian
+@compute @workgroup_size(8, 8)
+fn blur(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (!in_target(id)) {
+        return;
+    }
+    let texel = vec2<i32>(id.xy);
+    let last = vec2<i32>(params.bounds) - 1;
+    let step = vec2<i32>(params.direction);
+    let taps = i32(params.taps);
+
+    var sum = vec4(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i++) {
+        let w = exp(-f32(i * i) / (2.0 * params.sigma * params.sigma));
+        let p = clamp(texel + step * i, vec2(0), last);
+        sum += w * textureLoad(source, p, 0);
+        total += w;
+    }
+    textureStore(output_texture, texel, sum / total);
+}
+
+// Highlights above the threshold, tinted; the halation glow before blurring
+@compute @workgroup_size(8, 8)
+fn highlights(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (!in_target(id)) {
+        return;
+    }
+    let texel = vec2<i32>(id.xy);
+    let c = textureLoad(source, texel, 0);
+    let luma = LUMA.r * c.r + LUMA.g * c.g + LUMA.b * c.b;
+    let k = max(luma - params.threshold, 0.0) / max(1.0 - params.threshold, 1e-4);
+    textureStore(output_texture, texel, vec4(params.tint.rgb * k, 0.0));
+}
+
+// Screen the blurred glow over the image
+@compute @workgroup_size(8, 8)
+fn screen(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (!in_target(id)) {
+        return;
+    }
+    let texel = vec2<i32>(id.xy);
+    let base = textureLoad(source, texel, 0);
+    let glow = clamp(textureLoad(aux, texel, 0).rgb * params.amount, vec3(0.0), vec3(1.0));
+    textureStore(output_texture, texel, vec4(1.0 - (1.0 - base.rgb) * (1.0 - glow), base.a));
+}
+
+// Push each pixel away from its blurred surroundings
+@compute @workgroup_size(8, 8)
+fn unsharp(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (!in_target(id)) {
+        return;
+    }
+    let texel = vec2<i32>(id.xy);
+    let base = textureLoad(source, texel, 0);
+    let blurred = textureLoad(aux, texel, 0);
+    textureStore(output_texture, texel, vec4(base.rgb + params.amount * (base.rgb - blurred.rgb), base.a));
+}
@@ -0,0 +1,69 @@
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAINFORGE RANDOM NUMBERS
+// Integer hashes and distributions shared by every shader; mirrored by
+// engine/cpu_noise.rs
+// ═══════════════════════════════════════════════════════════════════════════
+
+// PCG Random Number Generator (high quality, GPU-optimized)
+fn pcg(v: u32) -> u32 {
+    var state = v * 747796405u + 2891336453u;
+    var word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn pcg2d(v: vec2<u32>) -> vec2<u32> {
+    var p = v * vec2(1664525u, 1013904223u);
+    p.x += p.y * 1664525u;
+    p.y += p.x * 1664525u;
+    p ^= p >> vec2(16u);
+    p.x += p.y * 1664525u;
+    p.y += p.x * 1664525u;
+    p ^= p >> vec2(16u);
+    return p;
+}
+
+// Key mixed into every noise hash, derived from the render seed
+var<private> noise_key: vec2<u32>;
+// Time in re-rolls of each lattice point; animated sequences advance it
+var<private> noise_time: f32;
+
+// Key all noise on a 64-bit seed given as (low, high) words; seed 0 leaves the hashes unkeyed.
+// Time starts at 0.
+fn seed_noise(seed: vec2<u32>) {
+    noise_key = pcg2d(seed);
+    noise_time = 0.0;
+}
+
+// Move all noise to `time`; the pattern at time 0 is the still one
+fn set_noise_time(time: f32) {
+    noise_time = time;
+}
+
+// Number of times the lattice point hashed to `h` has re-rolled by `noise_time`. Each point
+// re-rolls at its own phase, so the pattern changes point by point rather than all at once.
+fn noise_epoch(h: u32) -> u32 {
+    return u32(floor(noise_time + uint_to_float(pcg(h))));
+}
+
+// A lattice hash at the current time
+fn evolve(h: u32) -> u32 {
+    let epoch = noise_epoch(h);
+    return select(pcg(h ^ pcg(epoch)), h, epoch == 0u);
+}
+
+fn evolve2(h: vec2<u32>) -> vec2<u32> {
+    let epoch = noise_epoch(h.x);
+    return select(pcg2d(h ^ vec2(epoch, pcg(epoch))), h, epoch == 0u);
+}
+
+// Convert uint to float in [0, 1)
+fn uint_to_float(x: u32) -> f32 {
+    return f32(x) * (1.0 / 4294967296.0);
+}
+
+// Box-Muller transform: uniform -> gaussian
+fn box_muller(u1: f32, u2: f32) -> vec2<f32> {
+    let r = sqrt(-2.0 * log(max(u1, 1e-10)));
+    let theta = 6.283185307 * u2;
+    return vec2(r * cos(theta), r * sin(theta));
+}
@@ -1,2 +1,46 @@
+use image::Rgba32FImage;
+use wgpu::{
+    Device, Extent3d, Origin3d, Queue, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
 // Placeholder for texture manager
 pub struct TextureManager {}
+
+/// Upload a float RGBA image into a sampled texture (values stay sRGB encoded)
+pub fn upload_rgba32f(device: &Device, queue: &Queue, image: &Rgba32FImage, label: &str) -> Texture {
+    let size = Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba32Float,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        bytemuck::cast_slice(image.as_raw()),
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(16 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+
+    texture
+}
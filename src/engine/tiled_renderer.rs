@@ -0,0 +1,338 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use image::Rgba32FImage;
+use wgpu::{Device, Queue};
+
+use crate::core::error::GrainError;
+use crate::core::film_stock::PostProcessChain;
+use crate::engine::cpu_renderer::CpuGrainRenderer;
+use crate::engine::gpu_context::GpuContext;
+use crate::engine::grain_renderer::{GrainParams, GrainRenderer};
+use crate::engine::output_format::OutputFormat;
+use crate::export::image_export;
+
+/// Edge length of the core region each tile contributes to the output
+pub const DEFAULT_TILE_SIZE: u32 = 2048;
+/// Extra pixels rendered around each tile's core, so neighbourhood filters see
+/// real pixels instead of the tile edge; per-pixel grain ignores it
+pub const DEFAULT_TILE_OVERLAP: u32 = 32;
+
+/// Rectangle of output pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One tile of a [`TilePlan`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub column: u32,
+    pub row: u32,
+    /// Output pixels this tile is responsible for
+    pub core: TileRegion,
+    /// Output pixel at the top-left of the tile's render target, `overlap` pixels before
+    /// the core unless that would leave the image
+    pub origin: [u32; 2],
+}
+
+impl Tile {
+    /// Position of the core region inside the tile's render target
+    pub fn core_offset(&self) -> [u32; 2] {
+        [self.core.x - self.origin[0], self.core.y - self.origin[1]]
+    }
+}
+
+/// Splits a large output into a grid of overlapping tiles that fit in one render target.
+///
+/// Cores partition the output exactly; every tile is rendered into a square target of
+/// [`TilePlan::target_size`] pixels, and whatever falls outside the core is discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilePlan {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub overlap: u32,
+}
+
+impl TilePlan {
+    pub fn new(width: u32, height: u32, tile_size: u32, overlap: u32) -> Result<Self, GrainError> {
+        if width == 0 || height == 0 {
+            return Err(GrainError::InvalidParameter {
+                name: "size".to_string(),
+                reason: format!("Output must not be empty, got {width}x{height}"),
+            });
+        }
+        if tile_size == 0 {
+            return Err(GrainError::InvalidParameter {
+                name: "tile_size".to_string(),
+                reason: "Tiles must be at least one pixel".to_string(),
+            });
+        }
+        Ok(Self { width, height, tile_size, overlap })
+    }
+
+    /// Largest plan with the default overlap whose render target fits within `max_texture_size`
+    pub fn for_max_texture_size(width: u32, height: u32, max_texture_size: u32) -> Result<Self, GrainError> {
+        let tile_size = DEFAULT_TILE_SIZE.min(max_texture_size.saturating_sub(2 * DEFAULT_TILE_OVERLAP));
+        Self::new(width, height, tile_size, DEFAULT_TILE_OVERLAP)
+    }
+
+    /// Edge length of the render target each tile is drawn into
+    pub fn target_size(&self) -> u32 {
+        self.tile_size + 2 * self.overlap
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.width.div_ceil(self.tile_size)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.height.div_ceil(self.tile_size)
+    }
+
+    pub fn tile(&self, column: u32, row: u32) -> Tile {
+        let x = column * self.tile_size;
+        let y = row * self.tile_size;
+        Tile {
+            column,
+            row,
+            core: TileRegion {
+                x,
+                y,
+                width: self.tile_size.min(self.width - x),
+                height: self.tile_size.min(self.height - y),
+            },
+            origin: [x.saturating_sub(self.overlap), y.saturating_sub(self.overlap)],
+        }
+    }
+
+    /// Tiles of one row of the grid, left to right
+    pub fn row(&self, row: u32) -> impl Iterator<Item = Tile> + '_ {
+        (0..self.columns()).map(move |column| self.tile(column, row))
+    }
+
+    /// Every tile in row-major order, the order the exporter writes them
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        (0..self.rows()).flat_map(move |row| self.row(row))
+    }
+}
+
+/// Where tiles are rendered
+enum TileBackend {
+    Gpu {
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        renderer: Box<GrainRenderer>,
+    },
+    Cpu(CpuGrainRenderer),
+}
+
+/// Renders outputs larger than a single render target, one [`Tile`] at a time.
+///
+/// Noise is sampled in full-output coordinates, so the assembled image is identical to a
+/// single render of the same size. Beyond the plate, which is held whole, memory peaks at one
+/// render target plus one full-width band: `width * tile_size` pixels in the output format.
+pub struct TiledRenderer {
+    plan: TilePlan,
+    format: OutputFormat,
+    post_process: PostProcessChain,
+    backend: TileBackend,
+}
+
+impl TiledRenderer {
+    /// Render tiles on the GPU; the plan's target must fit the device's texture limit
+    pub fn gpu(context: &GpuContext, plan: TilePlan, format: OutputFormat) -> Result<Self, GrainError> {
+        let max_size = context.device.limits().max_texture_dimension_2d;
+        if plan.target_size() > max_size {
+            return Err(GrainError::InvalidParameter {
+                name: "tile_size".to_string(),
+                reason: format!(
+                    "Tiles of {} pixels with {} overlap need a {}px target, but the device allows {}px",
+                    plan.tile_size, plan.overlap, plan.target_size(), max_size
+                ),
+            });
+        }
+
+        let renderer = GrainRenderer::with_format(&context.device, plan.target_size(), plan.target_size(), format)?;
+        Ok(Self {
+            plan,
+            format,
+            post_process: PostProcessChain::default(),
+            backend: TileBackend::Gpu {
+                device: context.device.clone(),
+                queue: context.queue.clone(),
+                renderer: Box::new(renderer),
+            },
+        })
+    }
+
+    /// Render tiles with the CPU reference renderer
+    pub fn cpu(plan: TilePlan, format: OutputFormat) -> Self {
+        let renderer = CpuGrainRenderer::with_format(plan.target_size(), plan.target_size(), format)
+            .expect("tile plans are never empty");
+        Self { plan, format, post_process: PostProcessChain::default(), backend: TileBackend::Cpu(renderer) }
+    }
+
+    /// Run `chain` over every tile before its core is cropped. The tile overlap must cover
+    /// the chain's reach, or tiles would filter their own edges instead of their neighbours.
+    pub fn with_post_process(mut self, chain: PostProcessChain) -> Result<Self, GrainError> {
+        if chain.reach() > self.plan.overlap {
+            return Err(GrainError::InvalidParameter {
+                name: "tile_overlap".to_string(),
+                reason: format!(
+                    "Post-processing reaches {} pixels but tiles only overlap by {}",
+                    chain.reach(), self.plan.overlap
+                ),
+            });
+        }
+        self.post_process = chain;
+        Ok(self)
+    }
+
+    pub fn plan(&self) -> &TilePlan {
+        &self.plan
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Render one tile and return its core region, tightly packed in the renderer's format.
+    ///
+    /// `plate`, if any, must be the size of the whole output.
+    pub fn render_tile(
+        &mut self,
+        params: &GrainParams,
+        plate: Option<&Rgba32FImage>,
+        tile: &Tile,
+    ) -> Result<Vec<u8>, GrainError> {
+        check_plate(plate, &self.plan)?;
+        let params = GrainParams { width: self.plan.width as f32, height: self.plan.height as f32, ..*params };
+        let input = plate.map(|plate| plate_region(plate, tile.origin, self.plan.target_size()));
+        // Part of the target inside the output; the rest only pads tiles on the far edges
+        let valid = [
+            self.plan.target_size().min(self.plan.width - tile.origin[0]),
+            self.plan.target_size().min(self.plan.height - tile.origin[1]),
+        ];
+
+        let pixels = match &mut self.backend {
+            TileBackend::Gpu { device, queue, renderer } => {
+                match &input {
+                    Some(input) => renderer.set_input_image(device, queue, input)?,
+                    None if renderer.has_input() => renderer.clear_input(device),
+                    None => {}
+                }
+                renderer.render_tile(device, queue, &params, tile.origin);
+                renderer.post_process_tile(device, queue, &self.post_process, valid)?;
+                renderer.read_pixels(device, queue)?
+            }
+            TileBackend::Cpu(renderer) => {
+                match input {
+                    Some(input) => renderer.set_input_image(input)?,
+                    None => renderer.clear_input(),
+                }
+                renderer.render_tile(&params, tile.origin);
+                renderer.post_process_tile(&self.post_process, valid);
+                renderer.output().to_vec()
+            }
+        };
+
+        Ok(crop_core(&pixels, self.plan.target_size(), tile, self.format.bytes_per_pixel()))
+    }
+
+    /// Render one row of tiles and stitch their cores into a full-width band of rows
+    pub fn render_band(
+        &mut self,
+        params: &GrainParams,
+        plate: Option<&Rgba32FImage>,
+        row: u32,
+    ) -> Result<Vec<u8>, GrainError> {
+        let pixel_bytes = self.format.bytes_per_pixel();
+        let row_bytes = self.plan.width as usize * pixel_bytes;
+        let plan = self.plan;
+        let band_height = plan.tile(0, row).core.height as usize;
+        let mut band = vec![0; row_bytes * band_height];
+
+        for tile in plan.row(row) {
+            let core = self.render_tile(params, plate, &tile)?;
+            let tile_row_bytes = tile.core.width as usize * pixel_bytes;
+            let x_offset = tile.core.x as usize * pixel_bytes;
+            for (y, src) in core.chunks_exact(tile_row_bytes).enumerate() {
+                let start = y * row_bytes + x_offset;
+                band[start..start + tile_row_bytes].copy_from_slice(src);
+            }
+        }
+
+        Ok(band)
+    }
+
+    /// Render every band top to bottom, calling `on_band` as each one finishes
+    pub fn render_bands(
+        &mut self,
+        params: &GrainParams,
+        plate: Option<&Rgba32FImage>,
+        mut on_band: impl FnMut(Vec<u8>) -> Result<(), GrainError>,
+    ) -> Result<(), GrainError> {
+        for row in 0..self.plan.rows() {
+            on_band(self.render_band(params, plate, row)?)?;
+        }
+        Ok(())
+    }
+
+    /// Render the whole output straight into an image file, band by band
+    pub fn render_to_file(
+        &mut self,
+        params: &GrainParams,
+        plate: Option<&Rgba32FImage>,
+        output_path: &Path,
+    ) -> Result<(), GrainError> {
+        // Checked up front so a bad plate never leaves a partial file behind
+        check_plate(plate, &self.plan)?;
+        let (width, height, format) = (self.plan.width, self.plan.height, self.format);
+        image_export::export_bands(output_path, width, height, format, |write| {
+            self.render_bands(params, plate, write)
+        })
+    }
+}
+
+/// Tiles sample the plate in output coordinates, so it must be exactly the output size
+fn check_plate(plate: Option<&Rgba32FImage>, plan: &TilePlan) -> Result<(), GrainError> {
+    match plate {
+        Some(plate) if plate.dimensions() != (plan.width, plan.height) => Err(GrainError::InvalidParameter {
+            name: "plate".to_string(),
+            reason: format!(
+                "Plate is {}x{} but the output is {}x{}",
+                plate.width(), plate.height(), plan.width, plan.height
+            ),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The part of the plate under a tile's render target; pixels past the plate edge
+/// repeat the edge, and are discarded with the rest of the overlap
+fn plate_region(plate: &Rgba32FImage, origin: [u32; 2], size: u32) -> Rgba32FImage {
+    Rgba32FImage::from_fn(size, size, |x, y| {
+        let px = (origin[0] + x).min(plate.width() - 1);
+        let py = (origin[1] + y).min(plate.height() - 1);
+        *plate.get_pixel(px, py)
+    })
+}
+
+/// Copy a tile's core out of its square render target
+fn crop_core(pixels: &[u8], target_size: u32, tile: &Tile, pixel_bytes: usize) -> Vec<u8> {
+    let [offset_x, offset_y] = tile.core_offset();
+    let target_row_bytes = target_size as usize * pixel_bytes;
+    let core_row_bytes = tile.core.width as usize * pixel_bytes;
+
+    let mut core = Vec::with_capacity(core_row_bytes * tile.core.height as usize);
+    for y in offset_y..offset_y + tile.core.height {
+        let start = y as usize * target_row_bytes + offset_x as usize * pixel_bytes;
+        core.extend_from_slice(&pixels[start..start + core_row_bytes]);
+    }
+    core
+}
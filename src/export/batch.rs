@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+
+use crate::core::error::{ExportError, GrainError};
+use crate::core::export::ExportFormat;
+use crate::core::film_stock::FilmStock;
+use crate::core::import;
+use crate::engine::grain_renderer::{BlendMode, GrainParams, DEFAULT_BOOLEAN_SAMPLES};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::tiled_renderer::{TilePlan, TiledRenderer, DEFAULT_TILE_OVERLAP, DEFAULT_TILE_SIZE};
+use crate::export::sequence_export::export_still;
+
+/// Output name used when none is given: the input's name with a suffix, in its own format
+pub const DEFAULT_NAMING: &str = "{name}_grain.{ext}";
+
+/// Grain a folder of stills with one stock.
+///
+/// Every file matching `input` is composited with the stock and written to `output_dir`
+/// under a name built from `naming`:
+///
+/// - `{name}`: the input file name without its extension
+/// - `{ext}`: the input extension
+/// - `{stock}`: the stock name
+///
+/// The output extension picks the file format. Files render on the CPU, several at once.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    /// Glob of input images, e.g. `stills/*.tif`
+    pub input: String,
+    pub stock: FilmStock,
+    pub output_dir: PathBuf,
+    pub naming: String,
+    pub format: OutputFormat,
+    pub blend_mode: BlendMode,
+    /// Boolean-model samples per pixel
+    pub samples: u32,
+    /// Re-render files whose output already exists
+    pub force: bool,
+    /// Files processed at once; 0 uses every core
+    pub threads: usize,
+}
+
+/// What happened to one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOutcome {
+    Written,
+    /// The output was already there and the job was not forced
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct FileReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub result: Result<FileOutcome, GrainError>,
+}
+
+/// Results of a batch, in input order. A cancelled batch lists only the files it got to.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub files: Vec<FileReport>,
+    pub cancelled: bool,
+}
+
+impl BatchReport {
+    pub fn written(&self) -> usize {
+        self.count(|result| matches!(result, Ok(FileOutcome::Written)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|result| matches!(result, Ok(FileOutcome::Skipped)))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (&Path, &GrainError)> {
+        self.files.iter().filter_map(|file| file.result.as_ref().err().map(|e| (file.input.as_path(), e)))
+    }
+
+    /// One line for a status bar or log
+    pub fn summary(&self) -> String {
+        let failed = self.failures().count();
+        let mut summary = format!("{} written, {} skipped, {failed} failed", self.written(), self.skipped());
+        if self.cancelled {
+            summary.push_str(" (cancelled)");
+        }
+        summary
+    }
+
+    fn count(&self, matches: impl Fn(&Result<FileOutcome, GrainError>) -> bool) -> usize {
+        self.files.iter().filter(|file| matches(&file.result)).count()
+    }
+}
+
+impl BatchJob {
+    pub fn new(input: impl Into<String>, stock: FilmStock, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            stock,
+            output_dir: output_dir.into(),
+            naming: DEFAULT_NAMING.to_string(),
+            format: OutputFormat::default(),
+            blend_mode: BlendMode::default(),
+            samples: DEFAULT_BOOLEAN_SAMPLES,
+            force: false,
+            threads: 0,
+        }
+    }
+
+    /// Files matching the input glob, sorted
+    pub fn inputs(&self) -> Result<Vec<PathBuf>, GrainError> {
+        let paths = glob::glob(&self.input).map_err(|e| GrainError::InvalidParameter {
+            name: "input".to_string(),
+            reason: format!("Invalid pattern '{}': {e}", self.input),
+        })?;
+        let mut inputs: Vec<PathBuf> = paths.filter_map(Result::ok).filter(|path| path.is_file()).collect();
+        inputs.sort();
+        Ok(inputs)
+    }
+
+    /// Where the grained version of `input` is written
+    pub fn output_path(&self, input: &Path) -> Result<PathBuf, GrainError> {
+        let invalid = |reason: String| GrainError::InvalidParameter { name: "naming".to_string(), reason };
+        let name = input.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let ext = input.extension().and_then(|e| e.to_str()).unwrap_or_default();
+
+        let mut file_name = String::new();
+        let mut rest = self.naming.as_str();
+        while let Some(open) = rest.find('{') {
+            file_name.push_str(&rest[..open]);
+            let close = rest[open..].find('}')
+                .ok_or_else(|| invalid(format!("Unclosed '{{' in '{}'", self.naming)))?;
+            match &rest[open + 1..open + close] {
+                "name" => file_name.push_str(name),
+                "ext" => file_name.push_str(ext),
+                "stock" => file_name.push_str(&self.stock.meta.name),
+                token => return Err(invalid(format!("Unknown token '{{{token}}}' (allowed: name, ext, stock)"))),
+            }
+            rest = &rest[open + close + 1..];
+        }
+        file_name.push_str(rest);
+
+        if file_name.is_empty() || file_name.contains(['/', '\\']) {
+            return Err(invalid(format!("'{file_name}' is not a file name")));
+        }
+        Ok(self.output_dir.join(file_name))
+    }
+
+    /// Process every input, calling `on_file` from the worker threads as each one finishes.
+    ///
+    /// Problems with the job itself (a bad pattern, no matching files, outputs that would
+    /// collide or overwrite an input) fail the whole batch before anything is written; a file
+    /// that fails to load or render is reported and the rest carry on. `cancel` is checked
+    /// before each file is started.
+    pub fn run(&self, cancel: &AtomicBool, on_file: impl Fn(&FileReport) + Sync) -> Result<BatchReport, GrainError> {
+        let inputs = self.inputs()?;
+        if inputs.is_empty() {
+            return Err(GrainError::InvalidParameter {
+                name: "input".to_string(),
+                reason: format!("No files match '{}'", self.input),
+            });
+        }
+        self.run_files(&inputs, cancel, on_file)
+    }
+
+    /// Like [`run`](Self::run), for a given list of inputs instead of the glob's matches
+    pub fn run_files(
+        &self,
+        inputs: &[PathBuf],
+        cancel: &AtomicBool,
+        on_file: impl Fn(&FileReport) + Sync,
+    ) -> Result<BatchReport, GrainError> {
+        let outputs = inputs.iter().map(|input| self.output_path(input)).collect::<Result<Vec<_>, _>>()?;
+        self.check_outputs(inputs, &outputs)?;
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let threads = match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let next = AtomicUsize::new(0);
+        let reports: Mutex<Vec<Option<FileReport>>> = Mutex::new((0..inputs.len()).map(|_| None).collect());
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads.min(inputs.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= inputs.len() || cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let report = self.process(&inputs[index], &outputs[index]);
+                    on_file(&report);
+                    reports.lock()[index] = Some(report);
+                });
+            }
+        });
+
+        let files: Vec<FileReport> = reports.into_inner().into_iter().flatten().collect();
+        Ok(BatchReport { cancelled: files.len() < inputs.len(), files })
+    }
+
+    fn check_outputs(&self, inputs: &[PathBuf], outputs: &[PathBuf]) -> Result<(), GrainError> {
+        let invalid = |reason: String| GrainError::InvalidParameter { name: "naming".to_string(), reason };
+        if let Some(output) = outputs.first() {
+            output.extension()
+                .and_then(|e| e.to_str())
+                .and_then(ExportFormat::from_extension)
+                .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+        }
+
+        let sources: HashSet<PathBuf> = inputs.iter().filter_map(|input| input.canonicalize().ok()).collect();
+        let mut seen = HashSet::new();
+        for output in outputs {
+            if !seen.insert(output) {
+                return Err(invalid(format!("Several inputs would be written to {}", output.display())));
+            }
+            if output.canonicalize().is_ok_and(|output| sources.contains(&output)) {
+                return Err(invalid(format!("{} would overwrite an input", output.display())));
+            }
+        }
+        Ok(())
+    }
+
+    fn process(&self, input: &Path, output: &Path) -> FileReport {
+        let result = if !self.force && output.exists() {
+            Ok(FileOutcome::Skipped)
+        } else {
+            self.render(input, output).map(|()| FileOutcome::Written)
+        };
+        FileReport { input: input.to_path_buf(), output: output.to_path_buf(), result }
+    }
+
+    fn render(&self, input: &Path, output: &Path) -> Result<(), GrainError> {
+        let plate = import::load_plate(input)?;
+        let (width, height) = plate.dimensions();
+        let post_process = self.stock.post_process.clone();
+        let plan = TilePlan::new(width, height, DEFAULT_TILE_SIZE, DEFAULT_TILE_OVERLAP.max(post_process.reach()))?;
+        let mut renderer = TiledRenderer::cpu(plan, self.format).with_post_process(post_process)?;
+        let params = GrainParams::from_film_stock(&self.stock, width, height)
+            .with_blend_mode(self.blend_mode)
+            .with_samples(self.samples);
+
+        // Written under a temporary name, so an interrupted batch never leaves a partial
+        // file that a later run would skip
+        let partial = partial_path(output);
+        let result = export_still(&mut renderer, &params, Some(&plate), &self.stock.damage, &partial)
+            .and_then(|()| Ok(std::fs::rename(&partial, output)?));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        result
+    }
+}
+
+/// Hidden sibling of `output` with the same extension
+fn partial_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let ext = output.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    output.with_file_name(format!(".{stem}.partial.{ext}"))
+}
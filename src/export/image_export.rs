@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use tiff::encoder::{colortype, TiffEncoder};
+
+use crate::core::error::{ExportError, GrainError};
+use crate::core::export::ExportFormat;
+use crate::engine::output_format::OutputFormat;
+use crate::utils::math::unorm16;
+use crate::utils::validation::validate_export_path;
+
+/// Write an image that arrives as full-width bands of rows, top to bottom, without
+/// ever holding the whole frame in memory.
+///
+/// `produce` is handed a callback to call once per band; each band is tightly packed rows
+/// in `format`. 8-bit renders are written as 8-bit, float renders as 16 bits per channel,
+/// matching [`export_image_f32`](crate::core::export::export_image_f32). Only PNG and TIFF
+/// can be streamed.
+pub fn export_bands<F>(
+    output_path: &Path,
+    width: u32,
+    height: u32,
+    format: OutputFormat,
+    produce: F,
+) -> Result<(), GrainError>
+where
+    F: FnOnce(&mut dyn FnMut(Vec<u8>) -> Result<(), GrainError>) -> Result<(), GrainError>,
+{
+    let validated_path = validate_export_path(output_path)?;
+
+    let export_format = validated_path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(ExportFormat::from_extension)
+        .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+
+    let file = BufWriter::new(File::create(&validated_path)?);
+    let mut bands = BandCounter::new(width, height, format);
+
+    match export_format {
+        ExportFormat::Png => {
+            let mut encoder = png::Encoder::new(file, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(match format {
+                OutputFormat::Rgba8 => png::BitDepth::Eight,
+                _ => png::BitDepth::Sixteen,
+            });
+            let mut stream = encoder.write_header()
+                .and_then(|writer| writer.into_stream_writer())
+                .map_err(write_failed)?;
+
+            produce(&mut |band| {
+                bands.accept(&band)?;
+                let bytes = match format {
+                    OutputFormat::Rgba8 => band,
+                    // PNG stores 16-bit samples big-endian
+                    _ => to_unorm16(format, &band).into_iter().flat_map(u16::to_be_bytes).collect(),
+                };
+                stream.write_all(&bytes).map_err(write_failed)
+            })?;
+
+            bands.finish()?;
+            stream.finish().map_err(write_failed)?;
+        }
+        ExportFormat::Tiff => {
+            let mut tiff = TiffEncoder::new(file).map_err(write_failed)?;
+            match format {
+                OutputFormat::Rgba8 => {
+                    let mut image = tiff.new_image::<colortype::RGBA8>(width, height).map_err(write_failed)?;
+                    produce(&mut |band| {
+                        let rows = bands.accept(&band)?;
+                        // One strip per band; only the last band may be shorter
+                        if bands.first() {
+                            image.rows_per_strip(rows).map_err(write_failed)?;
+                        }
+                        image.write_strip(&band).map_err(write_failed)
+                    })?;
+                    bands.finish()?;
+                    image.finish().map_err(write_failed)?;
+                }
+                _ => {
+                    let mut image = tiff.new_image::<colortype::RGBA16>(width, height).map_err(write_failed)?;
+                    produce(&mut |band| {
+                        let rows = bands.accept(&band)?;
+                        if bands.first() {
+                            image.rows_per_strip(rows).map_err(write_failed)?;
+                        }
+                        image.write_strip(&to_unorm16(format, &band)).map_err(write_failed)
+                    })?;
+                    bands.finish()?;
+                    image.finish().map_err(write_failed)?;
+                }
+            }
+        }
+        #[cfg(feature = "exr")]
+        ExportFormat::Exr => {
+            return Err(GrainError::Export(ExportError::WriteFailed(
+                "EXR cannot be streamed band by band; export large renders as PNG or TIFF".to_string(),
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that bands add up to exactly the declared image
+struct BandCounter {
+    row_bytes: usize,
+    height: u32,
+    rows_written: u32,
+    bands_written: u32,
+}
+
+impl BandCounter {
+    fn new(width: u32, height: u32, format: OutputFormat) -> Self {
+        Self {
+            row_bytes: width as usize * format.bytes_per_pixel(),
+            height,
+            rows_written: 0,
+            bands_written: 0,
+        }
+    }
+
+    /// Count a band, returning how many rows it holds
+    fn accept(&mut self, band: &[u8]) -> Result<u32, GrainError> {
+        if band.is_empty() || !band.len().is_multiple_of(self.row_bytes) {
+            return Err(write_failed(format!(
+                "Band of {} bytes is not a whole number of {}-byte rows", band.len(), self.row_bytes
+            )));
+        }
+        let rows = (band.len() / self.row_bytes) as u32;
+        if self.rows_written + rows > self.height {
+            return Err(write_failed(format!("Bands overrun the image height of {}", self.height)));
+        }
+        self.rows_written += rows;
+        self.bands_written += 1;
+        Ok(rows)
+    }
+
+    /// Whether the band just accepted was the first
+    fn first(&self) -> bool {
+        self.bands_written == 1
+    }
+
+    fn finish(&self) -> Result<(), GrainError> {
+        if self.rows_written != self.height {
+            return Err(write_failed(format!(
+                "Only {} of {} rows were written", self.rows_written, self.height
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn to_unorm16(format: OutputFormat, band: &[u8]) -> Vec<u16> {
+    format.decode(band).into_iter().map(unorm16).collect()
+}
+
+fn write_failed(e: impl ToString) -> GrainError {
+    GrainError::Export(ExportError::WriteFailed(e.to_string()))
+}
@@ -2,3 +2,5 @@ pub mod image_export;
 pub mod sequence_export;
 pub mod shader_export;
 pub mod preset_export;
+pub mod batch;
+pub mod watch_folder;
@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use image::{DynamicImage, Rgba32FImage};
+
+use crate::core::error::{ExportError, GrainError};
+use crate::core::export::ExportFormat;
+use crate::core::film_stock::DamageParameters;
+use crate::engine::damage::{DamageFrame, DamageGenerator};
+use crate::engine::grain_renderer::GrainParams;
+use crate::engine::output_format::OutputFormat;
+use crate::engine::tiled_renderer::TiledRenderer;
+use crate::utils::math::{mix64, SPLITMIX64_GAMMA};
+
+/// Re-rolls of each grain per second at full coherence, so a grain lasts about two seconds
+const SLOW_EVOLUTION: f32 = 0.5;
+
+/// Timing and temporal behaviour of an animated grain sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceSettings {
+    pub fps: f32,
+    /// Length in seconds
+    pub duration: f32,
+    /// 0 re-rolls the grain every frame; towards 1 grains re-roll ever more rarely
+    pub coherence: f32,
+    /// Number of the first file, e.g. 1001 for a VFX plate
+    pub start_frame: u32,
+    /// Dust, scratches, weave and flicker over the grain; off by default
+    pub damage: DamageParameters,
+    /// Write dust, hairs and scratches to their own files (see [`damage_path`]) instead of
+    /// printing them over the grain. Weave and flicker still move the grain frames.
+    pub separate_damage: bool,
+}
+
+impl Default for SequenceSettings {
+    fn default() -> Self {
+        Self {
+            fps: 24.0,
+            duration: 1.0,
+            coherence: 0.0,
+            start_frame: 1,
+            damage: DamageParameters::default(),
+            separate_damage: false,
+        }
+    }
+}
+
+impl SequenceSettings {
+    pub fn frame_count(&self) -> u32 {
+        (self.duration * self.fps).round().max(0.0) as u32
+    }
+
+    /// Grain parameters for the `index`th frame of the sequence (counting from 0).
+    ///
+    /// Without coherence every frame gets its own seed. Otherwise the seed is kept and the
+    /// noise time advances, so each grain re-rolls on its own while the rest stay put.
+    /// Just above zero coherence every grain re-rolls once a frame, like reseeding; towards 1
+    /// the rate falls to half a re-roll per second, whatever the frame rate.
+    pub fn frame_params(&self, params: &GrainParams, index: u32) -> GrainParams {
+        let coherence = self.coherence.clamp(0.0, 1.0);
+        if coherence == 0.0 {
+            return params.with_seed(frame_seed(params.seed_u64(), index));
+        }
+
+        let rate = self.fps.powf(1.0 - coherence) * SLOW_EVOLUTION.powf(coherence);
+        GrainParams { time: params.time + rate * index as f32 / self.fps, ..*params }
+    }
+
+    fn validate(&self) -> Result<(), GrainError> {
+        if !(self.fps > 0.0 && self.fps.is_finite()) {
+            return Err(GrainError::InvalidParameter {
+                name: "fps".to_string(),
+                reason: format!("Frame rate must be positive, got {}", self.fps),
+            });
+        }
+        if self.frame_count() == 0 {
+            return Err(GrainError::InvalidParameter {
+                name: "duration".to_string(),
+                reason: format!("{}s at {} fps is less than one frame", self.duration, self.fps),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reported after each frame is written
+#[derive(Debug, Clone)]
+pub struct SequenceProgress {
+    /// Frame number used in the file name
+    pub frame: u32,
+    pub frames_done: u32,
+    pub frame_count: u32,
+    pub path: PathBuf,
+}
+
+impl SequenceProgress {
+    pub fn fraction(&self) -> f32 {
+        self.frames_done as f32 / self.frame_count as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceOutcome {
+    Completed { frames: u32 },
+    /// Stopped on request; frames already written are kept
+    Cancelled { frames: u32 },
+}
+
+/// Seed for the `index`th frame of an incoherent sequence; the first frame keeps the
+/// sequence seed, so it matches a still render
+pub fn frame_seed(seed: u64, index: u32) -> u64 {
+    if index == 0 {
+        return seed;
+    }
+    // Consecutive frames get unrelated seeds
+    mix64(seed.wrapping_add((index as u64).wrapping_mul(SPLITMIX64_GAMMA)))
+}
+
+/// File name for one frame: the last run of `#` in `pattern` becomes the zero-padded
+/// frame number (`grain_####.png` -> `grain_0012.png`). Without one, `_####` is
+/// inserted before the extension.
+pub fn frame_path(pattern: &Path, frame: u32) -> PathBuf {
+    let file_name = pattern.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    let name = match file_name.rfind('#') {
+        Some(end) => {
+            let start = file_name[..=end].trim_end_matches('#').len();
+            let width = end + 1 - start;
+            format!("{}{frame:0width$}{}", &file_name[..start], &file_name[end + 1..])
+        }
+        None => {
+            let stem = pattern.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            match pattern.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("{stem}_{frame:04}.{ext}"),
+                None => format!("{stem}_{frame:04}"),
+            }
+        }
+    };
+
+    pattern.with_file_name(name)
+}
+
+/// File for the damage layer of a frame written to `frame`: `grain_0012.png` -> `grain_0012_damage.png`
+pub fn damage_path(frame: &Path) -> PathBuf {
+    let stem = frame.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    match frame.extension().and_then(|e| e.to_str()) {
+        Some(ext) => frame.with_file_name(format!("{stem}_damage.{ext}")),
+        None => frame.with_file_name(format!("{stem}_damage")),
+    }
+}
+
+/// Render a single image and write it to `path`. A still is the first frame of a sequence,
+/// so it gets that frame's damage; without damage the image streams tile by tile.
+pub fn export_still(
+    renderer: &mut TiledRenderer,
+    params: &GrainParams,
+    plate: Option<&Rgba32FImage>,
+    damage: &DamageParameters,
+    path: &Path,
+) -> Result<(), GrainError> {
+    let format = path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(ExportFormat::from_extension)
+        .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+
+    let frame = damage.is_enabled().then(|| {
+        let plan = renderer.plan();
+        DamageGenerator::new(damage, params.seed_u64(), plan.width, plan.height).frame(0)
+    });
+    match frame.filter(|frame| !frame.is_clean()) {
+        Some(frame) => write_damaged_frame(renderer, params, plate, &frame, false, path),
+        None => write_frame(renderer, params, plate, format, path),
+    }
+}
+
+/// Render `settings.frame_count()` frames of evolving grain and write them as numbered files.
+///
+/// PNG and TIFF frames stream tile by tile like [`TiledRenderer::render_to_file`]; EXR frames,
+/// and frames with damage (which moves the whole frame), are assembled in memory first.
+/// Damage is seeded from the sequence seed, so it does not depend on grain coherence.
+/// `cancel` is checked before every frame.
+pub fn export_sequence(
+    renderer: &mut TiledRenderer,
+    params: &GrainParams,
+    plate: Option<&Rgba32FImage>,
+    settings: &SequenceSettings,
+    output_pattern: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&SequenceProgress),
+) -> Result<SequenceOutcome, GrainError> {
+    settings.validate()?;
+    let format = output_pattern.extension()
+        .and_then(|e| e.to_str())
+        .and_then(ExportFormat::from_extension)
+        .ok_or(GrainError::Export(ExportError::InvalidExtension))?;
+
+    let frame_count = settings.frame_count();
+    let damage = settings.damage.is_enabled().then(|| {
+        let plan = renderer.plan();
+        DamageGenerator::new(&settings.damage, params.seed_u64(), plan.width, plan.height)
+    });
+    for index in 0..frame_count {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(SequenceOutcome::Cancelled { frames: index });
+        }
+
+        let frame = settings.start_frame + index;
+        let path = frame_path(output_pattern, frame);
+        let frame_params = settings.frame_params(params, index);
+        match damage.as_ref().map(|damage| damage.frame(index)).filter(|damage| !damage.is_clean()) {
+            Some(damage) => write_damaged_frame(renderer, &frame_params, plate, &damage, settings.separate_damage, &path)?,
+            None => write_frame(renderer, &frame_params, plate, format, &path)?,
+        }
+
+        on_progress(&SequenceProgress { frame, frames_done: index + 1, frame_count, path });
+    }
+
+    Ok(SequenceOutcome::Completed { frames: frame_count })
+}
+
+fn write_frame(
+    renderer: &mut TiledRenderer,
+    params: &GrainParams,
+    plate: Option<&Rgba32FImage>,
+    format: ExportFormat,
+    path: &Path,
+) -> Result<(), GrainError> {
+    match format {
+        ExportFormat::Png | ExportFormat::Tiff => renderer.render_to_file(params, plate, path),
+        #[cfg(feature = "exr")]
+        ExportFormat::Exr => crate::core::export::export_image_f32(&render_frame(renderer, params, plate)?, path),
+    }
+}
+
+fn write_damaged_frame(
+    renderer: &mut TiledRenderer,
+    params: &GrainParams,
+    plate: Option<&Rgba32FImage>,
+    damage: &DamageFrame,
+    separate: bool,
+    path: &Path,
+) -> Result<(), GrainError> {
+    let mut image = damage.apply_motion(&render_frame(renderer, params, plate)?);
+    if separate {
+        write_image(damage.layer(), renderer.format(), &damage_path(path))?;
+    } else {
+        damage.overlay(&mut image);
+    }
+    write_image(image, renderer.format(), path)
+}
+
+/// The whole frame, assembled from its bands
+fn render_frame(
+    renderer: &mut TiledRenderer,
+    params: &GrainParams,
+    plate: Option<&Rgba32FImage>,
+) -> Result<Rgba32FImage, GrainError> {
+    let mut pixels = Vec::new();
+    renderer.render_bands(params, plate, |band| {
+        pixels.extend(band);
+        Ok(())
+    })?;
+    let plan = renderer.plan();
+    renderer.format().to_rgba32f_image(plan.width, plan.height, &pixels)
+        .ok_or_else(|| GrainError::Export(ExportError::WriteFailed("Frame size mismatch".to_string())))
+}
+
+/// Write at the precision the frames were rendered at
+fn write_image(image: Rgba32FImage, format: OutputFormat, path: &Path) -> Result<(), GrainError> {
+    match format {
+        OutputFormat::Rgba8 => crate::core::export::export_image(&DynamicImage::ImageRgba32F(image).into_rgba8(), path),
+        _ => crate::core::export::export_image_f32(&image, path),
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::GrainError;
+use crate::export::batch::{BatchJob, BatchReport, FileReport};
+
+/// Name of the processed-files log kept in the output directory
+pub const DEFAULT_LOG_NAME: &str = "grainforge-processed.jsonl";
+/// Time between looks at the input directory
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Size and modification time; a file counts as fully written once these stop changing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    modified_ms: u64,
+}
+
+impl FileState {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self { size: metadata.len(), modified_ms: modified.as_millis() as u64 })
+    }
+}
+
+/// One line of the processed-files log
+#[derive(Debug, Serialize, Deserialize)]
+struct LogEntry {
+    input: PathBuf,
+    modified_ms: u64,
+    output: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Long-running batch: grains each new image that matches the job's input glob.
+///
+/// A file is picked up once its size and modification time are unchanged between two polls,
+/// so frames still being copied in are left alone. Every processed file is appended to a
+/// JSON-lines log; files listed there are not processed again after a restart unless they
+/// have been modified since, in which case their output is replaced. Files that fail are
+/// retried when they change. Anything inside the output directory is ignored, so outputs
+/// are never grained twice. A file whose output name is already taken by another input,
+/// in this poll or an earlier one, fails rather than overwriting that input's output.
+pub struct WatchFolder {
+    job: BatchJob,
+    log_path: PathBuf,
+    // Canonical input -> modification time when it was processed
+    processed: HashMap<PathBuf, u64>,
+    // Output -> the input it was written from
+    outputs: HashMap<PathBuf, PathBuf>,
+    failed: HashMap<PathBuf, FileState>,
+    // Seen on the last poll but not processed yet
+    pending: HashMap<PathBuf, FileState>,
+}
+
+impl WatchFolder {
+    /// Watch with the log in the job's output directory
+    pub fn new(job: BatchJob) -> Result<Self, GrainError> {
+        let log_path = job.output_dir.join(DEFAULT_LOG_NAME);
+        Self::with_log(job, log_path)
+    }
+
+    /// Watch, recording processed files in `log_path` and skipping those it already lists
+    pub fn with_log(job: BatchJob, log_path: impl Into<PathBuf>) -> Result<Self, GrainError> {
+        let log_path = log_path.into();
+        let mut processed = HashMap::new();
+        let mut outputs = HashMap::new();
+        match std::fs::read_to_string(&log_path) {
+            Ok(log) => {
+                for line in log.lines().filter(|line| !line.trim().is_empty()) {
+                    match serde_json::from_str::<LogEntry>(line) {
+                        Ok(entry) if entry.error.is_none() => {
+                            processed.insert(entry.input.clone(), entry.modified_ms);
+                            outputs.insert(entry.output, entry.input);
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::warn!("Ignoring malformed line in {}: {e}", log_path.display()),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Self { job, log_path, processed, outputs, failed: HashMap::new(), pending: HashMap::new() })
+    }
+
+    pub fn job(&self) -> &BatchJob {
+        &self.job
+    }
+
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// True once `input` is recorded as processed in its current version
+    pub fn is_processed(&self, input: &Path) -> bool {
+        let Some((path, state)) = input.canonicalize().ok().zip(FileState::of(input)) else {
+            return false;
+        };
+        self.processed.get(&path) == Some(&state.modified_ms)
+    }
+
+    /// Look at the input directory once and process every file that has finished arriving
+    pub fn poll(&mut self, cancel: &AtomicBool, on_file: impl Fn(&FileReport) + Sync) -> Result<BatchReport, GrainError> {
+        let output_dir = self.job.output_dir.canonicalize().ok();
+        let mut ready = Vec::new();
+        let mut pending = HashMap::new();
+
+        for input in self.job.inputs()? {
+            let Some((path, state)) = input.canonicalize().ok().zip(FileState::of(&input)) else {
+                continue;
+            };
+            if output_dir.as_ref().is_some_and(|dir| path.starts_with(dir))
+                || self.processed.get(&path) == Some(&state.modified_ms)
+                || self.failed.get(&path) == Some(&state)
+            {
+                continue;
+            }
+            if self.pending.get(&path) == Some(&state) {
+                ready.push(path);
+            } else {
+                pending.insert(path, state);
+            }
+        }
+        self.pending = pending;
+        if ready.is_empty() {
+            return Ok(BatchReport::default());
+        }
+
+        let mut report = BatchReport::default();
+        let ready = self.claim_outputs(ready, &mut report, &on_file)?;
+
+        // Files processed before in an older version replace their previous output
+        let (updated, new): (Vec<PathBuf>, Vec<PathBuf>) = ready.into_iter()
+            .partition(|path| self.processed.contains_key(path));
+        if !new.is_empty() {
+            let batch = self.job.run_files(&new, cancel, &on_file)?;
+            report.files.extend(batch.files);
+            report.cancelled |= batch.cancelled;
+        }
+        if !updated.is_empty() {
+            let job = BatchJob { force: true, ..self.job.clone() };
+            let forced = job.run_files(&updated, cancel, &on_file)?;
+            report.files.extend(forced.files);
+            report.cancelled |= forced.cancelled;
+        }
+        self.record(&report)?;
+        Ok(report)
+    }
+
+    /// Poll every `interval` until `cancel` is set. A failing poll (e.g. the input directory
+    /// went away) is reported to `on_error` and watching carries on.
+    pub fn run(
+        &mut self,
+        interval: Duration,
+        cancel: &AtomicBool,
+        on_file: impl Fn(&FileReport) + Sync,
+        mut on_error: impl FnMut(&GrainError),
+    ) {
+        while !cancel.load(Ordering::Relaxed) {
+            if let Err(e) = self.poll(cancel, &on_file) {
+                on_error(&e);
+            }
+            std::thread::sleep(interval);
+        }
+    }
+
+    /// The files in `ready` whose output no other input has claimed. The rest are added to
+    /// `report` as failures, so one clash does not hold up the other files.
+    fn claim_outputs(
+        &self,
+        ready: Vec<PathBuf>,
+        report: &mut BatchReport,
+        on_file: impl Fn(&FileReport),
+    ) -> Result<Vec<PathBuf>, GrainError> {
+        let mut claimed = self.outputs.clone();
+        let mut free = Vec::with_capacity(ready.len());
+        for input in ready {
+            let output = self.job.output_path(&input)?;
+            match claimed.get(&output) {
+                Some(owner) if *owner != input => {
+                    let result = Err(GrainError::InvalidParameter {
+                        name: "naming".to_string(),
+                        reason: format!("{} is already written from {}", output.display(), owner.display()),
+                    });
+                    let file = FileReport { input, output, result };
+                    on_file(&file);
+                    report.files.push(file);
+                }
+                _ => {
+                    claimed.insert(output, input.clone());
+                    free.push(input);
+                }
+            }
+        }
+        Ok(free)
+    }
+
+    fn record(&mut self, report: &BatchReport) -> Result<(), GrainError> {
+        let mut log = OpenOptions::new().create(true).append(true).open(&self.log_path)?;
+        for file in &report.files {
+            let Some(state) = FileState::of(&file.input) else {
+                continue;
+            };
+            let entry = LogEntry {
+                input: file.input.clone(),
+                modified_ms: state.modified_ms,
+                output: file.output.clone(),
+                error: file.result.as_ref().err().map(|e| e.to_string()),
+            };
+            let line = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+            writeln!(log, "{line}")?;
+
+            match file.result {
+                Ok(_) => {
+                    self.processed.insert(file.input.clone(), state.modified_ms);
+                    self.outputs.insert(file.output.clone(), file.input.clone());
+                }
+                Err(_) => {
+                    self.failed.insert(file.input.clone(), state);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -26,9 +26,24 @@ impl GrainForgeApp {
         // Apply custom theme
         grainforge::app::theme::apply_theme(&cc.egui_ctx);
         
-        Self {
-            state: grainforge::app::state::AppState::default(),
+        let mut state = grainforge::app::state::AppState::default();
+        // Preview on eframe's device, or a headless one when eframe has none; without any
+        // adapter the preview falls back to the CPU
+        let gpu = grainforge::engine::gpu_context::GpuContext::new(cc)
+            .or_else(|_| grainforge::engine::gpu_context::GpuContext::new_headless());
+        match gpu {
+            Ok(gpu) => {
+                state.gpu_preview = Some(grainforge::app::gpu_preview::GpuPreview::new(gpu));
+                // Shader hot reload is opt-in, for editing WGSL without rebuilding
+                state.shader_reload = grainforge::app::hot_reload::ShaderHotReload::from_env();
+            }
+            Err(e) => {
+                log::warn!("{e}, falling back to CPU grain renderer");
+                state.backend = grainforge::engine::backend::RenderBackend::Cpu;
+            }
         }
+
+        Self { state }
     }
 }
 
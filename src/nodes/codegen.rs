@@ -0,0 +1,414 @@
+//! Compiles a node graph into one fused WGSL compute shader.
+//!
+//! Every node becomes a function that computes its outputs at one pixel from its inputs at
+//! that pixel; `main` calls them in evaluation order and stores the value feeding the grain
+//! output, so the whole graph runs in a single dispatch with no intermediate textures.
+//!
+//! Nodes that need their neighbours (blurs, morphology) read an input through a sampler.
+//! The sampler re-evaluates everything upstream of that input at the neighbouring pixel,
+//! which trades extra arithmetic for the memory traffic of a texture per node.
+//!
+//! Numeric parameters are read from a storage buffer rather than baked into the code, so a
+//! shader only has to be rebuilt when the graph's [`structure_hash`] changes.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+use crate::core::error::{GrainError, GraphError};
+use crate::core::parameter::{Parameter, ParameterValue};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::shaders::{ProcessedShader, ShaderLibrary};
+use crate::nodes::node_graph::{Node, NodeGraph, NodeId, SocketRef};
+use crate::nodes::node_types::{NodeRegistry, NodeType, SocketType};
+use crate::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+/// GPU implementation of a node type: the body of the node's WGSL function.
+///
+/// The body sees the pixel as `px: Pixel`, each input by its socket name, and assigns every
+/// output to the field of the same name on `out`, e.g. `out.value = a + b;`.
+pub type WgslFn = fn(&WgslNode) -> Result<String, String>;
+
+/// Name of the generated source in the shader library
+pub const GRAPH_SHADER: &str = "graph.wgsl";
+
+/// Declarations every generated shader starts with
+const PRELUDE: &str = r#"#include "noise.wgsl"
+
+#ifndef OUTPUT_FORMAT
+#define OUTPUT_FORMAT rgba8unorm
+#endif
+
+@group(0) @binding(0) var output_texture: texture_storage_2d<OUTPUT_FORMAT, write>;
+
+// Layout must match GraphUniforms in graph_renderer.rs
+struct GraphUniforms {
+    size: vec2<u32>,
+    tile_origin: vec2<u32>,
+}
+
+@group(0) @binding(1) var<uniform> graph: GraphUniforms;
+// Numeric node parameters, one per element; see codegen::parameter_block
+@group(0) @binding(2) var<storage, read> node_params: array<vec4<f32>>;
+
+// Pixel being evaluated, in full-output coordinates
+struct Pixel {
+    coords: vec2<i32>,
+    size: vec2<i32>,
+}
+
+// Position across the output in 0 - 1
+fn pixel_uv(px: Pixel) -> vec2<f32> {
+    return vec2<f32>(px.coords) / vec2<f32>(px.size);
+}
+
+// Neighbouring pixel, clamped to the output edge like the CPU filters
+fn offset_pixel(px: Pixel, offset: vec2<i32>) -> Pixel {
+    return Pixel(clamp(px.coords + offset, vec2(0), px.size - 1), px.size);
+}
+"#;
+
+/// What a node's [`WgslFn`] sees while its function is generated
+pub struct WgslNode<'a> {
+    pub node: &'a Node,
+    pub node_type: &'a NodeType,
+    index: usize,
+    slots: &'a HashMap<(usize, usize), usize>,
+    sampled: RefCell<Vec<&'static str>>,
+}
+
+impl WgslNode<'_> {
+    /// WGSL expression reading a numeric parameter: `f32` for numbers and toggles,
+    /// `vec2<f32>` for vectors and `vec4<f32>` for colors
+    pub fn param(&self, id: &str) -> Result<String, String> {
+        let position = self.node_type.parameters.iter().position(|p| p.id == id)
+            .ok_or_else(|| format!("No parameter '{id}'"))?;
+        let slot = self.slots.get(&(self.index, position))
+            .ok_or_else(|| format!("Parameter '{id}' is not numeric"))?;
+        Ok(slot_expression(*slot, &self.node_type.parameters[position].value))
+    }
+
+    /// Selection parameter; the choice is compiled into the shader
+    pub fn selection(&self, id: &str) -> Result<&str, String> {
+        match effective_parameter(self.node, self.node_type, id).map(|p| &p.value) {
+            Some(ParameterValue::Selection(value)) => Ok(value),
+            _ => Err(format!("Missing selection parameter '{id}'")),
+        }
+    }
+
+    /// Name of a function `fn(px: Pixel, offset: vec2<i32>) -> T` giving the named input
+    /// at another pixel, for nodes that look at their neighbours
+    pub fn sampler(&self, input: &str) -> Result<String, String> {
+        let socket = self.node_type.input(input).ok_or_else(|| format!("No input '{input}'"))?;
+        self.sampled.borrow_mut().push(socket.name);
+        Ok(sampler_name(self.index, socket.name))
+    }
+}
+
+/// A graph compiled to WGSL
+#[derive(Debug, Clone)]
+pub struct GraphShader {
+    pub shader: ProcessedShader,
+    /// See [`structure_hash`]
+    pub structure_hash: u64,
+}
+
+/// Build and validate the fused shader for `graph`, writing to a texture of `format`
+pub fn compile(
+    graph: &NodeGraph,
+    registry: &NodeRegistry,
+    format: OutputFormat,
+    library: &ShaderLibrary,
+) -> Result<GraphShader, GrainError> {
+    let plan = Plan::new(graph, registry)?;
+    let code = plan.generate()?;
+
+    let mut library = library.clone();
+    library.set_source(GRAPH_SHADER, code);
+    let shader = library.preprocess(GRAPH_SHADER, &[("OUTPUT_FORMAT", format.wgsl_format())])?;
+    shader.validate()?;
+    Ok(GraphShader { shader, structure_hash: plan.structure_hash(format) })
+}
+
+/// Hash of everything that changes the generated code: the nodes that reach the output,
+/// their wiring and selection parameters, and the output format. Numeric parameter values
+/// are left out, since they live in the parameter buffer.
+pub fn structure_hash(graph: &NodeGraph, registry: &NodeRegistry, format: OutputFormat) -> Result<u64, GraphError> {
+    Ok(Plan::new(graph, registry)?.structure_hash(format))
+}
+
+/// Values of the numeric parameters, in the order the compiled shader reads them. Padded to
+/// one element, since storage buffers cannot be empty.
+pub fn parameter_block(graph: &NodeGraph, registry: &NodeRegistry) -> Result<Vec<[f32; 4]>, GraphError> {
+    let plan = Plan::new(graph, registry)?;
+    let mut block = vec![[0.0; 4]; plan.slot_count.max(1)];
+    for (&(index, position), &slot) in &plan.slots {
+        let live = &plan.nodes[index];
+        let id = &live.node_type.parameters[position].id;
+        if let Some(parameter) = effective_parameter(live.node, live.node_type, id) {
+            block[slot] = match parameter.value {
+                ParameterValue::Float(v) => [v, 0.0, 0.0, 0.0],
+                ParameterValue::Int(v) => [v as f32, 0.0, 0.0, 0.0],
+                ParameterValue::Bool(v) => [v as u32 as f32, 0.0, 0.0, 0.0],
+                ParameterValue::Vec2([x, y]) => [x, y, 0.0, 0.0],
+                ParameterValue::Color(c) => c,
+                ParameterValue::Selection(_) => [0.0; 4],
+            };
+        }
+    }
+    Ok(block)
+}
+
+/// A node that contributes to the output
+struct LiveNode<'a> {
+    node: &'a Node,
+    node_type: &'a NodeType,
+    /// Per input: the live index and output socket feeding it, if connected
+    sources: Vec<Option<(usize, &'a str)>>,
+}
+
+/// The nodes reaching the grain output, in evaluation order, with their parameter slots
+struct Plan<'a> {
+    nodes: Vec<LiveNode<'a>>,
+    /// Input of the output node
+    output: Option<(usize, &'a str)>,
+    output_node: &'a Node,
+    /// (live index, position in the type's parameters) -> element of `node_params`
+    slots: HashMap<(usize, usize), usize>,
+    slot_count: usize,
+}
+
+impl<'a> Plan<'a> {
+    fn new(graph: &'a NodeGraph, registry: &'a NodeRegistry) -> Result<Self, GraphError> {
+        graph.validate(registry)?;
+        let output_node = graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT).ok_or(GraphError::MissingOutput)?;
+
+        // Only nodes upstream of the output are compiled
+        let mut live_ids = HashSet::from([output_node.id]);
+        let mut stack = vec![output_node.id];
+        while let Some(id) = stack.pop() {
+            for connection in graph.connections().iter().filter(|c| c.to.node == id) {
+                if live_ids.insert(connection.from.node) {
+                    stack.push(connection.from.node);
+                }
+            }
+        }
+        let order: Vec<NodeId> = graph.topological_order()?.into_iter()
+            .filter(|id| live_ids.contains(id) && *id != output_node.id)
+            .collect();
+        let index: HashMap<NodeId, usize> = order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+        let source = |input: SocketRef| -> Option<(usize, &'a str)> {
+            let from = graph.source(&input)?;
+            Some((index[&from.node], from.socket.as_str()))
+        };
+        let mut nodes = Vec::with_capacity(order.len());
+        let mut slots = HashMap::new();
+        for (i, &id) in order.iter().enumerate() {
+            let node = graph.node(id).ok_or(GraphError::UnknownNode(id))?;
+            let node_type = registry.get(&node.kind).ok_or_else(|| GraphError::UnknownNodeType(node.kind.clone()))?;
+            let sources = node_type.inputs.iter().map(|input| source(SocketRef::new(id, input.name))).collect();
+            for (position, parameter) in node_type.parameters.iter().enumerate() {
+                if !matches!(parameter.value, ParameterValue::Selection(_)) {
+                    let slot = slots.len();
+                    slots.insert((i, position), slot);
+                }
+            }
+            nodes.push(LiveNode { node, node_type, sources });
+        }
+        let output = source(SocketRef::new(output_node.id, "grain"));
+        let slot_count = slots.len();
+        Ok(Self { nodes, output, output_node, slots, slot_count })
+    }
+
+    fn structure_hash(&self, format: OutputFormat) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        format.wgsl_format().hash(&mut hasher);
+        for live in &self.nodes {
+            live.node.kind.hash(&mut hasher);
+            live.sources.hash(&mut hasher);
+            for parameter in &live.node_type.parameters {
+                parameter.id.hash(&mut hasher);
+                if let Some(ParameterValue::Selection(value)) =
+                    effective_parameter(live.node, live.node_type, &parameter.id).map(|p| &p.value)
+                {
+                    value.hash(&mut hasher);
+                }
+            }
+        }
+        self.output.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn generate(&self) -> Result<String, GraphError> {
+        let mut code = String::from(PRELUDE);
+        let mut pulled = HashSet::new();
+        let mut samplers = Vec::new();
+
+        for (i, live) in self.nodes.iter().enumerate() {
+            let failed = |reason: String| GraphError::Evaluation { node: live.node.to_string(), reason };
+            let wgsl = live.node_type.wgsl.ok_or_else(|| failed("No GPU implementation".to_string()))?;
+            let context = WgslNode {
+                node: live.node,
+                node_type: live.node_type,
+                index: i,
+                slots: &self.slots,
+                sampled: RefCell::new(Vec::new()),
+            };
+            let body = wgsl(&context).map_err(failed)?;
+
+            // Only nodes with outputs can reach the grain output, so the struct is never empty
+            let _ = writeln!(code, "\n// {}", live.node);
+            let _ = writeln!(code, "struct Node{i} {{");
+            for output in &live.node_type.outputs {
+                let _ = writeln!(code, "    {}: {},", output.name, wgsl_type(output.ty));
+            }
+            let _ = writeln!(code, "}}\n");
+            let mut arguments = vec!["px: Pixel".to_string()];
+            arguments.extend(live.node_type.inputs.iter().map(|input| format!("{}: {}", input.name, wgsl_type(input.ty))));
+            let _ = writeln!(code, "fn node_{i}({}) -> Node{i} {{", arguments.join(", "));
+            let _ = writeln!(code, "    var out: Node{i};");
+            for line in body.lines() {
+                let _ = writeln!(code, "    {line}");
+            }
+            let _ = writeln!(code, "    return out;\n}}");
+
+            for input in context.sampled.into_inner() {
+                if !samplers.contains(&(i, input)) {
+                    samplers.push((i, input));
+                    if let Some((source, _)) = self.input_source(i, input) {
+                        self.mark_pulled(source, &mut pulled);
+                    }
+                }
+            }
+        }
+
+        // Upstream re-evaluation for samplers
+        let mut pulled: Vec<usize> = pulled.into_iter().collect();
+        pulled.sort_unstable();
+        for i in pulled {
+            let arguments = self.arguments(i, |source, socket| format!("pull_{source}(px).{socket}"));
+            let _ = writeln!(code, "\nfn pull_{i}(px: Pixel) -> Node{i} {{\n    return node_{i}({arguments});\n}}");
+        }
+        for (i, input) in samplers {
+            let live = &self.nodes[i];
+            let ty = live.node_type.input(input).map_or(SocketType::Field, |socket| socket.ty);
+            let value = self.input_expression(i, input, |source, socket| {
+                format!("pull_{source}(offset_pixel(px, offset)).{socket}")
+            });
+            let _ = writeln!(
+                code,
+                "\nfn {}(px: Pixel, offset: vec2<i32>) -> {} {{\n    return {value};\n}}",
+                sampler_name(i, input),
+                wgsl_type(ty),
+            );
+        }
+
+        let _ = writeln!(code, "\n@compute @workgroup_size(8, 8)");
+        let _ = writeln!(code, "fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {{");
+        let _ = writeln!(code, "    let texel = global_id.xy;");
+        let _ = writeln!(code, "    let size = textureDimensions(output_texture);");
+        let _ = writeln!(code, "    if (texel.x >= size.x || texel.y >= size.y) {{\n        return;\n    }}");
+        let _ = writeln!(code, "    let px = Pixel(vec2<i32>(texel + graph.tile_origin), vec2<i32>(graph.size));");
+        for i in 0..self.nodes.len() {
+            let arguments = self.arguments(i, |source, socket| format!("n{source}.{socket}"));
+            let _ = writeln!(code, "    let n{i} = node_{i}({arguments});");
+        }
+        let result = match self.output {
+            Some((source, socket)) => {
+                let ty = self.nodes[source].node_type.output(socket).map_or(SocketType::Field, |s| s.ty);
+                widen(format!("n{source}.{socket}"), ty, SocketType::Field)
+            }
+            None => return Err(GraphError::UnconnectedInput { input: format!("{}.grain", self.output_node) }),
+        };
+        let _ = writeln!(code, "    textureStore(output_texture, texel, {result});\n}}");
+        Ok(code)
+    }
+
+    /// Arguments of `node_i`, reading connected inputs through `connected(source, socket)`
+    fn arguments(&self, i: usize, connected: impl Fn(usize, &str) -> String) -> String {
+        let mut arguments = vec!["px".to_string()];
+        for input in &self.nodes[i].node_type.inputs {
+            arguments.push(self.input_expression(i, input.name, &connected));
+        }
+        arguments.join(", ")
+    }
+
+    /// Value reaching an input, widened to its type: the connected output or the parameter
+    fn input_expression(&self, i: usize, input: &str, connected: impl Fn(usize, &str) -> String) -> String {
+        let live = &self.nodes[i];
+        let Some(socket) = live.node_type.input(input) else {
+            return String::new();
+        };
+        match self.input_source(i, input) {
+            Some((source, output)) => {
+                let from = self.nodes[source].node_type.output(output).map_or(socket.ty, |s| s.ty);
+                widen(connected(source, output), from, socket.ty)
+            }
+            None => {
+                // Validation guarantees a parameter for every unconnected input
+                let position = live.node_type.parameters.iter().position(|p| p.id == input);
+                match position.and_then(|position| Some((&live.node_type.parameters[position], self.slots.get(&(i, position))?))) {
+                    Some((parameter, &slot)) => {
+                        let from = match parameter.value {
+                            ParameterValue::Vec2(_) => SocketType::Vec2,
+                            ParameterValue::Color(_) => SocketType::Color,
+                            _ => SocketType::Scalar,
+                        };
+                        widen(slot_expression(slot, &parameter.value), from, socket.ty)
+                    }
+                    None => format!("{}()", wgsl_type(socket.ty)),
+                }
+            }
+        }
+    }
+
+    fn input_source(&self, i: usize, input: &str) -> Option<(usize, &'a str)> {
+        let position = self.nodes[i].node_type.inputs.iter().position(|socket| socket.name == input)?;
+        self.nodes[i].sources[position]
+    }
+
+    /// Mark `i` and everything upstream of it as needing a pull function
+    fn mark_pulled(&self, i: usize, pulled: &mut HashSet<usize>) {
+        if pulled.insert(i) {
+            for (source, _) in self.nodes[i].sources.iter().flatten() {
+                self.mark_pulled(*source, pulled);
+            }
+        }
+    }
+}
+
+/// The node's value of a parameter, or the type's default for graphs saved before it existed
+fn effective_parameter<'a>(node: &'a Node, node_type: &'a NodeType, id: &str) -> Option<&'a Parameter> {
+    node.parameter(id).or_else(|| node_type.parameter(id))
+}
+
+fn slot_expression(slot: usize, value: &ParameterValue) -> String {
+    match value {
+        ParameterValue::Vec2(_) => format!("node_params[{slot}].xy"),
+        ParameterValue::Color(_) => format!("node_params[{slot}]"),
+        _ => format!("node_params[{slot}].x"),
+    }
+}
+
+fn sampler_name(i: usize, input: &str) -> String {
+    format!("sample_{i}_{input}")
+}
+
+fn wgsl_type(ty: SocketType) -> &'static str {
+    match ty {
+        SocketType::Scalar => "f32",
+        SocketType::Vec2 => "vec2<f32>",
+        SocketType::Color | SocketType::Field => "vec4<f32>",
+    }
+}
+
+/// Convert an expression like [`Value::widen`](crate::nodes::evaluator::Value::widen)
+fn widen(expression: String, from: SocketType, to: SocketType) -> String {
+    match (from, to) {
+        (SocketType::Scalar, SocketType::Color | SocketType::Field) => format!("vec4(vec3({expression}), 1.0)"),
+        _ => expression,
+    }
+}
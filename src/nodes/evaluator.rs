@@ -0,0 +1,412 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use image::Rgba32FImage;
+
+use crate::core::error::GraphError;
+use crate::core::parameter::{Parameter, ParameterValue};
+use crate::nodes::node_graph::{Node, NodeGraph, NodeId, SocketRef};
+use crate::nodes::node_types::{NodeRegistry, NodeType, SocketType};
+use crate::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+/// CPU implementation of a node type: one value per output socket, in declaration order.
+/// The error is a reason; the evaluator adds which node failed.
+pub type EvaluateFn = fn(&NodeContext) -> Result<Vec<Value>, String>;
+
+/// Per-pixel linear RGBA image, row-major. Scalar fields carry their value in every color
+/// channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Field {
+    /// Every pixel set to `color`
+    pub fn uniform(width: u32, height: u32, color: [f32; 4]) -> Self {
+        Self { width, height, pixels: vec![color; width as usize * height as usize] }
+    }
+
+    /// Field from a function of the pixel coordinates, filled on every core
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> [f32; 4] + Sync) -> Self {
+        let mut pixels = vec![[0.0; 4]; width as usize * height as usize];
+        if width > 0 {
+            // Rows are independent, so split them across threads
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+            let rows_per_chunk = (height as usize).div_ceil(threads).max(1);
+            std::thread::scope(|scope| {
+                for (chunk_index, chunk) in pixels.chunks_mut(rows_per_chunk * width as usize).enumerate() {
+                    let f = &f;
+                    scope.spawn(move || {
+                        let first_row = chunk_index * rows_per_chunk;
+                        for (row_offset, row) in chunk.chunks_exact_mut(width as usize).enumerate() {
+                            let y = (first_row + row_offset) as u32;
+                            for (x, pixel) in row.iter_mut().enumerate() {
+                                *pixel = f(x as u32, y);
+                            }
+                        }
+                    });
+                }
+            });
+        }
+        Self { width, height, pixels }
+    }
+
+    /// Apply `f` to every pixel
+    pub fn map(&self, f: impl Fn([f32; 4]) -> [f32; 4] + Sync) -> Self {
+        Self::from_fn(self.width, self.height, |x, y| f(self.get(x, y)))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[[f32; 4]] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [f32; 4] {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Pixel at a possibly out-of-bounds position, clamped to the edge like `offset_pixel`
+    /// in generated shaders
+    pub fn sample(&self, x: i32, y: i32) -> [f32; 4] {
+        let x = x.clamp(0, self.width as i32 - 1);
+        let y = y.clamp(0, self.height as i32 - 1);
+        self.get(x as u32, y as u32)
+    }
+
+    pub fn to_image(&self) -> Rgba32FImage {
+        let raw = self.pixels.iter().flatten().copied().collect();
+        Rgba32FImage::from_raw(self.width, self.height, raw).expect("pixel count always matches the field size")
+    }
+
+    pub fn from_image(image: &Rgba32FImage) -> Self {
+        let pixels = image.pixels().map(|p| p.0).collect();
+        Self { width: image.width(), height: image.height(), pixels }
+    }
+}
+
+/// Value flowing through a socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(f32),
+    Vec2([f32; 2]),
+    Color([f32; 4]),
+    /// Shared, so cached outputs feed downstream nodes without copying
+    Field(Arc<Field>),
+}
+
+impl Value {
+    pub fn socket_type(&self) -> SocketType {
+        match self {
+            Value::Scalar(_) => SocketType::Scalar,
+            Value::Vec2(_) => SocketType::Vec2,
+            Value::Color(_) => SocketType::Color,
+            Value::Field(_) => SocketType::Field,
+        }
+    }
+
+    /// Convert for an input of type `ty`, following [`SocketType::accepts`]: scalars become
+    /// opaque grey, constants become uniform fields of the given size
+    pub fn widen(self, ty: SocketType, width: u32, height: u32) -> Option<Value> {
+        match (self, ty) {
+            (value, ty) if value.socket_type() == ty => Some(value),
+            (Value::Scalar(v), SocketType::Color) => Some(Value::Color([v, v, v, 1.0])),
+            (Value::Scalar(v), SocketType::Field) => Some(Value::Field(Arc::new(Field::uniform(width, height, [v, v, v, 1.0])))),
+            (Value::Color(c), SocketType::Field) => Some(Value::Field(Arc::new(Field::uniform(width, height, c)))),
+            _ => None,
+        }
+    }
+
+    /// Value of an unconnected input, from the parameter of the same id
+    fn from_parameter(value: &ParameterValue) -> Option<Value> {
+        match *value {
+            ParameterValue::Float(v) => Some(Value::Scalar(v)),
+            ParameterValue::Int(v) => Some(Value::Scalar(v as f32)),
+            ParameterValue::Bool(v) => Some(Value::Scalar(if v { 1.0 } else { 0.0 })),
+            ParameterValue::Vec2(v) => Some(Value::Vec2(v)),
+            ParameterValue::Color(v) => Some(Value::Color(v)),
+            ParameterValue::Selection(_) => None,
+        }
+    }
+}
+
+/// What a node's [`EvaluateFn`] sees: its inputs, widened to the declared socket types, and
+/// its parameters
+pub struct NodeContext<'a> {
+    pub node: &'a Node,
+    pub node_type: &'a NodeType,
+    /// Size of every field in the graph
+    pub width: u32,
+    pub height: u32,
+    inputs: Vec<Value>,
+}
+
+impl NodeContext<'_> {
+    /// Value of the named input. Panics if the node type has no such input, which is a bug
+    /// in the node's implementation.
+    pub fn input(&self, name: &str) -> &Value {
+        let index = self.node_type.inputs.iter().position(|socket| socket.name == name)
+            .unwrap_or_else(|| panic!("{} has no input '{name}'", self.node_type.id));
+        &self.inputs[index]
+    }
+
+    pub fn scalar(&self, name: &str) -> f32 {
+        match self.input(name) {
+            Value::Scalar(v) => *v,
+            other => panic!("input '{name}' is a {}, not a scalar", other.socket_type().name()),
+        }
+    }
+
+    pub fn vec2(&self, name: &str) -> [f32; 2] {
+        match self.input(name) {
+            Value::Vec2(v) => *v,
+            other => panic!("input '{name}' is a {}, not a vec2", other.socket_type().name()),
+        }
+    }
+
+    pub fn color(&self, name: &str) -> [f32; 4] {
+        match self.input(name) {
+            Value::Color(v) => *v,
+            other => panic!("input '{name}' is a {}, not a color", other.socket_type().name()),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> &Field {
+        match self.input(name) {
+            Value::Field(field) => field,
+            other => panic!("input '{name}' is a {}, not a field", other.socket_type().name()),
+        }
+    }
+
+    /// Parameter of the node, or the type's default for graphs saved before it existed
+    pub fn parameter(&self, id: &str) -> Option<&Parameter> {
+        self.node.parameter(id).or_else(|| self.node_type.parameter(id))
+    }
+
+    pub fn float(&self, id: &str) -> Result<f32, String> {
+        match self.parameter(id).map(|p| &p.value) {
+            Some(ParameterValue::Float(v)) => Ok(*v),
+            Some(ParameterValue::Int(v)) => Ok(*v as f32),
+            _ => Err(format!("Missing number parameter '{id}'")),
+        }
+    }
+
+    pub fn int(&self, id: &str) -> Result<i32, String> {
+        match self.parameter(id).map(|p| &p.value) {
+            Some(ParameterValue::Int(v)) => Ok(*v),
+            _ => Err(format!("Missing integer parameter '{id}'")),
+        }
+    }
+
+    pub fn selection(&self, id: &str) -> Result<&str, String> {
+        match self.parameter(id).map(|p| &p.value) {
+            Some(ParameterValue::Selection(v)) => Ok(v),
+            _ => Err(format!("Missing selection parameter '{id}'")),
+        }
+    }
+}
+
+/// How long one node took in an evaluation
+#[derive(Debug, Clone)]
+pub struct NodeTiming {
+    pub node: NodeId,
+    pub kind: String,
+    pub duration: Duration,
+    /// Reused from the previous evaluation rather than computed
+    pub cached: bool,
+}
+
+/// Result of [`Evaluator::evaluate`]
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    /// Field feeding the graph's grain output, if the graph has one
+    pub output: Option<Arc<Field>>,
+    /// Every node, in evaluation order
+    pub timings: Vec<NodeTiming>,
+}
+
+impl Evaluation {
+    /// Nodes that were computed rather than reused
+    pub fn evaluated(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.timings.iter().filter(|t| !t.cached).map(|t| t.node)
+    }
+
+    pub fn total(&self) -> Duration {
+        self.timings.iter().map(|t| t.duration).sum()
+    }
+}
+
+struct CachedNode {
+    key: u64,
+    outputs: Vec<Value>,
+}
+
+/// Evaluates node graphs on the CPU, keeping every node's outputs between runs.
+///
+/// Each node gets a key hashed from its type, its parameters and the keys of the nodes
+/// feeding it, so a change anywhere upstream changes the key of everything downstream. A
+/// node whose key matches its cached outputs is not evaluated again: editing one node
+/// recomputes that node and what depends on it, nothing else;
+/// [`dirty_nodes`](Self::dirty_nodes) lists those nodes ahead of a run.
+pub struct Evaluator {
+    width: u32,
+    height: u32,
+    cache: HashMap<NodeId, CachedNode>,
+}
+
+impl Evaluator {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, cache: HashMap::new() }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Change the field size; every node is evaluated again on the next run
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.cache.clear();
+        }
+    }
+
+    /// Forget every cached output
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Outputs of `node` from the last evaluation
+    pub fn outputs(&self, node: NodeId) -> Option<&[Value]> {
+        self.cache.get(&node).map(|cached| cached.outputs.as_slice())
+    }
+
+    /// Nodes the next [`evaluate`](Self::evaluate) would compute, in evaluation order
+    pub fn dirty_nodes(&self, graph: &NodeGraph, registry: &NodeRegistry) -> Result<Vec<NodeId>, GraphError> {
+        graph.validate(registry)?;
+        let keys = self.keys(graph)?;
+        Ok(keys.into_iter()
+            .filter(|(id, key)| self.cache.get(id).is_none_or(|cached| cached.key != *key))
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Evaluate the graph, reusing the outputs of nodes that have not changed
+    pub fn evaluate(&mut self, graph: &NodeGraph, registry: &NodeRegistry) -> Result<Evaluation, GraphError> {
+        graph.validate(registry)?;
+        let keys = self.keys(graph)?;
+        // Drop outputs of nodes that were removed
+        self.cache.retain(|id, _| keys.iter().any(|(node, _)| node == id));
+
+        let mut timings = Vec::with_capacity(keys.len());
+        for (id, key) in keys {
+            let node = graph.node(id).ok_or(GraphError::UnknownNode(id))?;
+            let start = Instant::now();
+            let cached = self.cache.get(&id).is_some_and(|cached| cached.key == key);
+            if !cached {
+                let node_type = registry.get(&node.kind).ok_or_else(|| GraphError::UnknownNodeType(node.kind.clone()))?;
+                let outputs = self.evaluate_node(graph, registry, node, node_type)?;
+                self.cache.insert(id, CachedNode { key, outputs });
+            }
+            timings.push(NodeTiming { node: id, kind: node.kind.clone(), duration: start.elapsed(), cached });
+        }
+
+        let output = match graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT) {
+            Some(node) => match self.input_value(graph, registry, node, "grain", SocketType::Field)? {
+                Value::Field(field) => Some(field),
+                _ => None,
+            },
+            None => None,
+        };
+        Ok(Evaluation { output, timings })
+    }
+
+    fn evaluate_node(
+        &self,
+        graph: &NodeGraph,
+        registry: &NodeRegistry,
+        node: &Node,
+        node_type: &NodeType,
+    ) -> Result<Vec<Value>, GraphError> {
+        let failed = |reason: String| GraphError::Evaluation { node: node.to_string(), reason };
+        let inputs = node_type.inputs.iter()
+            .map(|socket| self.input_value(graph, registry, node, socket.name, socket.ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let evaluate = node_type.evaluate.ok_or_else(|| failed("No CPU implementation".to_string()))?;
+        let context = NodeContext { node, node_type, width: self.width, height: self.height, inputs };
+        let outputs = evaluate(&context).map_err(failed)?;
+
+        if outputs.len() != node_type.outputs.len()
+            || outputs.iter().zip(&node_type.outputs).any(|(value, socket)| value.socket_type() != socket.ty)
+        {
+            return Err(failed("Produced values that do not match its outputs".to_string()));
+        }
+        Ok(outputs)
+    }
+
+    /// Value reaching an input: the connected output if evaluated, else the parameter default
+    fn input_value(
+        &self,
+        graph: &NodeGraph,
+        registry: &NodeRegistry,
+        node: &Node,
+        socket: &str,
+        ty: SocketType,
+    ) -> Result<Value, GraphError> {
+        let failed = |reason: String| GraphError::Evaluation { node: node.to_string(), reason };
+        let value = match graph.source(&SocketRef::new(node.id, socket)) {
+            Some(source) => {
+                let source_node = graph.node(source.node).ok_or(GraphError::UnknownNode(source.node))?;
+                let index = registry.get(&source_node.kind)
+                    .and_then(|source_type| source_type.outputs.iter().position(|output| output.name == source.socket));
+                self.outputs(source.node)
+                    .zip(index)
+                    .and_then(|(outputs, index)| outputs.get(index))
+                    .ok_or_else(|| failed(format!("Input '{socket}' was not evaluated")))?
+                    .clone()
+            }
+            None => node.parameter(socket)
+                .or_else(|| registry.get(&node.kind).and_then(|node_type| node_type.parameter(socket)))
+                .and_then(|parameter| Value::from_parameter(&parameter.value))
+                .ok_or_else(|| failed(format!("Input '{socket}' has no value")))?,
+        };
+        let from = value.socket_type();
+        value.widen(ty, self.width, self.height)
+            .ok_or_else(|| failed(format!("Input '{socket}' expects a {}, got a {}", ty.name(), from.name())))
+    }
+
+    /// Cache keys of every node, in evaluation order
+    fn keys(&self, graph: &NodeGraph) -> Result<Vec<(NodeId, u64)>, GraphError> {
+        let order = graph.topological_order()?;
+        let mut keys: HashMap<NodeId, u64> = HashMap::with_capacity(order.len());
+        for &id in &order {
+            let node = graph.node(id).ok_or(GraphError::UnknownNode(id))?;
+            let mut hasher = DefaultHasher::new();
+            (self.width, self.height).hash(&mut hasher);
+            node.kind.hash(&mut hasher);
+            // Parameters hold floats, which do not implement Hash
+            serde_json::to_vec(&node.parameters).unwrap_or_default().hash(&mut hasher);
+            let mut sources: Vec<_> = graph.connections().iter().filter(|c| c.to.node == id).collect();
+            sources.sort_by(|a, b| a.to.socket.cmp(&b.to.socket));
+            for connection in sources {
+                connection.to.socket.hash(&mut hasher);
+                connection.from.socket.hash(&mut hasher);
+                keys.get(&connection.from.node).hash(&mut hasher);
+            }
+            keys.insert(id, hasher.finish());
+        }
+        Ok(order.into_iter().map(|id| (id, keys[&id])).collect())
+    }
+}
@@ -1,4 +1,6 @@
 pub mod node_graph;
 pub mod node_types;
 pub mod evaluator;
+pub mod codegen;
+#[allow(clippy::module_inception)]
 pub mod nodes;
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::error::GraphError;
+use crate::core::parameter::{Parameter, ParameterValue};
+use crate::nodes::node_types::{NodeRegistry, NodeType};
+
+/// Stable node id, kept across saves so connections and undo history survive
+pub type NodeId = Uuid;
+
+/// One node of a graph. Its sockets come from its type in the [`NodeRegistry`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub id: NodeId,
+    /// Id of the node type, e.g. `math.add`
+    pub kind: String,
+    /// Position in the node editor
+    #[serde(default)]
+    pub position: [f32; 2],
+    pub parameters: Vec<Parameter>,
+}
+
+impl Node {
+    /// New node with a fresh id and the type's default parameters
+    pub fn new(node_type: &NodeType) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind: node_type.id.to_string(),
+            position: [0.0, 0.0],
+            parameters: node_type.parameters.clone(),
+        }
+    }
+
+    pub fn parameter(&self, id: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|parameter| parameter.id == id)
+    }
+
+    /// Change a parameter's value, which must be of the same kind as before
+    pub fn set_parameter(&mut self, id: &str, value: ParameterValue) -> Result<(), GraphError> {
+        let name = self.to_string();
+        let error = |reason: String| GraphError::Parameter { node: name.clone(), parameter: id.to_string(), reason };
+        let parameter = self.parameters.iter_mut()
+            .find(|parameter| parameter.id == id)
+            .ok_or_else(|| error("No such parameter".to_string()))?;
+        if std::mem::discriminant(&parameter.value) != std::mem::discriminant(&value) {
+            return Err(error(format!("Expected a {} value, got a {}", parameter.value.kind(), value.kind())));
+        }
+        parameter.value = value;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.id.simple().to_string();
+        write!(f, "{} [{}]", self.kind, &id[..8])
+    }
+}
+
+/// A socket of a particular node
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SocketRef {
+    pub node: NodeId,
+    pub socket: String,
+}
+
+impl SocketRef {
+    pub fn new(node: NodeId, socket: impl Into<String>) -> Self {
+        Self { node, socket: socket.into() }
+    }
+}
+
+/// Edge from an output socket to an input socket
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Connection {
+    pub from: SocketRef,
+    pub to: SocketRef,
+}
+
+/// Nodes and the connections between them, as saved with a project.
+///
+/// Editing through [`connect`](Self::connect) keeps the graph valid; a graph loaded from
+/// disk should be checked with [`validate`](Self::validate) before it is evaluated.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeGraph {
+    nodes: Vec<Node>,
+    connections: Vec<Connection>,
+}
+
+impl NodeGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nodes in the order they were added
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.id == id)
+    }
+
+    /// Add a node of the given type with its default parameters
+    pub fn add_node(&mut self, registry: &NodeRegistry, kind: &str) -> Result<NodeId, GraphError> {
+        let node_type = registry.get(kind).ok_or_else(|| GraphError::UnknownNodeType(kind.to_string()))?;
+        let node = Node::new(node_type);
+        let id = node.id;
+        self.nodes.push(node);
+        Ok(id)
+    }
+
+    /// Remove a node along with every connection to or from it
+    pub fn remove_node(&mut self, id: NodeId) -> Option<Node> {
+        let index = self.nodes.iter().position(|node| node.id == id)?;
+        self.connections.retain(|c| c.from.node != id && c.to.node != id);
+        Some(self.nodes.remove(index))
+    }
+
+    /// Connect an output to an input, replacing whatever fed that input before. Fails,
+    /// leaving the graph unchanged, if a socket is missing, the types do not match or the
+    /// connection would close a cycle.
+    pub fn connect(&mut self, registry: &NodeRegistry, from: SocketRef, to: SocketRef) -> Result<(), GraphError> {
+        let connection = Connection { from, to };
+        self.check_connection(registry, &connection)?;
+
+        let replaced = self.disconnect(&connection.to);
+        self.connections.push(connection);
+        if let Err(e) = self.topological_order() {
+            self.connections.pop();
+            self.connections.extend(replaced);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove the connection feeding `input`, if any
+    pub fn disconnect(&mut self, input: &SocketRef) -> Option<Connection> {
+        let index = self.connections.iter().position(|c| c.to == *input)?;
+        Some(self.connections.remove(index))
+    }
+
+    /// Output connected to `input`, if any
+    pub fn source(&self, input: &SocketRef) -> Option<&SocketRef> {
+        self.connections.iter().find(|c| c.to == *input).map(|c| &c.from)
+    }
+
+    /// Check everything evaluation relies on: known node types, existing sockets of matching
+    /// types, one connection per input, no unconnected inputs without a default, no cycles
+    pub fn validate(&self, registry: &NodeRegistry) -> Result<(), GraphError> {
+        for node in &self.nodes {
+            registry.get(&node.kind).ok_or_else(|| GraphError::UnknownNodeType(node.kind.clone()))?;
+        }
+
+        let mut fed = HashSet::new();
+        for connection in &self.connections {
+            self.check_connection(registry, connection)?;
+            if !fed.insert(&connection.to) {
+                return Err(GraphError::DuplicateInput { input: self.describe(&connection.to) });
+            }
+        }
+
+        for node in &self.nodes {
+            let node_type = registry.get(&node.kind).ok_or_else(|| GraphError::UnknownNodeType(node.kind.clone()))?;
+            for input in &node_type.inputs {
+                let socket = SocketRef::new(node.id, input.name);
+                if !fed.contains(&socket) && node_type.parameter(input.name).is_none() {
+                    return Err(GraphError::UnconnectedInput { input: self.describe(&socket) });
+                }
+            }
+        }
+
+        self.topological_order().map(|_| ())
+    }
+
+    /// Node ids ordered so every node comes after the nodes feeding it. Ties keep the order
+    /// the nodes were added in.
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, GraphError> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            Visiting,
+            Done,
+        }
+
+        let index: HashMap<NodeId, usize> = self.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+        let mut upstream = vec![Vec::new(); self.nodes.len()];
+        for connection in &self.connections {
+            if let (Some(&from), Some(&to)) = (index.get(&connection.from.node), index.get(&connection.to.node)) {
+                upstream[to].push(from);
+            }
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for root in 0..self.nodes.len() {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+            // Depth-first over upstream nodes; the stack holds the path from `root`
+            let mut stack = vec![(root, 0)];
+            marks[root] = Mark::Visiting;
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                match upstream[node].get(*next) {
+                    Some(&source) => {
+                        *next += 1;
+                        match marks[source] {
+                            Mark::Unvisited => {
+                                marks[source] = Mark::Visiting;
+                                stack.push((source, 0));
+                            }
+                            Mark::Visiting => {
+                                // `source` is on the path, so data flows from it back round to itself
+                                let start = stack.iter().position(|&(i, _)| i == source).unwrap_or(0);
+                                let path: Vec<String> = std::iter::once(source)
+                                    .chain(stack[start..].iter().rev().map(|&(i, _)| i))
+                                    .map(|i| self.nodes[i].to_string())
+                                    .collect();
+                                return Err(GraphError::Cycle { path: path.join(" -> ") });
+                            }
+                            Mark::Done => {}
+                        }
+                    }
+                    None => {
+                        marks[node] = Mark::Done;
+                        order.push(self.nodes[node].id);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    fn check_connection(&self, registry: &NodeRegistry, connection: &Connection) -> Result<(), GraphError> {
+        let (from_node, from_type) = self.typed_node(registry, connection.from.node)?;
+        let (to_node, to_type) = self.typed_node(registry, connection.to.node)?;
+        let unknown = |node: &Node, direction, socket: &str| GraphError::UnknownSocket {
+            node: node.to_string(),
+            direction,
+            socket: socket.to_string(),
+        };
+        let output = from_type.output(&connection.from.socket)
+            .ok_or_else(|| unknown(from_node, "output", &connection.from.socket))?;
+        let input = to_type.input(&connection.to.socket)
+            .ok_or_else(|| unknown(to_node, "input", &connection.to.socket))?;
+
+        if !input.ty.accepts(output.ty) {
+            return Err(GraphError::TypeMismatch {
+                from: self.describe(&connection.from),
+                from_type: output.ty.name(),
+                to: self.describe(&connection.to),
+                to_type: input.ty.name(),
+            });
+        }
+        Ok(())
+    }
+
+    fn typed_node<'a>(&self, registry: &'a NodeRegistry, id: NodeId) -> Result<(&Node, &'a NodeType), GraphError> {
+        let node = self.node(id).ok_or(GraphError::UnknownNode(id))?;
+        let node_type = registry.get(&node.kind).ok_or_else(|| GraphError::UnknownNodeType(node.kind.clone()))?;
+        Ok((node, node_type))
+    }
+
+    /// `kind [id].socket`, for error messages
+    fn describe(&self, socket: &SocketRef) -> String {
+        match self.node(socket.node) {
+            Some(node) => format!("{node}.{}", socket.socket),
+            None => format!("{}.{}", socket.node, socket.socket),
+        }
+    }
+}
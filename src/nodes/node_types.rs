@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::parameter::Parameter;
+use crate::nodes::codegen::WgslFn;
+use crate::nodes::evaluator::EvaluateFn;
+use crate::nodes::nodes::{color_nodes, filter_nodes, math_nodes, noise_nodes, output_nodes};
+
+/// Kind of value carried by a socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketType {
+    Scalar,
+    Vec2,
+    /// Linear RGBA
+    Color,
+    /// Per-pixel image at the output resolution
+    Field,
+}
+
+impl SocketType {
+    pub const ALL: [SocketType; 4] = [SocketType::Scalar, SocketType::Vec2, SocketType::Color, SocketType::Field];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SocketType::Scalar => "scalar",
+            SocketType::Vec2 => "vec2",
+            SocketType::Color => "color",
+            SocketType::Field => "field",
+        }
+    }
+
+    /// Whether an output of type `from` may feed an input of this type. Values widen
+    /// (a scalar to a grey color, a constant to a uniform field) but never narrow.
+    pub fn accepts(&self, from: SocketType) -> bool {
+        *self == from
+            || matches!(
+                (from, self),
+                (SocketType::Scalar, SocketType::Color)
+                    | (SocketType::Scalar, SocketType::Field)
+                    | (SocketType::Color, SocketType::Field)
+            )
+    }
+}
+
+/// Named, typed input or output of a node type
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketDef {
+    pub name: &'static str,
+    pub ty: SocketType,
+}
+
+/// Grouping of node types in the editor's menus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeCategory {
+    Input,
+    Noise,
+    Math,
+    Filter,
+    Color,
+    Grain,
+    Output,
+}
+
+impl NodeCategory {
+    pub const ALL: [NodeCategory; 7] = [
+        NodeCategory::Input,
+        NodeCategory::Noise,
+        NodeCategory::Math,
+        NodeCategory::Filter,
+        NodeCategory::Color,
+        NodeCategory::Grain,
+        NodeCategory::Output,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeCategory::Input => "Input",
+            NodeCategory::Noise => "Noise",
+            NodeCategory::Math => "Math",
+            NodeCategory::Filter => "Filter",
+            NodeCategory::Color => "Color",
+            NodeCategory::Grain => "Grain",
+            NodeCategory::Output => "Output",
+        }
+    }
+}
+
+/// Description of a kind of node: its sockets and the parameters new nodes start with.
+///
+/// An input left unconnected takes the value of the node's parameter with the same id;
+/// inputs without such a parameter must be connected.
+#[derive(Debug, Clone)]
+pub struct NodeType {
+    /// Stable id stored in saved graphs, e.g. `math.add`
+    pub id: &'static str,
+    pub name: &'static str,
+    pub category: NodeCategory,
+    pub inputs: Vec<SocketDef>,
+    pub outputs: Vec<SocketDef>,
+    pub parameters: Vec<Parameter>,
+    /// CPU implementation; nodes without one can only run on the GPU
+    pub evaluate: Option<EvaluateFn>,
+    /// GPU implementation; nodes without one can only run on the CPU
+    pub wgsl: Option<WgslFn>,
+}
+
+impl NodeType {
+    pub fn new(id: &'static str, name: &'static str, category: NodeCategory) -> Self {
+        Self {
+            id,
+            name,
+            category,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            parameters: Vec::new(),
+            evaluate: None,
+            wgsl: None,
+        }
+    }
+
+    pub fn with_input(mut self, name: &'static str, ty: SocketType) -> Self {
+        self.inputs.push(SocketDef { name, ty });
+        self
+    }
+
+    pub fn with_output(mut self, name: &'static str, ty: SocketType) -> Self {
+        self.outputs.push(SocketDef { name, ty });
+        self
+    }
+
+    pub fn with_parameter(mut self, parameter: Parameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    pub fn with_evaluate(mut self, evaluate: EvaluateFn) -> Self {
+        self.evaluate = Some(evaluate);
+        self
+    }
+
+    pub fn with_wgsl(mut self, wgsl: WgslFn) -> Self {
+        self.wgsl = Some(wgsl);
+        self
+    }
+
+    pub fn input(&self, name: &str) -> Option<&SocketDef> {
+        self.inputs.iter().find(|socket| socket.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&SocketDef> {
+        self.outputs.iter().find(|socket| socket.name == name)
+    }
+
+    /// Default parameter of the given id
+    pub fn parameter(&self, id: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|parameter| parameter.id == id)
+    }
+}
+
+/// Node types a graph may use, looked up by id
+#[derive(Debug, Clone, Default)]
+pub struct NodeRegistry {
+    types: Vec<NodeType>,
+}
+
+impl NodeRegistry {
+    /// Every node type that ships with GrainForge
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        noise_nodes::register(&mut registry);
+        math_nodes::register(&mut registry);
+        filter_nodes::register(&mut registry);
+        color_nodes::register(&mut registry);
+        output_nodes::register(&mut registry);
+        registry
+    }
+
+    /// Add a node type, replacing any with the same id
+    pub fn register(&mut self, node_type: NodeType) {
+        match self.types.iter_mut().find(|existing| existing.id == node_type.id) {
+            Some(existing) => *existing = node_type,
+            None => self.types.push(node_type),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&NodeType> {
+        self.types.iter().find(|node_type| node_type.id == id)
+    }
+
+    /// Node types in registration order
+    pub fn types(&self) -> impl Iterator<Item = &NodeType> {
+        self.types.iter()
+    }
+}
@@ -0,0 +1,121 @@
+//! Color nodes. Fields carry linear RGBA unless a node says otherwise; alpha passes through.
+
+use std::sync::Arc;
+
+use crate::core::parameter::Parameter;
+use crate::nodes::evaluator::{Field, NodeContext, Value};
+use crate::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use crate::utils::color::{linear_to_srgb, luminance, srgb_to_linear};
+use crate::utils::math::mix;
+
+/// Rec. 709 weights, as in `utils::color::luminance`
+const LUMA_WGSL: &str = "vec3(0.2126, 0.7152, 0.0722)";
+
+pub fn register(registry: &mut NodeRegistry) {
+    registry.register(
+        NodeType::new("color.split", "Split Channels", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_output("r", SocketType::Field)
+            .with_output("g", SocketType::Field)
+            .with_output("b", SocketType::Field)
+            .with_output("a", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let color = ctx.field("color");
+                Ok((0..4).map(|c| Value::Field(Arc::new(color.map(|p| [p[c], p[c], p[c], 1.0])))).collect())
+            })
+            .with_wgsl(|_| {
+                Ok("out.r = vec4(vec3(color.r), 1.0);\n\
+                    out.g = vec4(vec3(color.g), 1.0);\n\
+                    out.b = vec4(vec3(color.b), 1.0);\n\
+                    out.a = vec4(vec3(color.a), 1.0);"
+                    .to_string())
+            }),
+    );
+    registry.register(
+        // Channels come from the red of each input, so grey fields and scalars both work
+        NodeType::new("color.merge", "Merge Channels", NodeCategory::Color)
+            .with_input("r", SocketType::Field)
+            .with_input("g", SocketType::Field)
+            .with_input("b", SocketType::Field)
+            .with_input("a", SocketType::Field)
+            .with_output("color", SocketType::Field)
+            .with_parameter(Parameter::new_float("r", "Red", "", 0.0, 0.0, 1.0))
+            .with_parameter(Parameter::new_float("g", "Green", "", 0.0, 0.0, 1.0))
+            .with_parameter(Parameter::new_float("b", "Blue", "", 0.0, 0.0, 1.0))
+            .with_parameter(Parameter::new_float("a", "Alpha", "", 1.0, 0.0, 1.0))
+            .with_evaluate(|ctx| {
+                let channels = ["r", "g", "b", "a"].map(|name| ctx.field(name));
+                let field = Field::from_fn(ctx.width, ctx.height, |x, y| channels.map(|channel| channel.get(x, y)[0]));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok("out.color = vec4(r.r, g.r, b.r, a.r);".to_string())),
+    );
+    registry.register(
+        NodeType::new("color.luminance", "Luminance", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_output("luminance", SocketType::Field)
+            .with_evaluate(|ctx| {
+                Ok(map_color(ctx, |[r, g, b, a]| {
+                    let l = luminance(r, g, b);
+                    [l, l, l, a]
+                }))
+            })
+            .with_wgsl(|_| Ok(format!("out.luminance = vec4(vec3(dot(color.rgb, {LUMA_WGSL})), color.a);"))),
+    );
+    registry.register(
+        NodeType::new("color.to_linear", "sRGB to Linear", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_output("color", SocketType::Field)
+            .with_evaluate(|ctx| Ok(map_color(ctx, |[r, g, b, a]| [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a])))
+            .with_wgsl(|_| {
+                Ok("let c = color.rgb;\n\
+                    out.color = vec4(select(pow((c + 0.055) / 1.055, vec3(2.4)), c / 12.92, c <= vec3(0.04045)), color.a);"
+                    .to_string())
+            }),
+    );
+    registry.register(
+        NodeType::new("color.to_srgb", "Linear to sRGB", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_output("color", SocketType::Field)
+            .with_evaluate(|ctx| Ok(map_color(ctx, |[r, g, b, a]| [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a])))
+            .with_wgsl(|_| {
+                Ok("let c = color.rgb;\n\
+                    out.color = vec4(select(1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3(0.0031308)), color.a);"
+                    .to_string())
+            }),
+    );
+    registry.register(
+        NodeType::new("color.tint", "Tint", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_input("tint", SocketType::Color)
+            .with_output("color", SocketType::Field)
+            .with_parameter(Parameter::new_color("tint", "Tint", "Multiplies the red, green and blue channels", [1.0, 0.9, 0.8, 1.0]))
+            .with_evaluate(|ctx| {
+                let tint = ctx.color("tint");
+                Ok(map_color(ctx, |[r, g, b, a]| [r * tint[0], g * tint[1], b * tint[2], a]))
+            })
+            .with_wgsl(|_| Ok("out.color = vec4(color.rgb * tint.rgb, color.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("color.saturation", "Saturation", NodeCategory::Color)
+            .with_input("color", SocketType::Field)
+            .with_input("saturation", SocketType::Scalar)
+            .with_output("color", SocketType::Field)
+            .with_parameter(Parameter::new_float("saturation", "Saturation", "0 is grey, 1 leaves the color alone", 1.0, 0.0, 4.0))
+            .with_evaluate(|ctx| {
+                let saturation = ctx.scalar("saturation");
+                Ok(map_color(ctx, |[r, g, b, a]| {
+                    let l = luminance(r, g, b);
+                    [mix(l, r, saturation), mix(l, g, saturation), mix(l, b, saturation), a]
+                }))
+            })
+            .with_wgsl(|_| {
+                Ok(format!("out.color = vec4(mix(vec3(dot(color.rgb, {LUMA_WGSL})), color.rgb, saturation), color.a);"))
+            }),
+    );
+}
+
+/// The `color` input with `f` applied to every pixel, as the node's single output
+fn map_color(ctx: &NodeContext, f: impl Fn([f32; 4]) -> [f32; 4] + Sync) -> Vec<Value> {
+    vec![Value::Field(Arc::new(ctx.field("color").map(f)))]
+}
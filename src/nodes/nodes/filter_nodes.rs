@@ -0,0 +1,214 @@
+//! Neighbourhood filters. On the GPU each tap re-evaluates the upstream graph through the
+//! input's sampler, so radii are capped to keep the fused shader affordable.
+
+use std::sync::Arc;
+
+use crate::core::parameter::Parameter;
+use crate::nodes::codegen::WgslNode;
+use crate::nodes::evaluator::{Field, NodeContext, Value};
+use crate::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+
+/// Widest Gaussian kernel, in pixels either side of the centre
+const MAX_BLUR_RADIUS: i32 = 48;
+/// Widest median window either side of the centre; it sorts up to 25 values per pixel
+const MAX_MEDIAN_RADIUS: i32 = 2;
+const MAX_MORPHOLOGY_RADIUS: i32 = 16;
+
+pub fn register(registry: &mut NodeRegistry) {
+    registry.register(
+        filter_type("filter.gaussian_blur", "Gaussian Blur")
+            .with_input("radius", SocketType::Scalar)
+            .with_parameter(blur_radius(2.0, "Standard deviation of the blur in pixels"))
+            .with_evaluate(|ctx| Ok(output(gaussian_blur(ctx.field("field"), ctx.scalar("radius")))))
+            .with_wgsl(|ctx| Ok(format!("{}out.field = blurred;", blur_wgsl(ctx)?))),
+    );
+    registry.register(
+        filter_type("filter.sharpen", "Sharpen")
+            .with_input("radius", SocketType::Scalar)
+            .with_input("amount", SocketType::Scalar)
+            .with_parameter(blur_radius(1.0, "Size of the detail that is boosted, in pixels"))
+            .with_parameter(Parameter::new_float("amount", "Amount", "How much detail is added back", 1.0, 0.0, 8.0))
+            .with_evaluate(|ctx| {
+                let (field, amount) = (ctx.field("field"), ctx.scalar("amount"));
+                let blurred = gaussian_blur(field, ctx.scalar("radius"));
+                Ok(output(Field::from_fn(ctx.width, ctx.height, |x, y| {
+                    let (f, b) = (field.get(x, y), blurred.get(x, y));
+                    [f[0] + amount * (f[0] - b[0]), f[1] + amount * (f[1] - b[1]), f[2] + amount * (f[2] - b[2]), f[3]]
+                })))
+            })
+            .with_wgsl(|ctx| {
+                Ok(format!("{}out.field = vec4(field.rgb + amount * (field.rgb - blurred.rgb), field.a);", blur_wgsl(ctx)?))
+            }),
+    );
+    registry.register(
+        filter_type("filter.high_pass", "High Pass")
+            .with_input("radius", SocketType::Scalar)
+            .with_parameter(blur_radius(4.0, "Detail smaller than this many pixels is kept"))
+            .with_evaluate(|ctx| {
+                let field = ctx.field("field");
+                let blurred = gaussian_blur(field, ctx.scalar("radius"));
+                Ok(output(Field::from_fn(ctx.width, ctx.height, |x, y| {
+                    let (f, b) = (field.get(x, y), blurred.get(x, y));
+                    // Centred on mid-grey, like a high-pass layer in an image editor
+                    [f[0] - b[0] + 0.5, f[1] - b[1] + 0.5, f[2] - b[2] + 0.5, f[3]]
+                })))
+            })
+            .with_wgsl(|ctx| Ok(format!("{}out.field = vec4(field.rgb - blurred.rgb + 0.5, field.a);", blur_wgsl(ctx)?))),
+    );
+    registry.register(
+        filter_type("filter.median", "Median")
+            .with_input("radius", SocketType::Scalar)
+            .with_parameter(Parameter::new_float("radius", "Radius", "Window size either side of the pixel", 1.0, 0.0, 2.0))
+            .with_evaluate(|ctx| {
+                let field = ctx.field("field");
+                let r = (ctx.scalar("radius") as i32).clamp(0, MAX_MEDIAN_RADIUS);
+                Ok(output(Field::from_fn(ctx.width, ctx.height, |x, y| {
+                    let mut window = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+                    for dy in -r..=r {
+                        for dx in -r..=r {
+                            window.push(field.sample(x as i32 + dx, y as i32 + dy));
+                        }
+                    }
+                    // Channels are sorted independently, as the shader's min/max network does
+                    let middle = window.len() / 2;
+                    std::array::from_fn(|c| {
+                        let mut channel: Vec<f32> = window.iter().map(|p| p[c]).collect();
+                        *channel.select_nth_unstable_by(middle, f32::total_cmp).1
+                    })
+                })))
+            })
+            .with_wgsl(|ctx| {
+                let sample = ctx.sampler("field")?;
+                Ok(format!(
+                    "let r = clamp(i32(radius), 0, {MAX_MEDIAN_RADIUS});\n\
+                     var window: array<vec4<f32>, 25>;\n\
+                     var n = 0;\n\
+                     for (var dy = -r; dy <= r; dy++) {{\n    \
+                         for (var dx = -r; dx <= r; dx++) {{\n        \
+                             window[n] = {sample}(px, vec2(dx, dy));\n        \
+                             n++;\n    \
+                         }}\n\
+                     }}\n\
+                     // Bubble sort with min/max sorts every channel independently\n\
+                     for (var i = 0; i < n; i++) {{\n    \
+                         for (var j = 0; j < n - 1 - i; j++) {{\n        \
+                             let a = window[j];\n        \
+                             let b = window[j + 1];\n        \
+                             window[j] = min(a, b);\n        \
+                             window[j + 1] = max(a, b);\n    \
+                         }}\n\
+                     }}\n\
+                     out.field = window[n / 2];"
+                ))
+            }),
+    );
+    registry.register(
+        filter_type("filter.dilate", "Dilate")
+            .with_input("radius", SocketType::Scalar)
+            .with_parameter(morphology_radius("Bright areas grow by this many pixels"))
+            .with_evaluate(|ctx| Ok(output(morphology(ctx, f32::max))))
+            .with_wgsl(|ctx| morphology_wgsl(ctx, "max")),
+    );
+    registry.register(
+        filter_type("filter.erode", "Erode")
+            .with_input("radius", SocketType::Scalar)
+            .with_parameter(morphology_radius("Dark areas grow by this many pixels"))
+            .with_evaluate(|ctx| Ok(output(morphology(ctx, f32::min))))
+            .with_wgsl(|ctx| morphology_wgsl(ctx, "min")),
+    );
+}
+
+/// Filter node type taking and producing a field named `field`
+fn filter_type(id: &'static str, name: &'static str) -> NodeType {
+    NodeType::new(id, name, NodeCategory::Filter)
+        .with_input("field", SocketType::Field)
+        .with_output("field", SocketType::Field)
+}
+
+fn blur_radius(radius: f32, description: &str) -> Parameter {
+    Parameter::new_float("radius", "Radius", description, radius, 0.0, 16.0)
+}
+
+fn morphology_radius(description: &str) -> Parameter {
+    Parameter::new_float("radius", "Radius", description, 1.0, 0.0, MAX_MORPHOLOGY_RADIUS as f32)
+}
+
+fn output(field: Field) -> Vec<Value> {
+    vec![Value::Field(Arc::new(field))]
+}
+
+/// Gaussian blur with standard deviation `sigma`, cut off at three deviations (matches
+/// `blur_wgsl`)
+fn gaussian_blur(field: &Field, sigma: f32) -> Field {
+    let sigma = sigma.max(0.0);
+    let r = ((3.0 * sigma).ceil() as i32).min(MAX_BLUR_RADIUS);
+    let s = sigma.max(1e-3);
+    Field::from_fn(field.width(), field.height(), |x, y| {
+        let mut sum = [0.0; 4];
+        let mut total = 0.0;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let w = (-((dx * dx + dy * dy) as f32) / (2.0 * s * s)).exp();
+                for (acc, v) in sum.iter_mut().zip(field.sample(x as i32 + dx, y as i32 + dy)) {
+                    *acc += w * v;
+                }
+                total += w;
+            }
+        }
+        sum.map(|acc| acc / total)
+    })
+}
+
+/// WGSL defining `blurred`, the node's `field` input through [`gaussian_blur`] by `radius`
+fn blur_wgsl(ctx: &WgslNode) -> Result<String, String> {
+    let sample = ctx.sampler("field")?;
+    Ok(format!(
+        "let sigma = max(radius, 0.0);\n\
+         let r = min(i32(ceil(3.0 * sigma)), {MAX_BLUR_RADIUS});\n\
+         let s = max(sigma, 1e-3);\n\
+         var sum = vec4(0.0);\n\
+         var total = 0.0;\n\
+         for (var dy = -r; dy <= r; dy++) {{\n    \
+             for (var dx = -r; dx <= r; dx++) {{\n        \
+                 let w = exp(-f32(dx * dx + dy * dy) / (2.0 * s * s));\n        \
+                 sum += w * {sample}(px, vec2(dx, dy));\n        \
+                 total += w;\n    \
+             }}\n\
+         }}\n\
+         let blurred = sum / total;\n"
+    ))
+}
+
+/// Per-channel `op` (max to dilate, min to erode) over a disc of the node's radius
+fn morphology(ctx: &NodeContext, op: fn(f32, f32) -> f32) -> Field {
+    let field = ctx.field("field");
+    let r = (ctx.scalar("radius") as i32).clamp(0, MAX_MORPHOLOGY_RADIUS);
+    Field::from_fn(ctx.width, ctx.height, |x, y| {
+        let mut result = field.get(x, y);
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy <= r * r {
+                    let v = field.sample(x as i32 + dx, y as i32 + dy);
+                    result = std::array::from_fn(|c| op(result[c], v[c]));
+                }
+            }
+        }
+        result
+    })
+}
+
+fn morphology_wgsl(ctx: &WgslNode, op: &str) -> Result<String, String> {
+    let sample = ctx.sampler("field")?;
+    Ok(format!(
+        "let r = clamp(i32(radius), 0, {MAX_MORPHOLOGY_RADIUS});\n\
+         var result = field;\n\
+         for (var dy = -r; dy <= r; dy++) {{\n    \
+             for (var dx = -r; dx <= r; dx++) {{\n        \
+                 if (dx * dx + dy * dy <= r * r) {{\n            \
+                     result = {op}(result, {sample}(px, vec2(dx, dy)));\n        \
+                 }}\n    \
+             }}\n\
+         }}\n\
+         out.field = result;"
+    ))
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use crate::core::parameter::{Parameter, ParameterValue};
+use crate::nodes::evaluator::{Field, NodeContext, Value};
+use crate::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use crate::utils::math::mix;
+
+pub fn register(registry: &mut NodeRegistry) {
+    // Constants feed most math chains, so they live with the math nodes. Their value is the
+    // parameter an unconnected input of the same name would read.
+    registry.register(
+        NodeType::new("input.value", "Value", NodeCategory::Input)
+            .with_output("value", SocketType::Scalar)
+            .with_parameter(Parameter::new_float("value", "Value", "Constant output", 0.5, -1000.0, 1000.0))
+            .with_evaluate(|ctx| Ok(vec![Value::Scalar(ctx.float("value")?)]))
+            .with_wgsl(|ctx| Ok(format!("out.value = {};", ctx.param("value")?))),
+    );
+    registry.register(
+        NodeType::new("input.vector", "Vector", NodeCategory::Input)
+            .with_output("vector", SocketType::Vec2)
+            .with_parameter(Parameter::new_vec2("vector", "Vector", "Constant output", [0.0, 0.0], -1000.0, 1000.0))
+            .with_evaluate(|ctx| constant(ctx, "vector"))
+            .with_wgsl(|ctx| Ok(format!("out.vector = {};", ctx.param("vector")?))),
+    );
+    registry.register(
+        NodeType::new("input.color", "Color", NodeCategory::Input)
+            .with_output("color", SocketType::Color)
+            .with_parameter(Parameter::new_color("color", "Color", "Constant linear RGBA output", [0.5, 0.5, 0.5, 1.0]))
+            .with_evaluate(|ctx| constant(ctx, "color"))
+            .with_wgsl(|ctx| Ok(format!("out.color = {};", ctx.param("color")?))),
+    );
+
+    // Arithmetic works on RGB; the result keeps the alpha of the first input
+    registry.register(
+        NodeType::new("math.add", "Add", NodeCategory::Math)
+            .with_input("a", SocketType::Field)
+            .with_input("b", SocketType::Field)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("a", "A", "First term", 0.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("b", "B", "Second term", 0.0, -1000.0, 1000.0))
+            .with_evaluate(|ctx| Ok(combine(ctx, ["a", "b"], |[a, b]| rgb(a, |c| a[c] + b[c]))))
+            .with_wgsl(|_| Ok("out.result = vec4(a.rgb + b.rgb, a.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("math.multiply", "Multiply", NodeCategory::Math)
+            .with_input("a", SocketType::Field)
+            .with_input("b", SocketType::Field)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("a", "A", "First factor", 1.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("b", "B", "Second factor", 1.0, -1000.0, 1000.0))
+            .with_evaluate(|ctx| Ok(combine(ctx, ["a", "b"], |[a, b]| rgb(a, |c| a[c] * b[c]))))
+            .with_wgsl(|_| Ok("out.result = vec4(a.rgb * b.rgb, a.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("math.mix", "Mix", NodeCategory::Math)
+            .with_input("a", SocketType::Field)
+            .with_input("b", SocketType::Field)
+            .with_input("factor", SocketType::Field)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("a", "A", "Result at factor 0", 0.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("b", "B", "Result at factor 1", 1.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("factor", "Factor", "Blend from A to B; connect a mask to vary it", 0.5, 0.0, 1.0))
+            .with_evaluate(|ctx| {
+                Ok(combine(ctx, ["a", "b", "factor"], |[a, b, t]| rgb(a, |c| mix(a[c], b[c], t[c]))))
+            })
+            .with_wgsl(|_| Ok("out.result = vec4(mix(a.rgb, b.rgb, factor.rgb), a.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("math.clamp", "Clamp", NodeCategory::Math)
+            .with_input("value", SocketType::Field)
+            .with_input("low", SocketType::Scalar)
+            .with_input("high", SocketType::Scalar)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("low", "Low", "Smallest result", 0.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("high", "High", "Largest result", 1.0, -1000.0, 1000.0))
+            .with_evaluate(|ctx| {
+                let (low, high) = (ctx.scalar("low"), ctx.scalar("high"));
+                // Not f32::clamp, which panics when the bounds cross; WGSL clamp is min(max())
+                Ok(combine(ctx, ["value"], |[v]| rgb(v, |c| v[c].max(low).min(high))))
+            })
+            .with_wgsl(|_| Ok("out.result = vec4(clamp(value.rgb, vec3(low), vec3(high)), value.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("math.remap", "Remap", NodeCategory::Math)
+            .with_input("value", SocketType::Field)
+            .with_input("from_min", SocketType::Scalar)
+            .with_input("from_max", SocketType::Scalar)
+            .with_input("to_min", SocketType::Scalar)
+            .with_input("to_max", SocketType::Scalar)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("from_min", "From Min", "Input mapped to To Min", 0.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("from_max", "From Max", "Input mapped to To Max", 1.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("to_min", "To Min", "", 0.0, -1000.0, 1000.0))
+            .with_parameter(Parameter::new_float("to_max", "To Max", "", 1.0, -1000.0, 1000.0))
+            .with_evaluate(|ctx| {
+                let (from_min, from_max) = (ctx.scalar("from_min"), ctx.scalar("from_max"));
+                let (to_min, to_max) = (ctx.scalar("to_min"), ctx.scalar("to_max"));
+                Ok(combine(ctx, ["value"], |[v]| {
+                    rgb(v, |c| (v[c] - from_min) / (from_max - from_min) * (to_max - to_min) + to_min)
+                }))
+            })
+            .with_wgsl(|_| {
+                Ok("out.result = vec4((value.rgb - from_min) / (from_max - from_min) * (to_max - to_min) + to_min, value.a);"
+                    .to_string())
+            }),
+    );
+    registry.register(
+        NodeType::new("math.power", "Power", NodeCategory::Math)
+            .with_input("value", SocketType::Field)
+            .with_input("exponent", SocketType::Scalar)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("exponent", "Exponent", "Applied to the magnitude; the sign is kept", 1.0, 0.01, 16.0))
+            .with_evaluate(|ctx| {
+                let exponent = ctx.scalar("exponent");
+                Ok(combine(ctx, ["value"], |[v]| rgb(v, |c| v[c].abs().powf(exponent).copysign(v[c]))))
+            })
+            .with_wgsl(|_| Ok("out.result = vec4(sign(value.rgb) * pow(abs(value.rgb), vec3(exponent)), value.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("math.curve", "Curve", NodeCategory::Math)
+            .with_input("value", SocketType::Field)
+            .with_input("shadows", SocketType::Scalar)
+            .with_input("midtones", SocketType::Scalar)
+            .with_input("highlights", SocketType::Scalar)
+            .with_output("result", SocketType::Field)
+            .with_parameter(Parameter::new_float("shadows", "Shadows", "Result for an input of 0", 0.0, -1.0, 2.0))
+            .with_parameter(Parameter::new_float("midtones", "Midtones", "Result for an input of 0.5", 0.5, -1.0, 2.0))
+            .with_parameter(Parameter::new_float("highlights", "Highlights", "Result for an input of 1", 1.0, -1.0, 2.0))
+            .with_evaluate(|ctx| {
+                let points = [ctx.scalar("shadows"), ctx.scalar("midtones"), ctx.scalar("highlights")];
+                Ok(combine(ctx, ["value"], |[v]| rgb(v, |c| curve(v[c], points))))
+            })
+            .with_wgsl(|_| {
+                Ok("let x = value.rgb;\n\
+                    out.result = vec4(\n    \
+                        2.0 * (x - 0.5) * (x - 1.0) * shadows - 4.0 * x * (x - 1.0) * midtones + 2.0 * x * (x - 0.5) * highlights,\n    \
+                        value.a\n\
+                    );"
+                    .to_string())
+            }),
+    );
+}
+
+/// Output a vector or color parameter as is
+fn constant(ctx: &NodeContext, id: &str) -> Result<Vec<Value>, String> {
+    match ctx.parameter(id).map(|p| &p.value) {
+        Some(ParameterValue::Vec2(v)) => Ok(vec![Value::Vec2(*v)]),
+        Some(ParameterValue::Color(v)) => Ok(vec![Value::Color(*v)]),
+        _ => Err(format!("Missing parameter '{id}'")),
+    }
+}
+
+/// Combine the named field inputs pixel by pixel into the node's single output
+fn combine<const N: usize>(ctx: &NodeContext, inputs: [&str; N], f: impl Fn([[f32; 4]; N]) -> [f32; 4] + Sync) -> Vec<Value> {
+    let fields = inputs.map(|name| ctx.field(name));
+    let field = Field::from_fn(ctx.width, ctx.height, |x, y| f(fields.map(|field| field.get(x, y))));
+    vec![Value::Field(Arc::new(field))]
+}
+
+/// `f` of each RGB channel index, with the alpha of `alpha_from`
+fn rgb(alpha_from: [f32; 4], f: impl Fn(usize) -> f32) -> [f32; 4] {
+    [f(0), f(1), f(2), alpha_from[3]]
+}
+
+/// Quadratic tone curve through (0, shadows), (0.5, midtones) and (1, highlights); the
+/// defaults give the identity
+fn curve(x: f32, [shadows, midtones, highlights]: [f32; 3]) -> f32 {
+    2.0 * (x - 0.5) * (x - 1.0) * shadows - 4.0 * x * (x - 1.0) * midtones + 2.0 * x * (x - 0.5) * highlights
+}
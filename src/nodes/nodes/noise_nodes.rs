@@ -0,0 +1,212 @@
+//! The noise library of noise.wgsl as nodes, with the CPU ports from `engine::cpu_noise`.
+//!
+//! Every noise node samples a lattice placed by the same four inputs: `seed` keys the hashes,
+//! `scale` is the number of lattice cells across the output's height (cells stay square on
+//! wide outputs), `offset` moves the lattice in cells and `tiling` wraps it so the output
+//! repeats seamlessly. While tiling, the cell counts are snapped to whole, even numbers.
+
+use std::sync::Arc;
+
+use crate::core::parameter::Parameter;
+use crate::engine::cpu_noise::{
+    box_muller, domain_warp, fbm, hash21, hash22, seed_noise, simplex_noise, value_noise, voronoi, wrap_cell,
+};
+use crate::nodes::evaluator::{Field, NodeContext, Value};
+use crate::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use crate::utils::math::Vec2;
+
+/// Start of every noise node's WGSL body, mirroring [`Lattice`]: seeds the hashes and
+/// defines the sample point `p` and the lattice `period`
+const LATTICE: &str = "seed_noise(vec2(u32(seed), 0u));
+var cells = scale * vec2(f32(px.size.x) / f32(px.size.y), 1.0);
+if (tiling > 0.5) {
+    cells = max(vec2(2.0), 2.0 * floor(cells * 0.5 + 0.5));
+}
+let period = select(vec2(0.0), cells, tiling > 0.5);
+let p = pixel_uv(px) * cells + offset;
+";
+
+pub fn register(registry: &mut NodeRegistry) {
+    registry.register(
+        noise_type("noise.value", "Value Noise", 8.0)
+            .with_output("noise", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let field = noise_field(ctx, |p, period| grey(value_noise(p, period)));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok(format!("{LATTICE}out.noise = vec4(vec3(value_noise(p, period)), 1.0);"))),
+    );
+    registry.register(
+        noise_type("noise.simplex", "Simplex Noise", 8.0)
+            .with_output("noise", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let field = noise_field(ctx, |p, period| grey(simplex_noise(p, period)));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok(format!("{LATTICE}out.noise = vec4(vec3(simplex_noise(p, period)), 1.0);"))),
+    );
+    registry.register(
+        noise_type("noise.voronoi", "Voronoi", 8.0)
+            .with_input("jitter", SocketType::Scalar)
+            .with_output("distance", SocketType::Field)
+            .with_output("cell_id", SocketType::Field)
+            .with_parameter(Parameter::new_float("jitter", "Jitter", "How far cell points stray from the grid", 1.0, 0.0, 1.0))
+            .with_evaluate(|ctx| {
+                let jitter = ctx.scalar("jitter");
+                // One pass for both outputs: the distance, then the cell's color
+                let cells = noise_field(ctx, |p, period| {
+                    let result = voronoi(p, jitter, period);
+                    let id = wrap_cell(result.cell_id, period);
+                    let color = hash22(id);
+                    [result.distance, color.x, color.y, hash21(id)]
+                });
+                let distance = cells.map(|[d, ..]| grey(d));
+                let cell_id = cells.map(|[_, r, g, b]| [r, g, b, 1.0]);
+                Ok(vec![Value::Field(Arc::new(distance)), Value::Field(Arc::new(cell_id))])
+            })
+            .with_wgsl(|_| {
+                Ok(format!(
+                    "{LATTICE}let cell = voronoi(p, jitter, period);\n\
+                     out.distance = vec4(vec3(cell.distance), 1.0);\n\
+                     let id = wrap_cell(cell.cell_id, period);\n\
+                     out.cell_id = vec4(hash22(id), hash21(id), 1.0);"
+                ))
+            }),
+    );
+    registry.register(
+        noise_type("noise.fbm", "Fractal Noise", 4.0)
+            .with_input("octaves", SocketType::Scalar)
+            .with_input("lacunarity", SocketType::Scalar)
+            .with_input("persistence", SocketType::Scalar)
+            .with_output("noise", SocketType::Field)
+            .with_parameter(octaves_parameter())
+            .with_parameter(Parameter::new_float(
+                "lacunarity",
+                "Lacunarity",
+                "Frequency gain per octave; keep it whole while tiling",
+                2.0,
+                1.0,
+                4.0,
+            ))
+            .with_parameter(Parameter::new_float("persistence", "Persistence", "Amplitude gain per octave", 0.5, 0.0, 1.0))
+            .with_evaluate(|ctx| {
+                let octaves = ctx.scalar("octaves") as i32;
+                let (lacunarity, persistence) = (ctx.scalar("lacunarity"), ctx.scalar("persistence"));
+                let field = noise_field(ctx, |p, period| grey(fbm(p, octaves, lacunarity, persistence, period)));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| {
+                Ok(format!(
+                    "{LATTICE}out.noise = vec4(vec3(fbm(p, i32(octaves), lacunarity, persistence, period)), 1.0);"
+                ))
+            }),
+    );
+    registry.register(
+        noise_type("noise.domain_warp", "Domain Warp", 4.0)
+            .with_input("strength", SocketType::Scalar)
+            .with_input("octaves", SocketType::Scalar)
+            .with_output("noise", SocketType::Field)
+            .with_parameter(Parameter::new_float("strength", "Strength", "How far the noise bends itself", 1.0, 0.0, 8.0))
+            .with_parameter(octaves_parameter())
+            .with_evaluate(|ctx| {
+                let (strength, octaves) = (ctx.scalar("strength"), ctx.scalar("octaves") as i32);
+                let field = noise_field(ctx, |p, period| grey(domain_warp(p, strength, octaves, period)));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| {
+                Ok(format!("{LATTICE}out.noise = vec4(vec3(domain_warp(p, strength, i32(octaves), period)), 1.0);"))
+            }),
+    );
+    registry.register(
+        noise_type("noise.white", "White Noise", 512.0)
+            .with_output("noise", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let field = noise_field(ctx, |p, period| grey(hash21(wrap_cell(p.floor(), period))));
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok(format!("{LATTICE}out.noise = vec4(vec3(hash21(wrap_cell(floor(p), period))), 1.0);"))),
+    );
+    registry.register(
+        noise_type("noise.gaussian", "Gaussian Noise", 512.0)
+            .with_output("noise", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let field = noise_field(ctx, |p, period| {
+                    let u = hash22(wrap_cell(p.floor(), period));
+                    grey(box_muller(u.x, u.y).x)
+                });
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| {
+                Ok(format!(
+                    "{LATTICE}let u = hash22(wrap_cell(floor(p), period));\n\
+                     out.noise = vec4(vec3(box_muller(u.x, u.y).x), 1.0);"
+                ))
+            }),
+    );
+}
+
+/// Noise node type with the lattice inputs every noise node shares
+fn noise_type(id: &'static str, name: &'static str, scale: f32) -> NodeType {
+    NodeType::new(id, name, NodeCategory::Noise)
+        .with_input("seed", SocketType::Scalar)
+        .with_input("scale", SocketType::Scalar)
+        .with_input("offset", SocketType::Vec2)
+        .with_input("tiling", SocketType::Scalar)
+        .with_parameter(Parameter::new_float("seed", "Seed", "Picks a different pattern", 0.0, 0.0, 65535.0))
+        .with_parameter(Parameter::new_float("scale", "Scale", "Lattice cells across the output's height", scale, 0.01, 4096.0))
+        .with_parameter(Parameter::new_vec2("offset", "Offset", "Lattice shift in cells", [0.0, 0.0], -1000.0, 1000.0))
+        .with_parameter(Parameter::new_bool("tiling", "Tiling", "Repeat seamlessly across the output's edges", false))
+}
+
+fn octaves_parameter() -> Parameter {
+    Parameter::new_float("octaves", "Octaves", "Layers of detail; fractions are dropped", 4.0, 1.0, 8.0)
+}
+
+/// Where a noise node samples its lattice (matches `LATTICE`)
+struct Lattice {
+    seed: u32,
+    size: Vec2,
+    cells: Vec2,
+    offset: Vec2,
+    period: Vec2,
+}
+
+impl Lattice {
+    fn new(ctx: &NodeContext) -> Self {
+        let size = Vec2::new(ctx.width as f32, ctx.height as f32);
+        let scale = ctx.scalar("scale");
+        let tiling = ctx.scalar("tiling") > 0.5;
+        let snap = |cells: f32| (2.0 * (cells * 0.5 + 0.5).floor()).max(2.0);
+
+        let mut cells = Vec2::new(scale * (size.x / size.y), scale);
+        if tiling {
+            cells = Vec2::new(snap(cells.x), snap(cells.y));
+        }
+        let [x, y] = ctx.vec2("offset");
+        Self {
+            seed: ctx.scalar("seed") as u32,
+            size,
+            cells,
+            offset: Vec2::new(x, y),
+            period: if tiling { cells } else { Vec2::ZERO },
+        }
+    }
+
+    fn point(&self, x: u32, y: u32) -> Vec2 {
+        Vec2::new(x as f32, y as f32) / self.size * self.cells + self.offset
+    }
+}
+
+/// Sample `f(p, period)` at every pixel, keyed on the node's seed
+fn noise_field(ctx: &NodeContext, f: impl Fn(Vec2, Vec2) -> [f32; 4] + Sync) -> Field {
+    let lattice = Lattice::new(ctx);
+    Field::from_fn(ctx.width, ctx.height, |x, y| {
+        // The key is per thread, and rows are split across threads
+        seed_noise([lattice.seed, 0]);
+        f(lattice.point(x, y), lattice.period)
+    })
+}
+
+fn grey(v: f32) -> [f32; 4] {
+    [v, v, v, 1.0]
+}
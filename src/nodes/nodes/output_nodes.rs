@@ -0,0 +1,13 @@
+use crate::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+
+/// Id of the node whose input is the graph's result
+pub const GRAIN_OUTPUT: &str = "output.grain";
+
+pub fn register(registry: &mut NodeRegistry) {
+    // The evaluator reads the result straight from the input, so there is nothing to compute
+    registry.register(
+        NodeType::new(GRAIN_OUTPUT, "Grain Output", NodeCategory::Output)
+            .with_input("grain", SocketType::Field)
+            .with_evaluate(|_| Ok(Vec::new())),
+    );
+}
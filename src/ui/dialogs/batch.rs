@@ -0,0 +1,81 @@
+use std::time::Duration;
+use egui::Context;
+use crate::app::batch::BatchRun;
+use crate::app::state::AppState;
+use crate::export::batch::BatchJob;
+
+// How often a running batch refreshes its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Grain a folder of stills with the current stock and export settings
+pub fn show(ctx: &Context, state: &mut AppState) {
+    let mut open = state.batch.open;
+    egui::Window::new("Batch").open(&mut open).show(ctx, |ui| {
+        let batch = &mut state.batch;
+        let running = batch.run.is_some();
+
+        ui.add_enabled_ui(!running, |ui| {
+            egui::Grid::new("batch_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Input:");
+                ui.text_edit_singleline(&mut batch.input)
+                    .on_hover_text("Glob of PNG or TIFF plates, e.g. stills/*.tif");
+                ui.end_row();
+                ui.label("Output folder:");
+                ui.text_edit_singleline(&mut batch.output_dir);
+                ui.end_row();
+                ui.label("Naming:");
+                ui.text_edit_singleline(&mut batch.naming)
+                    .on_hover_text("{name}, {ext} and {stock} are replaced; the extension picks the format");
+                ui.end_row();
+            });
+            ui.checkbox(&mut batch.force, "Overwrite existing outputs");
+        });
+
+        ui.horizontal(|ui| {
+            match &batch.run {
+                Some(run) => {
+                    if ui.button("Cancel").clicked() {
+                        run.cancel();
+                    }
+                    ui.add(egui::ProgressBar::new(run.files_done as f32 / run.file_count.max(1) as f32)
+                        .text(format!("{}/{}", run.files_done, run.file_count)));
+                }
+                None => {
+                    if ui.button("Run").clicked() {
+                        let job = BatchJob {
+                            naming: batch.naming.trim().to_string(),
+                            format: state.output_format,
+                            blend_mode: state.blend_mode,
+                            samples: state.grain_samples,
+                            force: batch.force,
+                            ..BatchJob::new(batch.input.trim(), state.film_stock.clone(), batch.output_dir.trim())
+                        };
+                        batch.log.clear();
+                        batch.run = Some(BatchRun::start(job));
+                    }
+                }
+            }
+        });
+
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(200.0).stick_to_bottom(true).show(ui, |ui| {
+            for line in &batch.log {
+                ui.label(line);
+            }
+        });
+    });
+    state.batch.open = open;
+
+    let batch = &mut state.batch;
+    let Some(run) = &mut batch.run else {
+        return;
+    };
+    ctx.request_repaint_after(PROGRESS_INTERVAL);
+    if let Some(result) = run.poll(&mut batch.log) {
+        state.status_message = Some(match result {
+            Ok(report) => format!("Batch: {}", report.summary()),
+            Err(e) => format!("Batch failed: {e}"),
+        });
+        batch.run = None;
+    }
+}
@@ -1,3 +1,4 @@
 pub mod export;
 pub mod about;
 pub mod preferences;
+pub mod batch;
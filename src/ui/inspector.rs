@@ -2,6 +2,8 @@ use egui::Ui;
 use crate::app::state::{AppState, EditMode};
 use crate::core::parameter::{ParameterValue, ParameterRange};
 use crate::core::history::Command;
+use crate::core::film_stock::{DamageParameters, DamagePolarity, PostEffect, PostProcessChain};
+use crate::utils::validation::BoundedFloat;
 
 pub fn show(ui: &mut Ui, state: &mut AppState) {
     ui.heading("Inspector");
@@ -46,6 +48,15 @@ pub fn show(ui: &mut Ui, state: &mut AppState) {
                     state.history.push(cmd);
                 }
             });
+
+            ui.collapsing("Post-processing", |ui| {
+                let defaults = PostEffect::defaults_for(&state.film_stock);
+                post_process_section(ui, &mut state.film_stock.post_process, defaults);
+            });
+
+            ui.collapsing("Film damage", |ui| {
+                damage_section(ui, &mut state.film_stock.damage);
+            });
         }
         EditMode::Advanced => {
             ui.label("Node Properties (Advanced Mode)");
@@ -53,3 +64,98 @@ pub fn show(ui: &mut Ui, state: &mut AppState) {
         }
     }
 }
+
+/// Reordering requested from an effect's header row
+enum ChainEdit {
+    MoveUp(usize),
+    MoveDown(usize),
+    Remove(usize),
+}
+
+/// The stock's post-process chain, applied top to bottom.
+/// Effects are added with `defaults`, e.g. set up to suit the current stock
+fn post_process_section(ui: &mut Ui, chain: &mut PostProcessChain, defaults: [PostEffect; 3]) {
+    let count = chain.effects.len();
+    let mut edit = None;
+
+    for (index, effect) in chain.effects.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.horizontal(|ui| {
+                ui.strong(effect.name());
+                if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                    edit = Some(ChainEdit::MoveUp(index));
+                }
+                if ui.add_enabled(index + 1 < count, egui::Button::new("Down")).clicked() {
+                    edit = Some(ChainEdit::MoveDown(index));
+                }
+                if ui.button("Remove").clicked() {
+                    edit = Some(ChainEdit::Remove(index));
+                }
+            });
+
+            match effect {
+                PostEffect::Blur { radius } => bounded_slider(ui, "Radius", radius),
+                PostEffect::Halation { threshold, radius, strength, tint } => {
+                    bounded_slider(ui, "Threshold", threshold);
+                    bounded_slider(ui, "Radius", radius);
+                    bounded_slider(ui, "Strength", strength);
+                    for (name, channel) in ["Tint R", "Tint G", "Tint B"].into_iter().zip(tint) {
+                        bounded_slider(ui, name, channel);
+                    }
+                }
+                PostEffect::UnsharpMask { radius, amount } => {
+                    bounded_slider(ui, "Radius", radius);
+                    bounded_slider(ui, "Amount", amount);
+                }
+            }
+        });
+        ui.separator();
+    }
+
+    match edit {
+        Some(ChainEdit::MoveUp(index)) => chain.effects.swap(index - 1, index),
+        Some(ChainEdit::MoveDown(index)) => chain.effects.swap(index, index + 1),
+        Some(ChainEdit::Remove(index)) => {
+            chain.effects.remove(index);
+        }
+        None => {}
+    }
+
+    ui.menu_button("Add effect", |ui| {
+        for effect in defaults.clone() {
+            if ui.button(effect.name()).clicked() {
+                chain.effects.push(effect);
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+fn damage_section(ui: &mut Ui, damage: &mut DamageParameters) {
+    bounded_slider(ui, "Dust", &mut damage.dust);
+    bounded_slider(ui, "Dust size", &mut damage.dust_size);
+    bounded_slider(ui, "Hairs", &mut damage.hairs);
+    bounded_slider(ui, "Scratches", &mut damage.scratches);
+    ui.horizontal(|ui| {
+        ui.label("Prints");
+        egui::ComboBox::from_id_salt("damage_polarity")
+            .selected_text(damage.polarity.name())
+            .show_ui(ui, |ui| {
+                for polarity in DamagePolarity::ALL {
+                    ui.selectable_value(&mut damage.polarity, polarity, polarity.name());
+                }
+            });
+    });
+    bounded_slider(ui, "Gate weave", &mut damage.gate_weave);
+    bounded_slider(ui, "Flicker", &mut damage.flicker);
+}
+
+fn bounded_slider(ui: &mut Ui, label: &str, value: &mut BoundedFloat) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut current = value.get();
+        if ui.add(egui::Slider::new(&mut current, value.min..=value.max)).changed() {
+            value.set(current);
+        }
+    });
+}
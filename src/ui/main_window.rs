@@ -1,6 +1,10 @@
+use std::time::Duration;
 use egui::{Context, SidePanel, TopBottomPanel, CentralPanel};
 use crate::app::state::AppState;
 
+// How often hot reload checks the shader directory
+const SHADER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 pub fn show(ctx: &Context, state: &mut AppState) {
     // Global Keyboard Shortcuts
     if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Z)) {
@@ -10,6 +14,8 @@ pub fn show(ctx: &Context, state: &mut AppState) {
         state.history.redo(&mut state.parameters);
     }
 
+    reload_shaders(ctx, state);
+
     // Toolbar (Top)
     TopBottomPanel::top("toolbar").show(ctx, |ui| {
         crate::ui::toolbar::show(ui, state);
@@ -18,9 +24,12 @@ pub fn show(ctx: &Context, state: &mut AppState) {
     // SB (Bottom)
     TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
         ui.horizontal(|ui| {
-            ui.label("Ready");
+            ui.label(state.status_message.as_deref().unwrap_or("Ready"));
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label("GPU: Active");
+                // Report what actually rendered, which is the CPU after a GPU failure
+                if let Some(backend) = state.preview.backend {
+                    ui.label(format!("{}: Active", backend.name()));
+                }
             });
         });
     });
@@ -45,4 +54,24 @@ pub fn show(ctx: &Context, state: &mut AppState) {
     CentralPanel::default().show(ctx, |ui| {
         crate::ui::preview::show(ui, state);
     });
+
+    crate::ui::dialogs::batch::show(ctx, state);
+}
+
+/// Recompile edited shaders in hot-reload mode and report the outcome in the status bar
+fn reload_shaders(ctx: &Context, state: &mut AppState) {
+    let (Some(reload), Some(preview)) = (&mut state.shader_reload, &mut state.gpu_preview) else {
+        return;
+    };
+    // Keep polling while idle, since saving a file in an editor is not an egui event
+    ctx.request_repaint_after(SHADER_POLL_INTERVAL);
+
+    match reload.poll(preview) {
+        Some(Ok(())) => {
+            state.status_message = Some(format!("Shaders reloaded from {}", reload.watcher().dir().display()));
+            state.preview.rendered_params = None;
+        }
+        Some(Err(e)) => state.status_message = Some(format!("Shader reload failed, keeping previous shaders: {e}")),
+        None => {}
+    }
 }
@@ -1,9 +1,28 @@
+use std::path::Path;
 use egui::Ui;
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
 use crate::app::state::AppState;
+use crate::core::import;
+use crate::core::error::GrainError;
+use crate::core::film_stock::{DamageParameters, GrainSynthesis, PostProcessChain};
+use crate::engine::backend::RenderBackend;
+use crate::engine::cpu_renderer::CpuGrainRenderer;
+use crate::engine::damage::DamageGenerator;
+use crate::engine::grain_renderer::{BlendMode, GrainParams};
+use crate::engine::output_format::OutputFormat;
+use crate::engine::tiled_renderer::{TilePlan, TiledRenderer, DEFAULT_TILE_OVERLAP, DEFAULT_TILE_SIZE};
+use crate::export::sequence_export::export_still;
+
+// Longest preview edge; exports render at full plate resolution
+const PREVIEW_MAX_SIZE: u32 = 512;
+// Output size when exporting a standalone grain texture
+const STANDALONE_EXPORT_SIZE: u32 = 1024;
+// Boolean-model samples per pixel in the preview; exports use the full setting
+const PREVIEW_BOOLEAN_SAMPLES: u32 = 4;
 
 pub fn show(ui: &mut Ui, state: &mut AppState) {
     ui.heading("Preview Canvas");
-    
+
     // Grain parameter sliders
     ui.horizontal(|ui| {
         ui.label("Amount:");
@@ -15,22 +34,263 @@ pub fn show(ui: &mut Ui, state: &mut AppState) {
     });
     ui.horizontal(|ui| {
         ui.label("Seed:");
-        ui.add(egui::Slider::new(&mut state.preview_seed, 0.0..=100.0));
+        // Text rather than a DragValue, which goes through f64 and would round large seeds
+        let id = ui.make_persistent_id("seed");
+        // Outside an edit the field follows the stock, so text that isn't a seed reverts
+        if !ui.memory(|memory| memory.has_focus(id)) {
+            state.seed_text = state.seed().to_string();
+        }
+        let response = ui.add(egui::TextEdit::singleline(&mut state.seed_text).id(id).desired_width(180.0));
+        // Applied once the edit ends (Enter also drops focus), so partial input never re-renders
+        if response.lost_focus() {
+            if let Ok(seed) = state.seed_text.trim().parse() {
+                state.set_seed(seed);
+            }
+        }
+        if ui.button("Randomize").clicked() {
+            state.reseed();
+        }
     });
-    
+    ui.horizontal(|ui| {
+        ui.label("Synthesis:");
+        let synthesis = &mut state.film_stock.grain.synthesis;
+        egui::ComboBox::from_id_salt("synthesis")
+            .selected_text(synthesis.name())
+            .show_ui(ui, |ui| {
+                for mode in GrainSynthesis::ALL {
+                    ui.selectable_value(synthesis, mode, mode.name());
+                }
+            });
+        if *synthesis == GrainSynthesis::Boolean {
+            ui.label("Samples:");
+            ui.add(egui::Slider::new(&mut state.grain_samples, 1..=256).logarithmic(true));
+        }
+    });
+
+    // Photograph to apply grain to
+    ui.horizontal(|ui| {
+        ui.label("Plate:");
+        ui.text_edit_singleline(&mut state.plate_path);
+        if ui.button("Load").clicked() {
+            match import::load_plate(Path::new(state.plate_path.trim())) {
+                Ok(image) => {
+                    state.plate = Some(image);
+                    state.status_message = None;
+                }
+                Err(e) => state.status_message = Some(e.to_string()),
+            }
+            state.preview.plate_changed = true;
+        }
+        if state.plate.is_some() && ui.button("Clear").clicked() {
+            state.plate = None;
+            state.preview.plate_changed = true;
+        }
+        egui::ComboBox::from_id_salt("blend_mode")
+            .selected_text(state.blend_mode.name())
+            .show_ui(ui, |ui| {
+                for mode in BlendMode::ALL {
+                    ui.selectable_value(&mut state.blend_mode, mode, mode.name());
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Export:");
+        ui.text_edit_singleline(&mut state.export_path);
+        ui.checkbox(&mut state.tileable, "Tileable");
+        egui::ComboBox::from_id_salt("output_format")
+            .selected_text(state.output_format.name())
+            .show_ui(ui, |ui| {
+                for format in OutputFormat::ALL {
+                    ui.selectable_value(&mut state.output_format, format, format.name());
+                }
+            });
+        ui.checkbox(&mut state.tiled_export, "Tiled")
+            .on_hover_text("Render in tiles and stream them to disk (PNG/TIFF), for very large plates");
+        if ui.button("Export").clicked() {
+            let path = Path::new(state.export_path.trim());
+            let result = if state.tiled_export {
+                export_tiled(state, path)
+            } else {
+                export_full(state, path)
+            };
+            state.status_message = Some(match result {
+                Ok(()) => format!("Exported {}", state.export_path.trim()),
+                Err(e) => e.to_string(),
+            });
+        }
+    });
+
     ui.separator();
-    
-    // Placeholder for rendered texture
-    // In the future, this will display the egui::TextureHandle from GrainRenderer
+
+    update_preview(ui.ctx(), state);
+
     let available = ui.available_size();
     let (rect, _response) = ui.allocate_exact_size(available, egui::Sense::hover());
     ui.painter().rect_filled(rect, 0.0, egui::Color32::from_gray(40));
-    ui.painter().text(
-        rect.center(),
-        egui::Align2::CENTER_CENTER,
-        format!("Grain: {:.2} | Size: {:.2}", state.grain_amount, state.grain_size),
-        egui::FontId::default(),
-        egui::Color32::WHITE,
+
+    if let Some(texture) = &state.preview.texture {
+        // Fit the preview inside the canvas, keeping its aspect ratio
+        let size = texture.size_vec2();
+        let scale = (rect.width() / size.x).min(rect.height() / size.y).min(1.0);
+        let image_rect = egui::Rect::from_center_size(rect.center(), size * scale);
+        ui.painter().image(
+            texture.id(),
+            image_rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+/// Grain parameters for the current stock, with the preview sliders applied
+fn preview_params(state: &AppState, width: u32, height: u32) -> GrainParams {
+    let mut params = GrainParams::from_film_stock(&state.film_stock, width, height)
+        .with_blend_mode(state.blend_mode)
+        .with_samples(state.grain_samples)
+        .with_tileable(state.tileable);
+    params.grain_amount = state.grain_amount;
+    params.grain_size = state.grain_size;
+    params
+}
+
+/// Re-render the preview texture when the parameters or the plate change
+fn update_preview(ctx: &egui::Context, state: &mut AppState) {
+    if state.preview.plate_changed {
+        state.preview.plate = state.plate.as_ref().map(|plate| {
+            let (w, h) = plate.dimensions();
+            if w.max(h) <= PREVIEW_MAX_SIZE {
+                return plate.clone();
+            }
+            let scale = PREVIEW_MAX_SIZE as f32 / w.max(h) as f32;
+            image::imageops::thumbnail(plate, (w as f32 * scale) as u32, (h as f32 * scale) as u32)
+        });
+    }
+    let (width, height) = state.preview.plate.as_ref()
+        .map_or((PREVIEW_MAX_SIZE, PREVIEW_MAX_SIZE), |p| p.dimensions());
+
+    let mut params = preview_params(state, width, height);
+    params.samples = params.samples.min(PREVIEW_BOOLEAN_SAMPLES);
+    // Effect radii are in output pixels, so shrink them with the preview
+    let scale = width as f32 / export_size(state).0 as f32;
+    let post_process = state.film_stock.post_process.scaled(scale);
+    let damage = state.film_stock.damage.scaled(scale);
+    let mut key = bytemuck::bytes_of(&params).to_vec();
+    key.extend(serde_json::to_vec(&post_process).unwrap_or_default());
+    key.extend(serde_json::to_vec(&damage).unwrap_or_default());
+    key.push(state.backend as u8);
+    if !state.preview.plate_changed && state.preview.rendered_params.as_ref() == Some(&key) {
+        return;
+    }
+
+    let (mut image, backend) = match render_gpu(state, &params, &post_process) {
+        Some(image) => (image, RenderBackend::Gpu),
+        None => {
+            let plate = state.preview.plate.clone();
+            match render_cpu(&params, &post_process, width, height, plate, OutputFormat::Rgba8) {
+                Ok(renderer) => (renderer.output_image(), RenderBackend::Cpu),
+                Err(e) => {
+                    state.status_message = Some(e.to_string());
+                    return;
+                }
+            }
+        }
+    };
+    if damage.is_enabled() {
+        let damaged = apply_damage(&damage, state.seed(), &DynamicImage::ImageRgba8(image).into_rgba32f());
+        image = DynamicImage::ImageRgba32F(damaged).into_rgba8();
+    }
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(
+        [width as usize, height as usize],
+        image.as_raw(),
     );
+    state.preview.texture = Some(ctx.load_texture("grain_preview", color_image, egui::TextureOptions::NEAREST));
+    state.preview.rendered_params = Some(key);
+    state.preview.plate_changed = false;
+    state.preview.backend = Some(backend);
+}
+
+/// Preview on the GPU when it is selected, or always while hot reloading shaders, which the
+/// CPU renderer does not mirror; `None` renders on the CPU instead
+fn render_gpu(state: &mut AppState, params: &GrainParams, post_process: &PostProcessChain) -> Option<RgbaImage> {
+    if state.backend != RenderBackend::Gpu && state.shader_reload.is_none() {
+        return None;
+    }
+    let Some(preview) = state.gpu_preview.as_mut() else {
+        state.status_message = Some("No GPU available, previewing on the CPU".to_string());
+        return None;
+    };
+    match preview.render(params, post_process, state.preview.plate.as_ref()) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            state.status_message = Some(e.to_string());
+            None
+        }
+    }
+}
+
+/// Full export resolution: the plate size, or a square standalone texture
+fn export_size(state: &AppState) -> (u32, u32) {
+    state.plate.as_ref()
+        .map_or((STANDALONE_EXPORT_SIZE, STANDALONE_EXPORT_SIZE), |p| p.dimensions())
+}
+
+/// Render the whole output and write it with the stock's damage, as batch and CLI stills are
+fn export_full(state: &AppState, path: &Path) -> Result<(), GrainError> {
+    let (width, height) = export_size(state);
+    let params = preview_params(state, width, height);
+    let mut renderer = export_renderer(state, width, height)?;
+    export_still(&mut renderer, &params, state.plate.as_ref(), &state.film_stock.damage, path)
+}
+
+/// A still shows the damage of the first frame of a sequence
+fn apply_damage(damage: &DamageParameters, seed: u64, image: &Rgba32FImage) -> Rgba32FImage {
+    if !damage.is_enabled() {
+        return image.clone();
+    }
+    DamageGenerator::new(damage, seed, image.width(), image.height()).frame(0).composite(image)
+}
+
+/// Export without ever holding the full render in memory
+fn export_tiled(state: &AppState, path: &Path) -> Result<(), GrainError> {
+    if state.film_stock.damage.is_enabled() {
+        // Gate weave moves the whole frame, which tiles cannot do on their own
+        return Err(GrainError::InvalidParameter {
+            name: "damage".to_string(),
+            reason: "Film damage needs the full frame; turn off tiled export".to_string(),
+        });
+    }
+    let (width, height) = export_size(state);
+    let params = preview_params(state, width, height);
+    export_renderer(state, width, height)?.render_to_file(&params, state.plate.as_ref(), path)
+}
+
+/// Tiles for a full-resolution export on the selected backend; the CPU renders them when
+/// there is no GPU device
+fn export_renderer(state: &AppState, width: u32, height: u32) -> Result<TiledRenderer, GrainError> {
+    let post_process = state.film_stock.post_process.clone();
+    let overlap = DEFAULT_TILE_OVERLAP.max(post_process.reach());
+    let plan = TilePlan::new(width, height, DEFAULT_TILE_SIZE, overlap)?;
+    let renderer = match &state.gpu_preview {
+        Some(preview) if state.backend == RenderBackend::Gpu => TiledRenderer::gpu(preview.context(), plan, state.output_format)?,
+        _ => TiledRenderer::cpu(plan, state.output_format),
+    };
+    renderer.with_post_process(post_process)
 }
 
+fn render_cpu(
+    params: &GrainParams,
+    post_process: &PostProcessChain,
+    width: u32,
+    height: u32,
+    plate: Option<Rgba32FImage>,
+    format: OutputFormat,
+) -> Result<CpuGrainRenderer, GrainError> {
+    let mut renderer = CpuGrainRenderer::with_format(width, height, format)?;
+    if let Some(plate) = plate {
+        renderer.set_input_image(plate)?;
+    }
+    renderer.render(params);
+    renderer.post_process(post_process);
+    Ok(renderer)
+}
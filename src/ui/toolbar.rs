@@ -1,22 +1,37 @@
 use egui::Ui;
 use crate::app::state::AppState;
+use crate::engine::backend::RenderBackend;
 
-pub fn show(ui: &mut Ui, _state: &mut AppState) {
+// Buttons without a feature behind them yet, shown disabled until they are wired up
+const FILE_PLACEHOLDERS: [&str; 3] = ["New", "Open", "Save"];
+const TOOL_PLACEHOLDERS: [&str; 2] = ["⚙", "Export ▼"];
+
+pub fn show(ui: &mut Ui, state: &mut AppState) {
     ui.horizontal(|ui| {
         ui.label("🎬 GrainForge");
         ui.separator();
-        if ui.button("New").clicked() {}
-        if ui.button("Open").clicked() {}
-        if ui.button("Save").clicked() {}
+        for label in FILE_PLACEHOLDERS {
+            ui.add_enabled(false, egui::Button::new(label));
+        }
         
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-            if ui.button("⚙").clicked() {}
-            if ui.button("Export ▼").clicked() {}
+            for label in TOOL_PLACEHOLDERS {
+                ui.add_enabled(false, egui::Button::new(label));
+            }
+            if ui.button("Batch…").clicked() {
+                state.batch.open = !state.batch.open;
+            }
+            ui.separator();
+
+            // Preview backend
+            for backend in RenderBackend::ALL.into_iter().rev() {
+                ui.selectable_value(&mut state.backend, backend, backend.name());
+            }
             ui.separator();
             
             // Mode Switcher
-            ui.selectable_value(&mut _state.active_mode, crate::app::state::EditMode::Simple, "Simple");
-            ui.selectable_value(&mut _state.active_mode, crate::app::state::EditMode::Advanced, "Advanced");
+            ui.selectable_value(&mut state.active_mode, crate::app::state::EditMode::Simple, "Simple");
+            ui.selectable_value(&mut state.active_mode, crate::app::state::EditMode::Advanced, "Advanced");
         });
     });
 }
@@ -63,9 +63,16 @@ impl Default for BoundedFloat {
 }
 
 pub fn validate_export_path(path: &Path) -> Result<PathBuf, ExportError> {
-    let canonical = path.canonicalize()
-        .map_err(|_| ExportError::InvalidPath)?;
-    
+    // The output file usually does not exist yet, so canonicalize its parent directory
+    let file_name = path.file_name().ok_or(ExportError::InvalidPath)?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let canonical = parent.canonicalize()
+        .map_err(|_| ExportError::InvalidPath)?
+        .join(file_name);
+
     // For MVP, allow any path that canonicalizes (exists)
     // Real implementation would check against an allowlist of dirs
     
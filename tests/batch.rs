@@ -0,0 +1,93 @@
+//! Batch jobs: naming, parallel processing, per-file errors and skipping finished files.
+
+mod common;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use common::{temp_dir, write_plate, PLATE_SIZE};
+use grainforge::core::film_stock::FilmStock;
+use grainforge::export::batch::BatchJob;
+
+#[test]
+fn output_names_follow_the_pattern() {
+    let job = BatchJob { naming: "{stock}_{name}.tif".to_string(), ..BatchJob::new("*.png", FilmStock::default(), "out") };
+    let stock = FilmStock::default().meta.name;
+    assert_eq!(job.output_path(Path::new("in/shot_010.png")).unwrap(), Path::new("out").join(format!("{stock}_shot_010.tif")));
+
+    let default = BatchJob::new("*.png", FilmStock::default(), "out");
+    assert_eq!(default.output_path(Path::new("a.tiff")).unwrap(), Path::new("out/a_grain.tiff"));
+
+    for naming in ["{frame}.png", "{name.png", "../{name}.png"] {
+        let job = BatchJob { naming: naming.to_string(), ..default.clone() };
+        assert!(job.output_path(Path::new("a.png")).is_err(), "{naming} must be rejected");
+    }
+}
+
+#[test]
+fn batches_grain_every_file_and_report_failures() {
+    let dir = temp_dir("batch-run");
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).unwrap();
+    for (i, name) in ["a.png", "b.png", "c.tif", "d.png"].iter().enumerate() {
+        write_plate(&input.join(name), 60 * i as u8);
+    }
+    // Matches the pattern but is not an image
+    std::fs::write(input.join("broken.png"), b"not a png").unwrap();
+
+    let output = dir.join("out");
+    let job = BatchJob {
+        threads: 3,
+        ..BatchJob::new(format!("{}/*.[pt][ni][gf]", input.display()), FilmStock::default(), &output)
+    };
+    let reported = AtomicUsize::new(0);
+    let report = job.run(&AtomicBool::new(false), |_| {
+        reported.fetch_add(1, Ordering::Relaxed);
+    })
+    .unwrap();
+
+    assert_eq!(reported.into_inner(), 5);
+    assert_eq!((report.written(), report.skipped()), (4, 0));
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].0.ends_with("broken.png"));
+    assert!(report.files.windows(2).all(|w| w[0].input < w[1].input), "reports keep input order");
+
+    let grained = image::open(output.join("a_grain.png")).unwrap().into_rgba8();
+    assert_eq!(grained.dimensions(), PLATE_SIZE);
+    assert!(output.join("c_grain.tif").exists());
+    assert!(std::fs::read_dir(&output).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().starts_with('.')),
+        "no partial files are left behind");
+
+    // A second run skips what is done, unless forced
+    std::fs::remove_file(output.join("b_grain.png")).unwrap();
+    let report = job.run(&AtomicBool::new(false), |_| {}).unwrap();
+    assert_eq!((report.written(), report.skipped()), (1, 3));
+    let forced = BatchJob { force: true, ..job.clone() };
+    assert_eq!(forced.run(&AtomicBool::new(false), |_| {}).unwrap().written(), 4);
+
+    // Cancelled before it starts, nothing is processed
+    let report = forced.run(&AtomicBool::new(true), |_| {}).unwrap();
+    assert!(report.cancelled && report.files.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn jobs_that_would_lose_data_fail_up_front() {
+    let dir = temp_dir("batch-invalid");
+    write_plate(&dir.join("a.png"), 100);
+    write_plate(&dir.join("a.tif"), 100);
+    let pattern = format!("{}/a.*", dir.display());
+
+    let collide = BatchJob { naming: "{name}.png".to_string(), ..BatchJob::new(&pattern, FilmStock::default(), dir.join("out")) };
+    assert!(collide.run(&AtomicBool::new(false), |_| {}).is_err(), "a.png and a.tif map to one output");
+
+    let overwrite = BatchJob { naming: "{name}.{ext}".to_string(), ..BatchJob::new(&pattern, FilmStock::default(), &dir) };
+    assert!(overwrite.run(&AtomicBool::new(false), |_| {}).is_err(), "outputs must not replace inputs");
+
+    let nothing = BatchJob::new(format!("{}/*.exr", dir.display()), FilmStock::default(), &dir);
+    assert!(nothing.run(&AtomicBool::new(false), |_| {}).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,41 @@
+//! Boolean-model synthesis: discs cover the plate as the model predicts.
+
+use grainforge::core::film_stock::{FilmStock, GrainSynthesis};
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+use image::Rgba32FImage;
+
+const SIZE: u32 = 64;
+
+/// Mean of the red channel rendered over a flat plate of `grey`
+fn mean_coverage(stock: &FilmStock, grey: u8) -> f32 {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    let grey = grey as f32 / 255.0;
+    renderer.set_input_image(Rgba32FImage::from_pixel(SIZE, SIZE, image::Rgba([grey, grey, grey, 1.0]))).unwrap();
+    renderer.render(&GrainParams::from_film_stock(stock, SIZE, SIZE).with_seed(17).with_samples(16));
+    let image = renderer.output_image_f32();
+    image.pixels().map(|p| p.0[0]).sum::<f32>() / (SIZE * SIZE) as f32
+}
+
+#[test]
+fn coverage_matches_the_poisson_expectation() {
+    // At full strength each pixel is the filtered disc coverage. Discs of intensity lambda and
+    // mean squared radius r^2 leave a point uncovered with probability e^(-lambda pi r^2),
+    // and the renderer picks lambda so that the expected coverage 1 - e^(-lambda pi r^2)
+    // is the plate value.
+    for variation in [0.0, 0.8] {
+        let mut stock = FilmStock::default();
+        stock.color.is_color = false;
+        stock.grain.synthesis = GrainSynthesis::Boolean;
+        stock.grain.intensity.set(1.0);
+        stock.grain.size.set(3.0);
+        stock.grain.size_variation.set(variation);
+
+        for grey in [32u8, 96, 160, 224] {
+            let expected = grey as f32 / 255.0;
+            let coverage = mean_coverage(&stock, grey);
+            assert!((coverage - expected).abs() < 0.04, "variation {variation}, plate {expected}: coverage {coverage}");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The headless `grainforge-cli` binary, run on the CPU backend.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::{temp_dir, write_plate, PLATE_SIZE};
+use grainforge::core::film_stock::FilmStock;
+
+fn cli(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_grainforge-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn arg(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn single_renders_a_stock_at_the_requested_size_and_seed() {
+    let dir = temp_dir("cli-single");
+    let stock_path = dir.join("stock.json");
+    std::fs::write(&stock_path, serde_json::to_string(&FilmStock::default()).unwrap()).unwrap();
+
+    let render = |seed: &str, name: &str| {
+        let output = dir.join(name);
+        let result = cli(&[
+            "single", "--stock", arg(&stock_path), "--backend", "cpu", "--width", "40", "--height", "24",
+            "--seed", seed, "--tile-size", "16", "-o", arg(&output),
+        ]);
+        assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+        image::open(&output).unwrap().into_rgba8()
+    };
+
+    let a = render("7", "a.png");
+    assert_eq!(a.dimensions(), (40, 24));
+    assert_eq!(a, render("7", "b.png"), "same seed, same grain");
+    assert_ne!(a, render("8", "c.png"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sequence_and_apply_write_their_outputs() {
+    let dir = temp_dir("cli-sequence");
+    let pattern = dir.join("grain_###.png");
+    let result = cli(&[
+        "sequence", "--backend", "cpu", "--width", "16", "--height", "16", "--fps", "3", "--duration", "1",
+        "--start-frame", "10", "-o", arg(&pattern),
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!((10..13).all(|frame| dir.join(format!("grain_{frame:03}.png")).exists()));
+
+    let plate_path = dir.join("plate.png");
+    write_plate(&plate_path, 120);
+    let output = dir.join("grained.tif");
+    let result = cli(&[
+        "apply", "--backend", "cpu", "--format", "rgba16f", "--blend", "soft-light",
+        "-i", arg(&plate_path), "-o", arg(&output),
+    ]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let grained = image::open(&output).unwrap();
+    assert_eq!((grained.width(), grained.height()), PLATE_SIZE);
+    assert!(matches!(grained, image::DynamicImage::ImageRgba16(_)), "float renders are written at 16 bits");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn errors_are_reported_with_a_failing_status() {
+    let dir = temp_dir("cli-errors");
+    let stock_path = dir.join("broken.json");
+    std::fs::write(&stock_path, "{ not json").unwrap();
+
+    let result = cli(&["single", "--backend", "cpu", "--stock", arg(&stock_path), "-o", arg(&dir.join("out.png"))]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("broken.json"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
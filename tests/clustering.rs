@@ -0,0 +1,52 @@
+//! Clustering: every mode clumps grain into regions of varying density, and more
+//! `organic` clumps harder.
+
+use grainforge::core::film_stock::{ClusteringType, FilmStock};
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+
+const SIZE: u32 = 128;
+const BLOCK: usize = 8;
+
+fn clustered(clustering: ClusteringType, organic: f32) -> FilmStock {
+    let mut stock = FilmStock::default();
+    stock.color.is_color = false;
+    stock.texture.clustering = clustering;
+    stock.texture.cluster_size.set(16.0);
+    stock.texture.organic.set(organic);
+    stock
+}
+
+/// Variance of the mean grain over 8x8 blocks. Unclustered grain averages out within a
+/// block; clusters several blocks wide keep the block means apart.
+fn block_variance(stock: &FilmStock) -> f32 {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    renderer.render(&GrainParams::from_film_stock(stock, SIZE, SIZE).with_seed(12));
+    let image = renderer.output_image_f32();
+
+    let blocks = SIZE as usize / BLOCK;
+    let mut means = vec![0.0f32; blocks * blocks];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        means[(y as usize / BLOCK) * blocks + x as usize / BLOCK] += pixel.0[0] / (BLOCK * BLOCK) as f32;
+    }
+    let mean = means.iter().sum::<f32>() / means.len() as f32;
+    means.iter().map(|m| (m - mean) * (m - mean)).sum::<f32>() / means.len() as f32
+}
+
+#[test]
+fn clusters_raise_large_scale_variation() {
+    let uniform = block_variance(&clustered(ClusteringType::None, 1.0));
+    for clustering in [ClusteringType::Poisson, ClusteringType::Fractal, ClusteringType::Voronoi, ClusteringType::Hybrid] {
+        let clumped = block_variance(&clustered(clustering, 1.0));
+        assert!(clumped > 2.0 * uniform, "{clustering:?}: {clumped} vs {uniform} without clusters");
+    }
+}
+
+#[test]
+fn organic_strengthens_the_clusters() {
+    for clustering in [ClusteringType::Poisson, ClusteringType::Voronoi] {
+        let (gentle, strong) = (block_variance(&clustered(clustering, 1.0)), block_variance(&clustered(clustering, 2.0)));
+        assert!(strong > gentle, "{clustering:?}: {strong} vs {gentle}");
+    }
+}
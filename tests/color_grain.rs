@@ -0,0 +1,98 @@
+//! Colour grain: monochrome stocks stay neutral, dye layers correlate as asked and dye
+//! softness turns crystals into smooth clouds.
+
+use grainforge::core::film_stock::FilmStock;
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+
+const SIZE: u32 = 96;
+
+/// Red, green and blue planes of a standalone render
+fn channels(stock: &FilmStock) -> [Vec<f32>; 3] {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    renderer.render(&GrainParams::from_film_stock(stock, SIZE, SIZE).with_seed(21));
+    let image = renderer.output_image_f32();
+    [0, 1, 2].map(|c| image.pixels().map(|p| p.0[c]).collect())
+}
+
+/// Pearson correlation
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+    let (ma, mb) = (mean(a), mean(b));
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        ab += (x - ma) * (y - mb);
+        aa += (x - ma) * (x - ma);
+        bb += (y - mb) * (y - mb);
+    }
+    ab / (aa * bb).sqrt()
+}
+
+/// Correlation of each pixel with its right-hand neighbour
+fn smoothness(plane: &[f32]) -> f32 {
+    let size = SIZE as usize;
+    let (left, right): (Vec<f32>, Vec<f32>) = plane
+        .chunks_exact(size)
+        .flat_map(|row| row.windows(2).map(|pair| (pair[0], pair[1])))
+        .unzip();
+    correlation(&left, &right)
+}
+
+fn colour(correlation: f32) -> FilmStock {
+    let mut stock = FilmStock::default();
+    stock.color.is_color = true;
+    stock.color.correlation.set(correlation);
+    stock
+}
+
+#[test]
+fn monochrome_stocks_are_neutral() {
+    let mut stock = colour(0.0);
+    stock.color.is_color = false;
+    stock.color.channel_intensity[0].set(2.0);
+    let [r, g, b] = channels(&stock);
+    assert!(r == g && g == b, "one silver layer, whatever the channel settings");
+}
+
+#[test]
+fn dye_layers_follow_the_correlation() {
+    let [r, g, _] = channels(&colour(1.0));
+    assert_eq!(r, g, "fully correlated layers are one layer");
+
+    let independent = channels(&colour(0.0));
+    let r_g = correlation(&independent[0], &independent[1]);
+    assert!(r_g.abs() < 0.2, "independent layers correlate by {r_g}");
+
+    let opposed = channels(&colour(-0.5));
+    let r_g = correlation(&opposed[0], &opposed[1]);
+    assert!(r_g < -0.2, "anti-correlated layers correlate by {r_g}");
+
+    let partial = channels(&colour(0.5));
+    let r_g = correlation(&partial[0], &partial[1]);
+    assert!((0.2..0.9).contains(&r_g), "half-correlated layers correlate by {r_g}");
+}
+
+#[test]
+fn dye_softness_smooths_the_layers() {
+    // Grains a few pixels across, so neighbouring pixels can see the difference
+    let mut crisp = colour(0.0);
+    crisp.grain.size.set(3.0);
+    crisp.color.dye_softness.set(0.0);
+    let mut soft = crisp.clone();
+    soft.color.dye_softness.set(1.0);
+
+    let (crisp, soft) = (channels(&crisp), channels(&soft));
+    for c in 0..3 {
+        let (soft, crisp) = (smoothness(&soft[c]), smoothness(&crisp[c]));
+        assert!(soft > crisp + 0.1, "channel {c}: {soft} vs {crisp}");
+    }
+
+    // Bigger dye clouds are smoother still
+    let mut large = colour(0.0);
+    large.grain.size.set(3.0);
+    large.color.dye_softness.set(0.5);
+    large.color.channel_size[2].set(2.0);
+    let [_, green, blue] = channels(&large);
+    assert!(smoothness(&blue) > smoothness(&green));
+}
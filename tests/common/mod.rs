@@ -0,0 +1,84 @@
+//! Fixtures shared between test crates: node graphs for the graph, evaluator, shader and
+//! node library tests, and scratch directories and plates for the batch, watch and CLI tests.
+
+// Each test crate compiles its own copy and uses only some of these
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use grainforge::core::parameter::ParameterValue;
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::graph_renderer::GraphRenderer;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::nodes::evaluator::{Evaluator, Field};
+use grainforge::nodes::node_graph::{NodeGraph, NodeId, SocketRef};
+use grainforge::nodes::node_types::NodeRegistry;
+use grainforge::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+use image::RgbaImage;
+
+/// Size of the plates written by [`write_plate`]
+pub const PLATE_SIZE: (u32, u32) = (24, 16);
+
+pub fn connect(registry: &NodeRegistry, graph: &mut NodeGraph, from: (NodeId, &str), to: (NodeId, &str)) {
+    graph.connect(registry, SocketRef::new(from.0, from.1), SocketRef::new(to.0, to.1)).unwrap();
+}
+
+pub fn set(graph: &mut NodeGraph, node: NodeId, id: &str, value: ParameterValue) {
+    graph.node_mut(node).unwrap().set_parameter(id, value).unwrap();
+}
+
+/// Grain output fed by `output` of `node`; returns the output node
+pub fn finish(registry: &NodeRegistry, graph: &mut NodeGraph, node: NodeId, output: &str) -> NodeId {
+    let grain = graph.add_node(registry, GRAIN_OUTPUT).unwrap();
+    connect(registry, graph, (node, output), (grain, "grain"));
+    grain
+}
+
+/// A single node of `kind` feeding the output from its socket `output`
+pub fn single(registry: &NodeRegistry, kind: &str, output: &str) -> (NodeGraph, NodeId) {
+    let mut graph = NodeGraph::new();
+    let node = graph.add_node(registry, kind).unwrap();
+    finish(registry, &mut graph, node, output);
+    (graph, node)
+}
+
+/// The graph's output, evaluated on the CPU
+pub fn evaluate(registry: &NodeRegistry, graph: &NodeGraph, width: u32, height: u32) -> Field {
+    let output = Evaluator::new(width, height).evaluate(graph, registry).unwrap().output.unwrap();
+    (*output).clone()
+}
+
+pub fn max_difference(a: &Field, b: &Field) -> f32 {
+    a.pixels().iter().flatten().zip(b.pixels().iter().flatten()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+/// A headless device and a float graph renderer, or None when there is no adapter
+pub fn gpu_renderer(width: u32, height: u32) -> Option<(GpuContext, GraphRenderer)> {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return None;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let renderer = GraphRenderer::new(&context.device, width, height, OutputFormat::Rgba32Float);
+    Some((context, renderer))
+}
+
+/// The graph's output, rendered on the GPU
+pub fn render(context: &GpuContext, renderer: &mut GraphRenderer, graph: &NodeGraph, registry: &NodeRegistry) -> Field {
+    renderer.render(&context.device, &context.queue, graph, registry).unwrap();
+    Field::from_image(&renderer.read_image_f32(&context.device, &context.queue).unwrap())
+}
+
+/// An empty scratch directory unique to this test process
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("grainforge-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Save a flat grey plate of [`PLATE_SIZE`]
+pub fn write_plate(path: &Path, grey: u8) {
+    RgbaImage::from_pixel(PLATE_SIZE.0, PLATE_SIZE.1, image::Rgba([grey, grey, grey, 255])).save(path).unwrap();
+}
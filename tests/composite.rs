@@ -0,0 +1,61 @@
+//! Compositing onto a photograph: blend modes and the response curve.
+
+use grainforge::core::film_stock::{FilmStock, ResponseMode};
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::{BlendMode, GrainParams};
+use grainforge::engine::output_format::OutputFormat;
+use image::Rgba32FImage;
+
+const SIZE: u32 = 64;
+
+/// Red channel of `stock` composited onto a flat plate of `grey`
+fn composite(stock: &FilmStock, grey: u8, mode: BlendMode) -> Vec<f32> {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    let grey = grey as f32 / 255.0;
+    renderer.set_input_image(Rgba32FImage::from_pixel(SIZE, SIZE, image::Rgba([grey, grey, grey, 1.0]))).unwrap();
+    renderer.render(&GrainParams::from_film_stock(stock, SIZE, SIZE).with_seed(9).with_blend_mode(mode));
+    renderer.output_image_f32().pixels().map(|p| p.0[0]).collect()
+}
+
+fn std_dev(values: &[f32]) -> f32 {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    (values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32).sqrt()
+}
+
+#[test]
+fn no_grain_leaves_the_plate_alone() {
+    let mut stock = FilmStock::default();
+    stock.grain.intensity.set(0.0);
+    for mode in BlendMode::ALL {
+        for grey in [20, 128, 230] {
+            let plate = grey as f32 / 255.0;
+            let max_error = composite(&stock, grey, mode).iter().map(|v| (v - plate).abs()).fold(0.0, f32::max);
+            assert!(max_error < 1e-4, "{} on {grey} is off by {max_error}", mode.name());
+        }
+    }
+}
+
+#[test]
+fn grain_strength_follows_the_response_curve() {
+    let strength = |shadows: f32, mode: ResponseMode, grey: u8| {
+        // Only the shadow band responds, so any grain left comes from it
+        let mut stock = FilmStock::default();
+        stock.response.shadows.set(shadows);
+        stock.response.midtones.set(0.0);
+        stock.response.mode = mode;
+        std_dev(&composite(&stock, grey, BlendMode::Additive))
+    };
+
+    // More shadow response, more grain in the shadows, at every step
+    let dark: Vec<f32> = [0.0, 0.5, 1.0, 1.5, 2.0].map(|s| strength(s, ResponseMode::Negative, 40)).to_vec();
+    assert!(dark[0] < 1e-4, "no shadow response leaves the shadows clean: {dark:?}");
+    assert!(dark.windows(2).all(|w| w[0] < w[1]), "{dark:?}");
+
+    // The shadow band never reaches the highlights
+    assert_eq!(strength(0.0, ResponseMode::Negative, 220), strength(2.0, ResponseMode::Negative, 220));
+    assert!(strength(2.0, ResponseMode::Negative, 40) > strength(2.0, ResponseMode::Negative, 220));
+
+    // Positive-working stocks build density inversely, so the bands swap ends
+    assert!(strength(2.0, ResponseMode::Print, 220) > strength(0.0, ResponseMode::Print, 220));
+    assert_eq!(strength(0.0, ResponseMode::Print, 40), strength(2.0, ResponseMode::Print, 40));
+}
@@ -0,0 +1,168 @@
+//! Film damage: determinism, animation, compositing and sequence export.
+
+use std::sync::atomic::AtomicBool;
+
+use grainforge::core::film_stock::{DamageParameters, DamagePolarity, FilmStock};
+use grainforge::engine::damage::DamageGenerator;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer};
+use grainforge::export::sequence_export::{damage_path, export_sequence, frame_path, SequenceSettings};
+use image::Rgba32FImage;
+
+const SIZE: u32 = 200;
+
+fn damage(dust: f32, hairs: f32, scratches: f32) -> DamageParameters {
+    let mut damage = DamageParameters::default();
+    damage.dust.set(dust);
+    damage.hairs.set(hairs);
+    damage.scratches.set(scratches);
+    damage
+}
+
+fn grey() -> Rgba32FImage {
+    Rgba32FImage::from_pixel(SIZE, SIZE, image::Rgba([0.5, 0.5, 0.5, 1.0]))
+}
+
+#[test]
+fn damage_is_seeded_and_changes_every_frame() {
+    let generator = DamageGenerator::new(&damage(1.0, 1.0, 0.0), 7, SIZE, SIZE);
+    let frame = generator.frame(3);
+    assert!(frame.coverage().iter().any(|&c| c > 0.5), "full dust leaves marks");
+    assert_eq!(frame.coverage(), generator.frame(3).coverage());
+    assert_ne!(frame.coverage(), generator.frame(4).coverage(), "dust moves between frames");
+    assert_ne!(frame.coverage(), DamageGenerator::new(&damage(1.0, 1.0, 0.0), 8, SIZE, SIZE).frame(3).coverage());
+
+    let clean = DamageGenerator::new(&DamageParameters::default(), 7, SIZE, SIZE).frame(0);
+    assert!(clean.is_clean());
+    assert_eq!(clean.composite(&grey()), grey());
+}
+
+#[test]
+fn scratches_persist_between_frames() {
+    let generator = DamageGenerator::new(&damage(0.0, 0.0, 1.0), 11, SIZE, SIZE);
+    // Columns a scratch passes through, by total coverage
+    let columns = |index| {
+        let frame = generator.frame(index);
+        let mut totals = vec![0.0f32; SIZE as usize];
+        for (i, c) in frame.coverage().iter().enumerate() {
+            totals[i % SIZE as usize] += c;
+        }
+        totals.iter().map(|&t| t > SIZE as f32 * 0.1).collect::<Vec<_>>()
+    };
+
+    let (first, next) = (columns(10), columns(11));
+    assert!(first.contains(&true), "some scratch is visible");
+    let shared = first.iter().zip(&next).filter(|(a, b)| **a && **b).count();
+    assert!(shared * 2 >= first.iter().filter(|&&a| a).count(), "scratches stay put from frame to frame");
+}
+
+#[test]
+fn scratches_and_weave_draw_on_separate_streams() {
+    let still = damage(0.0, 0.0, 1.0);
+    let mut weaving = still.clone();
+    weaving.gate_weave.set(4.0);
+    let (still, weaving) = (DamageGenerator::new(&still, 5, SIZE, SIZE), DamageGenerator::new(&weaving, 5, SIZE, SIZE));
+    for index in [0, 9, 30] {
+        assert_eq!(still.frame(index).coverage(), weaving.frame(index).coverage(), "frame {index}");
+    }
+
+    let mut unscratched = damage(0.0, 0.0, 0.0);
+    unscratched.gate_weave.set(4.0);
+    let unscratched = DamageGenerator::new(&unscratched, 5, SIZE, SIZE);
+    assert_eq!(unscratched.gate_weave(12), weaving.gate_weave(12));
+}
+
+#[test]
+fn weave_and_flicker_stay_within_their_amplitudes() {
+    let mut parameters = DamageParameters::default();
+    parameters.gate_weave.set(2.0);
+    parameters.flicker.set(0.5);
+    let generator = DamageGenerator::new(&parameters, 3, SIZE, SIZE);
+
+    let mut previous = generator.gate_weave(0);
+    let mut moved = false;
+    for index in 0..48 {
+        let weave = generator.gate_weave(index);
+        assert!(weave.offset[0].abs() <= 2.0 && weave.offset[1].abs() <= 1.0, "{weave:?}");
+        assert!((weave.offset[0] - previous.offset[0]).abs() < 1.5, "weave wanders rather than jumps");
+        moved |= weave.offset != previous.offset;
+        previous = weave;
+
+        let exposure = generator.exposure(index);
+        assert!((2f32.powf(-0.5)..=2f32.powf(0.5)).contains(&exposure), "{exposure}");
+    }
+    assert!(moved);
+
+    // Weave moves the picture but keeps a flat field flat; flicker brightens or darkens it
+    let frame = generator.frame(5);
+    let moved = frame.composite(&grey());
+    let expected = grainforge::utils::color::linear_to_srgb(grainforge::utils::color::srgb_to_linear(0.5) * frame.exposure);
+    assert!(moved.pixels().all(|p| (p.0[0] - expected).abs() < 1e-4));
+}
+
+#[test]
+fn marks_print_white_or_black_by_polarity() {
+    let mut parameters = damage(1.0, 0.0, 0.0);
+    let frame = DamageGenerator::new(&parameters, 5, SIZE, SIZE).frame(0);
+    let (index, &coverage) = frame.coverage().iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+    let (x, y) = (index as u32 % SIZE, index as u32 / SIZE);
+    assert!(coverage > 0.5);
+    assert!((frame.composite(&grey()).get_pixel(x, y).0[0] - (0.5 + 0.5 * coverage)).abs() < 1e-5);
+    assert_eq!(frame.layer().get_pixel(x, y).0, [1.0, 1.0, 1.0, coverage]);
+
+    parameters.polarity = DamagePolarity::Positive;
+    let frame = DamageGenerator::new(&parameters, 5, SIZE, SIZE).frame(0);
+    assert!((frame.composite(&grey()).get_pixel(x, y).0[0] - (0.5 - 0.5 * coverage)).abs() < 1e-5);
+}
+
+#[test]
+fn sequences_composite_or_separate_the_damage() {
+    let dir = std::env::temp_dir().join(format!("grainforge-damage-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let params = GrainParams::from_film_stock(&FilmStock::default(), 48, 32).with_seed(1);
+    let mut renderer = TiledRenderer::cpu(TilePlan::new(48, 32, 32, 8).unwrap(), OutputFormat::Rgba8);
+    let settings = SequenceSettings {
+        fps: 2.0,
+        damage: damage(1.0, 1.0, 1.0),
+        separate_damage: true,
+        ..Default::default()
+    };
+
+    let pattern = dir.join("grain_##.png");
+    export_sequence(&mut renderer, &params, None, &settings, &pattern, &AtomicBool::new(false), |_| {}).unwrap();
+    for frame in 1..=2 {
+        let path = frame_path(&pattern, frame);
+        assert!(path.exists());
+        let layer = image::open(damage_path(&path)).unwrap().into_rgba8();
+        assert_eq!(layer.dimensions(), (48, 32));
+    }
+    assert_eq!(damage_path(&frame_path(&pattern, 1)).file_name().unwrap(), "grain_01_damage.png");
+
+    // Composited frames differ from clean grain; without damage no layer is written
+    let clean = dir.join("clean_##.png");
+    let settings = SequenceSettings { fps: 2.0, ..Default::default() };
+    export_sequence(&mut renderer, &params, None, &settings, &clean, &AtomicBool::new(false), |_| {}).unwrap();
+    assert!(!damage_path(&frame_path(&clean, 1)).exists());
+
+    let damaged = dir.join("damaged_##.png");
+    let settings = SequenceSettings { fps: 2.0, damage: damage(1.0, 1.0, 1.0), ..Default::default() };
+    export_sequence(&mut renderer, &params, None, &settings, &damaged, &AtomicBool::new(false), |_| {}).unwrap();
+    let read = |pattern| image::open(frame_path(pattern, 1)).unwrap().into_rgba8();
+    assert_ne!(read(&clean), read(&damaged));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn damage_is_saved_with_the_stock() {
+    let stock = FilmStock { damage: damage(0.3, 0.1, 0.5), ..Default::default() };
+    let json = serde_json::to_value(&stock).unwrap();
+    assert_eq!(json["damage"]["polarity"], "negative");
+    let loaded: FilmStock = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(loaded.damage, stock.damage);
+
+    let mut legacy = json;
+    legacy.as_object_mut().unwrap().remove("damage");
+    assert!(!serde_json::from_value::<FilmStock>(legacy).unwrap().damage.is_enabled());
+}
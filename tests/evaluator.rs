@@ -0,0 +1,155 @@
+//! Graph evaluation: values, dirty propagation and the output cache.
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::{connect, finish, set, single};
+use grainforge::core::error::GraphError;
+use grainforge::core::parameter::{Parameter, ParameterValue};
+use grainforge::nodes::evaluator::{Evaluator, Field, Value};
+use grainforge::nodes::node_graph::{NodeGraph, NodeId};
+use grainforge::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use grainforge::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+static GRADIENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Built-in types plus a horizontal ramp and a multiplier
+fn registry() -> NodeRegistry {
+    let mut registry = NodeRegistry::builtin();
+    registry.register(
+        NodeType::new("test.gradient", "Gradient", NodeCategory::Noise)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|ctx| {
+                GRADIENTS.fetch_add(1, Ordering::Relaxed);
+                let width = ctx.width as f32;
+                let field = Field::from_fn(ctx.width, ctx.height, |x, _| {
+                    let v = x as f32 / width;
+                    [v, v, v, 1.0]
+                });
+                Ok(vec![Value::Field(Arc::new(field))])
+            }),
+    );
+    registry.register(
+        NodeType::new("test.scale", "Scale", NodeCategory::Math)
+            .with_input("field", SocketType::Field)
+            .with_input("factor", SocketType::Scalar)
+            .with_output("field", SocketType::Field)
+            .with_parameter(Parameter::new_float("factor", "Factor", "", 1.0, 0.0, 4.0))
+            .with_evaluate(|ctx| {
+                let factor = ctx.scalar("factor");
+                let field = ctx.field("field").map(|[r, g, b, a]| [r * factor, g * factor, b * factor, a]);
+                Ok(vec![Value::Field(Arc::new(field))])
+            }),
+    );
+    registry
+}
+
+/// gradient -> scale -> scale -> output
+fn chain(registry: &NodeRegistry) -> (NodeGraph, [NodeId; 4]) {
+    let mut graph = NodeGraph::new();
+    let gradient = graph.add_node(registry, "test.gradient").unwrap();
+    let first = graph.add_node(registry, "test.scale").unwrap();
+    let second = graph.add_node(registry, "test.scale").unwrap();
+    connect(registry, &mut graph, (gradient, "field"), (first, "field"));
+    connect(registry, &mut graph, (first, "field"), (second, "field"));
+    let output = finish(registry, &mut graph, second, "field");
+    (graph, [gradient, first, second, output])
+}
+
+#[test]
+fn only_changed_nodes_and_their_dependents_are_evaluated() {
+    let registry = registry();
+    let (mut graph, [gradient, first, second, output]) = chain(&registry);
+    let mut evaluator = Evaluator::new(8, 4);
+
+    let evaluation = evaluator.evaluate(&graph, &registry).unwrap();
+    assert_eq!(evaluation.evaluated().collect::<Vec<_>>(), vec![gradient, first, second, output]);
+    assert_eq!(evaluation.output.unwrap().get(4, 2), [0.5, 0.5, 0.5, 1.0]);
+
+    // Nothing changed
+    assert!(evaluator.dirty_nodes(&graph, &registry).unwrap().is_empty());
+    let evaluation = evaluator.evaluate(&graph, &registry).unwrap();
+    assert_eq!(evaluation.evaluated().count(), 0);
+    assert_eq!(evaluation.timings.len(), 4);
+    assert!(evaluation.timings.iter().all(|t| t.cached));
+
+    // Editing the middle of the chain leaves the gradient alone
+    set(&mut graph, first, "factor", ParameterValue::Float(2.0));
+    assert_eq!(evaluator.dirty_nodes(&graph, &registry).unwrap(), vec![first, second, output]);
+    let before = GRADIENTS.load(Ordering::Relaxed);
+    let evaluation = evaluator.evaluate(&graph, &registry).unwrap();
+    assert_eq!(evaluation.evaluated().collect::<Vec<_>>(), vec![first, second, output]);
+    assert_eq!(GRADIENTS.load(Ordering::Relaxed), before);
+    assert_eq!(evaluation.output.unwrap().get(4, 2), [1.0, 1.0, 1.0, 1.0]);
+
+    // Rewiring counts as a change too
+    connect(&registry, &mut graph, (first, "field"), (output, "grain"));
+    assert_eq!(evaluator.dirty_nodes(&graph, &registry).unwrap(), vec![output]);
+
+    // A new size invalidates everything
+    evaluator.resize(16, 4);
+    assert_eq!(evaluator.dirty_nodes(&graph, &registry).unwrap().len(), 4);
+    assert_eq!(evaluator.evaluate(&graph, &registry).unwrap().output.unwrap().width(), 16);
+}
+
+#[test]
+fn connected_values_widen_to_the_input_type() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let value = graph.add_node(&registry, "input.value").unwrap();
+    let scale = graph.add_node(&registry, "test.scale").unwrap();
+    set(&mut graph, value, "value", ParameterValue::Float(0.25));
+    set(&mut graph, scale, "factor", ParameterValue::Float(3.0));
+    connect(&registry, &mut graph, (value, "value"), (scale, "field"));
+    finish(&registry, &mut graph, scale, "field");
+
+    let mut evaluator = Evaluator::new(3, 2);
+    let evaluation = evaluator.evaluate(&graph, &registry).unwrap();
+    assert_eq!(evaluator.outputs(value).unwrap(), [Value::Scalar(0.25)]);
+    // The unconnected factor reads its parameter
+    assert_eq!(**evaluation.output.as_ref().unwrap(), Field::uniform(3, 2, [0.75, 0.75, 0.75, 1.0]));
+    assert!(evaluation.total() >= evaluation.timings[0].duration);
+}
+
+#[test]
+fn failures_name_the_node() {
+    let mut registry = registry();
+    registry.register(
+        NodeType::new("test.broken", "Broken", NodeCategory::Noise)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|_| Err("out of film".to_string())),
+    );
+    registry.register(NodeType::new("test.gpu_only", "GPU only", NodeCategory::Noise).with_output("field", SocketType::Field));
+
+    let mut evaluator = Evaluator::new(4, 4);
+    for (kind, reason) in [("test.broken", "out of film"), ("test.gpu_only", "No CPU implementation")] {
+        let (graph, _) = single(&registry, kind, "field");
+
+        let err = evaluator.evaluate(&graph, &registry).unwrap_err();
+        assert!(matches!(&err, GraphError::Evaluation { .. }), "{err}");
+        let message = err.to_string();
+        assert!(message.contains(kind) && message.contains(reason), "{message}");
+    }
+
+    // Graphs are validated before anything runs
+    let mut graph = NodeGraph::new();
+    graph.add_node(&registry, GRAIN_OUTPUT).unwrap();
+    assert!(matches!(evaluator.evaluate(&graph, &registry), Err(GraphError::UnconnectedInput { .. })));
+}
+
+#[test]
+fn removed_nodes_leave_the_cache() {
+    let registry = registry();
+    let (mut graph, [_, first, second, output]) = chain(&registry);
+    let mut evaluator = Evaluator::new(4, 4);
+    evaluator.evaluate(&graph, &registry).unwrap();
+    assert!(evaluator.outputs(second).is_some());
+
+    graph.remove_node(second);
+    connect(&registry, &mut graph, (first, "field"), (output, "grain"));
+    let evaluation = evaluator.evaluate(&graph, &registry).unwrap();
+    assert!(evaluator.outputs(second).is_none());
+    assert_eq!(evaluation.evaluated().collect::<Vec<_>>(), vec![output]);
+}
@@ -0,0 +1,104 @@
+//! Exporting float renders: PNG and TIFF keep 16 bits per channel, EXR stores linear
+//! light, and deep plates keep their precision through a grained export.
+
+use grainforge::core::export::export_image_f32;
+use grainforge::core::film_stock::FilmStock;
+use grainforge::core::import::load_plate;
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer};
+use grainforge::utils::math::unorm16;
+use image::{ImageBuffer, Rgba, Rgba32FImage};
+
+const WIDTH: u32 = 1024;
+
+/// Ramps finer than 8 bits can hold, one per channel
+fn ramp() -> Rgba32FImage {
+    Rgba32FImage::from_fn(WIDTH, 2, |x, y| {
+        let t = x as f32 / (WIDTH - 1) as f32;
+        Rgba([t, 1.0 - t, t * t, if y == 0 { 1.0 } else { 0.5 }])
+    })
+}
+
+#[test]
+fn deep_formats_round_trip_at_16_bits() {
+    let source = ramp();
+    for extension in ["png", "tiff"] {
+        let path = std::env::temp_dir().join(format!("grainforge_export_ramp.{extension}"));
+        export_image_f32(&source, &path).unwrap();
+        let read = image::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(read.color(), image::ColorType::Rgba16, "{extension} is not 16-bit");
+        let read = read.into_rgba16();
+        for (written, read) in source.pixels().zip(read.pixels()) {
+            assert_eq!(read.0, written.0.map(unorm16), "{extension}");
+        }
+
+        let mut levels: Vec<u16> = read.pixels().map(|p| p.0[0]).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        assert!(levels.len() > 256, "{extension} keeps only {} levels", levels.len());
+    }
+}
+
+#[test]
+fn deep_plates_keep_their_precision() {
+    // A ramp in steps of 3/65535, far finer than 8 bits can hold
+    let plate_path = std::env::temp_dir().join("grainforge_export_deep_plate.tiff");
+    let source = ImageBuffer::<Rgba<u16>, _>::from_fn(WIDTH, 2, |x, _| Rgba([20000 + 3 * x as u16, 30000, 40000 - 3 * x as u16, 65535]));
+    source.save(&plate_path).unwrap();
+    let plate = load_plate(&plate_path).unwrap();
+    std::fs::remove_file(&plate_path).ok();
+
+    let mut stock = FilmStock::default();
+    stock.grain.intensity.set(0.0);
+    let params = GrainParams::from_film_stock(&stock, WIDTH, 2);
+    let plan = TilePlan::new(WIDTH, 2, 256, 0).unwrap();
+    let mut renderers = vec![("cpu", TiledRenderer::cpu(plan, OutputFormat::Rgba32Float))];
+    if gpu_adapter_available() {
+        let context = GpuContext::new_headless().unwrap();
+        renderers.push(("gpu", TiledRenderer::gpu(&context, plan, OutputFormat::Rgba32Float).unwrap()));
+    }
+
+    for (backend, mut renderer) in renderers {
+        let path = std::env::temp_dir().join(format!("grainforge_export_deep_{backend}.tiff"));
+        renderer.render_to_file(&params, Some(&plate), &path).unwrap();
+        let read = image::open(&path).unwrap().into_rgba16();
+        std::fs::remove_file(&path).ok();
+
+        // 8-bit plate data would be off by up to 128 levels
+        let error = source.pixels().zip(read.pixels())
+            .flat_map(|(a, b)| a.0.into_iter().zip(b.0).map(|(a, b)| a.abs_diff(b)))
+            .max()
+            .unwrap();
+        assert!(error <= 4, "{backend}: exported plate is off by {error} levels");
+    }
+}
+
+#[cfg(feature = "exr")]
+#[test]
+fn exr_stores_linear_light() {
+    use grainforge::utils::color::srgb_to_linear;
+
+    let source = ramp();
+    let path = std::env::temp_dir().join("grainforge_export_ramp.exr");
+    export_image_f32(&source, &path).unwrap();
+    let read = exr::prelude::read_first_rgba_layer_from_file(
+        &path,
+        |resolution, _| vec![vec![[0.0f32; 4]; resolution.width()]; resolution.height()],
+        |pixels, position, (r, g, b, a): (f32, f32, f32, f32)| pixels[position.y()][position.x()] = [r, g, b, a],
+    )
+    .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let pixels = read.layer_data.channel_data.pixels;
+    for (x, y, written) in source.enumerate_pixels() {
+        let [r, g, b, a] = written.0;
+        // Colour is linearised, alpha is coverage and stays as it was
+        let expected = [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a];
+        assert_eq!(pixels[y as usize][x as usize], expected, "pixel {x},{y}");
+    }
+}
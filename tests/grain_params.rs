@@ -0,0 +1,97 @@
+//! The uniform block: its layout matches `Params` in grain.wgsl, and every stock field
+//! reaches it.
+
+use std::mem::{offset_of, size_of};
+
+use grainforge::core::film_stock::{ClusteringType, CrystalType, FilmStock, GrainSynthesis, ResponseMode};
+use grainforge::engine::compute_pipeline::grain_shader;
+use grainforge::engine::grain_renderer::{
+    GrainParams, CLUSTER_HYBRID, CRYSTAL_CELLULAR, CRYSTAL_CORE_SHELL, CRYSTAL_CUBIC, CRYSTAL_CUSTOM, CRYSTAL_NEEDLE,
+    CRYSTAL_TABULAR, RESPONSE_REVERSAL, SYNTHESIS_PARTICLE,
+};
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::shaders::ShaderLibrary;
+
+/// Byte offset of every `GrainParams` field, by name
+macro_rules! offsets {
+    ($($field:ident),* $(,)?) => {
+        vec![$((stringify!($field), offset_of!(GrainParams, $field) as u32)),*]
+    };
+}
+
+#[test]
+fn layout_matches_the_shader() {
+    assert_eq!(size_of::<GrainParams>(), 160);
+
+    let module = grain_shader(&ShaderLibrary::builtin(), OutputFormat::Rgba8).unwrap().validate().unwrap();
+    let (_, params) = module.types.iter().find(|(_, ty)| ty.name.as_deref() == Some("Params")).unwrap();
+    let naga::TypeInner::Struct { members, span } = &params.inner else {
+        panic!("Params is not a struct");
+    };
+    assert_eq!(*span as usize, size_of::<GrainParams>());
+
+    let shader: Vec<(&str, u32)> = members.iter().map(|m| (m.name.as_deref().unwrap(), m.offset)).collect();
+    let rust = offsets![
+        grain_amount, grain_size, width, height,
+        size_variation, sharpness, crystal_type, crystal_sides,
+        response_shadows, response_midtones, response_highlights, response_mode,
+        is_color, correlation, dye_softness, synthesis,
+        channel_intensity, channel_size,
+        clustering, cluster_size, organic, detail,
+        swirl, blend_mode, use_input, samples,
+        seed, tile_origin,
+        time, tileable, _padding0, _padding1,
+    ];
+    assert_eq!(shader, rust);
+}
+
+#[test]
+fn every_stock_field_reaches_the_shader() {
+    let mut stock = FilmStock::default();
+    stock.grain.intensity.set(1.25);
+    stock.grain.size.set(2.0);
+    stock.grain.size_variation.set(0.75);
+    stock.grain.sharpness.set(0.9);
+    stock.grain.crystal_type = CrystalType::Custom { sides: 7 };
+    stock.grain.synthesis = GrainSynthesis::Particle;
+    stock.grain.seed = 0x0123_4567_89ab_cdef;
+    stock.response.mode = ResponseMode::Reversal;
+    stock.response.shadows.set(0.2);
+    stock.response.midtones.set(0.6);
+    stock.response.highlights.set(0.3);
+    stock.color.is_color = false;
+    stock.color.correlation.set(-0.5);
+    stock.color.dye_softness.set(0.4);
+    stock.color.channel_intensity[1].set(2.0);
+    stock.color.channel_size[2].set(1.5);
+    stock.texture.clustering = ClusteringType::Hybrid;
+    stock.texture.cluster_size.set(3.0);
+    stock.texture.organic.set(1.5);
+    stock.texture.detail.set(4.0);
+    stock.texture.swirl.set(0.55);
+
+    let params = GrainParams::from_film_stock(&stock, 320, 200);
+    assert_eq!((params.grain_amount, params.grain_size, params.width, params.height), (1.25, 2.0, 320.0, 200.0));
+    assert_eq!((params.size_variation, params.sharpness), (0.75, 0.9));
+    assert_eq!((params.crystal_type, params.crystal_sides), (CRYSTAL_CUSTOM, 7));
+    assert_eq!(params.synthesis, SYNTHESIS_PARTICLE);
+    assert_eq!(params.seed_u64(), 0x0123_4567_89ab_cdef);
+    assert_eq!(params.response_mode, RESPONSE_REVERSAL);
+    assert_eq!((params.response_shadows, params.response_midtones, params.response_highlights), (0.2, 0.6, 0.3));
+    assert_eq!((params.is_color, params.correlation, params.dye_softness), (0, -0.5, 0.4));
+    assert_eq!(params.channel_intensity, [1.0, 2.0, 1.0, 0.0]);
+    assert_eq!(params.channel_size, [1.0, 1.0, 1.5, 0.0]);
+    assert_eq!(params.clustering, CLUSTER_HYBRID);
+    assert_eq!((params.cluster_size, params.organic, params.detail, params.swirl), (3.0, 1.5, 4.0, 0.55));
+
+    // Stocks that differ only in the enums map to different shader ids
+    let crystals = [CrystalType::Cubic, CrystalType::Tabular, CrystalType::CoreShell, CrystalType::Cellular, CrystalType::Needle];
+    let ids: Vec<u32> = crystals
+        .into_iter()
+        .map(|crystal_type| {
+            stock.grain.crystal_type = crystal_type;
+            GrainParams::from_film_stock(&stock, 1, 1).crystal_type
+        })
+        .collect();
+    assert_eq!(ids, [CRYSTAL_CUBIC, CRYSTAL_TABULAR, CRYSTAL_CORE_SHELL, CRYSTAL_CELLULAR, CRYSTAL_NEEDLE]);
+}
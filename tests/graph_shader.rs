@@ -0,0 +1,188 @@
+//! Fused WGSL for node graphs: generated code, structure hashing and GPU/CPU agreement.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{connect, finish, gpu_renderer, max_difference, render, set, single};
+use grainforge::core::error::{GrainError, GraphError};
+use grainforge::core::parameter::{Parameter, ParameterValue};
+use grainforge::engine::graph_renderer::GraphRenderer;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::shaders::ShaderLibrary;
+use grainforge::nodes::codegen::{compile, parameter_block, structure_hash};
+use grainforge::nodes::evaluator::{Evaluator, Field, Value};
+use grainforge::nodes::node_graph::{NodeGraph, NodeId, SocketRef};
+use grainforge::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use grainforge::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+/// Built-in types plus a ramp, a multiplier and a 3x3 box blur, each on both backends
+fn registry() -> NodeRegistry {
+    let mut registry = NodeRegistry::builtin();
+    registry.register(
+        NodeType::new("test.gradient", "Gradient", NodeCategory::Noise)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let width = ctx.width as f32;
+                let field = Field::from_fn(ctx.width, ctx.height, |x, y| [x as f32 / width, (y % 2) as f32, 0.25, 1.0]);
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok("out.field = vec4(pixel_uv(px).x, f32(px.coords.y % 2), 0.25, 1.0);".to_string())),
+    );
+    registry.register(
+        NodeType::new("test.scale", "Scale", NodeCategory::Math)
+            .with_input("field", SocketType::Field)
+            .with_input("factor", SocketType::Scalar)
+            .with_output("field", SocketType::Field)
+            .with_parameter(Parameter::new_float("factor", "Factor", "", 1.0, 0.0, 4.0))
+            .with_evaluate(|ctx| {
+                let factor = ctx.scalar("factor");
+                let field = ctx.field("field").map(|[r, g, b, a]| [r * factor, g * factor, b * factor, a]);
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|_| Ok("out.field = vec4(field.rgb * factor, field.a);".to_string())),
+    );
+    registry.register(
+        NodeType::new("test.box_blur", "Box Blur", NodeCategory::Filter)
+            .with_input("field", SocketType::Field)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let input = ctx.field("field");
+                let (w, h) = (ctx.width as i32, ctx.height as i32);
+                let field = Field::from_fn(ctx.width, ctx.height, |x, y| {
+                    let mut sum = [0.0; 4];
+                    for dy in -1..=1 {
+                        for dx in -1..=1 {
+                            let sx = (x as i32 + dx).clamp(0, w - 1) as u32;
+                            let sy = (y as i32 + dy).clamp(0, h - 1) as u32;
+                            for (s, v) in sum.iter_mut().zip(input.get(sx, sy)) {
+                                *s += v / 9.0;
+                            }
+                        }
+                    }
+                    sum
+                });
+                Ok(vec![Value::Field(Arc::new(field))])
+            })
+            .with_wgsl(|ctx| {
+                let sample = ctx.sampler("field")?;
+                Ok(format!(
+                    "var sum = vec4(0.0);\n\
+                     for (var dy = -1; dy <= 1; dy++) {{\n    \
+                         for (var dx = -1; dx <= 1; dx++) {{\n        \
+                             sum += {sample}(px, vec2(dx, dy)) / 9.0;\n    \
+                         }}\n\
+                     }}\n\
+                     out.field = sum;"
+                ))
+            }),
+    );
+    registry.register(
+        NodeType::new("test.cpu_only", "CPU only", NodeCategory::Noise)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|ctx| Ok(vec![Value::Field(Arc::new(Field::uniform(ctx.width, ctx.height, [0.0; 4])))])),
+    );
+    registry
+}
+
+/// gradient -> scale -> blur -> output, with a constant feeding the factor
+fn graph(registry: &NodeRegistry) -> (NodeGraph, [NodeId; 4]) {
+    let mut graph = NodeGraph::new();
+    let gradient = graph.add_node(registry, "test.gradient").unwrap();
+    let scale = graph.add_node(registry, "test.scale").unwrap();
+    let blur = graph.add_node(registry, "test.box_blur").unwrap();
+    finish(registry, &mut graph, blur, "field");
+    let factor = graph.add_node(registry, "input.value").unwrap();
+    connect(registry, &mut graph, (gradient, "field"), (scale, "field"));
+    connect(registry, &mut graph, (factor, "value"), (scale, "factor"));
+    connect(registry, &mut graph, (scale, "field"), (blur, "field"));
+    (graph, [gradient, scale, blur, factor])
+}
+
+#[test]
+fn graphs_compile_to_one_validated_shader() {
+    let registry = registry();
+    let (graph, _) = graph(&registry);
+    let compiled = compile(&graph, &registry, OutputFormat::Rgba32Float, &ShaderLibrary::builtin()).unwrap();
+    let code = &compiled.shader.code;
+
+    assert_eq!(code.matches("@compute").count(), 1);
+    assert!(code.contains("texture_storage_2d<rgba32float, write>"));
+    for function in ["fn node_0(", "fn node_1(", "fn node_2(", "fn node_3(", "fn pull_", "fn sample_"] {
+        assert!(code.contains(function), "missing {function}");
+    }
+    // The noise library is available to every node
+    assert!(code.contains("fn simplex_noise("));
+}
+
+#[test]
+fn structure_hash_ignores_parameter_values() {
+    let registry = registry();
+    let (mut graph, [gradient, scale, blur, factor]) = graph(&registry);
+    let hash = |graph: &NodeGraph| structure_hash(graph, &registry, OutputFormat::Rgba8).unwrap();
+    let original = hash(&graph);
+
+    set(&mut graph, factor, "value", ParameterValue::Float(3.0));
+    assert_eq!(hash(&graph), original);
+    assert!(parameter_block(&graph, &registry).unwrap().iter().any(|p| p[0] == 3.0));
+
+    assert_ne!(structure_hash(&graph, &registry, OutputFormat::Rgba16Float).unwrap(), original);
+
+    // Unrelated nodes do not count; rewiring does
+    graph.add_node(&registry, "input.color").unwrap();
+    assert_eq!(hash(&graph), original);
+    let output = graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT).unwrap().id;
+    connect(&registry, &mut graph, (gradient, "field"), (output, "grain"));
+    assert_ne!(hash(&graph), original);
+    connect(&registry, &mut graph, (blur, "field"), (output, "grain"));
+    assert_eq!(hash(&graph), original);
+
+    graph.disconnect(&SocketRef::new(scale, "factor"));
+    assert_ne!(hash(&graph), original, "the factor now comes from a parameter");
+}
+
+#[test]
+fn nodes_without_gpu_code_are_named() {
+    let registry = registry();
+    let (mut graph, _) = single(&registry, "test.cpu_only", "field");
+    let output = graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT).unwrap().id;
+
+    let err = compile(&graph, &registry, OutputFormat::Rgba8, &ShaderLibrary::builtin()).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("test.cpu_only") && message.contains("No GPU implementation"), "{message}");
+
+    graph.remove_node(output);
+    let err = compile(&graph, &registry, OutputFormat::Rgba8, &ShaderLibrary::builtin()).unwrap_err();
+    assert!(matches!(err, GrainError::Graph(GraphError::MissingOutput)), "{err}");
+}
+
+#[test]
+fn gpu_matches_the_cpu_evaluator_and_reuses_pipelines() {
+    let (width, height) = (37, 21);
+    let Some((context, mut renderer)) = gpu_renderer(width, height) else {
+        return;
+    };
+    let registry = registry();
+    let (mut graph, [gradient, _, blur, factor]) = graph(&registry);
+    let mut evaluator = Evaluator::new(width, height);
+
+    let mut compare = |graph: &NodeGraph, renderer: &mut GraphRenderer| {
+        let gpu = render(&context, renderer, graph, &registry);
+        let max_error = max_difference(&gpu, &evaluator.evaluate(graph, &registry).unwrap().output.unwrap());
+        assert!(max_error < 1e-5, "GPU differs from CPU by {max_error}");
+    };
+
+    compare(&graph, &mut renderer);
+    set(&mut graph, factor, "value", ParameterValue::Float(0.5));
+    compare(&graph, &mut renderer);
+    assert_eq!(renderer.cached_pipelines(), 1, "parameter edits reuse the pipeline");
+
+    let output = graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT).unwrap().id;
+    connect(&registry, &mut graph, (gradient, "field"), (output, "grain"));
+    compare(&graph, &mut renderer);
+    assert_eq!(renderer.cached_pipelines(), 2);
+    connect(&registry, &mut graph, (blur, "field"), (output, "grain"));
+    compare(&graph, &mut renderer);
+    assert_eq!(renderer.cached_pipelines(), 2);
+}
+
@@ -0,0 +1,141 @@
+//! Node graph model: typed connections, validation and saving.
+
+use grainforge::core::error::GraphError;
+use grainforge::core::parameter::{Parameter, ParameterValue};
+use grainforge::nodes::node_graph::{NodeGraph, SocketRef};
+use grainforge::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use grainforge::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+/// Built-in types plus a pass-through node, so cycles can be built
+fn registry() -> NodeRegistry {
+    let mut registry = NodeRegistry::builtin();
+    registry.register(
+        NodeType::new("test.scale", "Scale", NodeCategory::Math)
+            .with_input("field", SocketType::Field)
+            .with_input("factor", SocketType::Scalar)
+            .with_output("field", SocketType::Field)
+            .with_parameter(Parameter::new_float("factor", "Factor", "", 1.0, 0.0, 4.0)),
+    );
+    registry
+}
+
+#[test]
+fn connections_are_type_checked() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let value = graph.add_node(&registry, "input.value").unwrap();
+    let vector = graph.add_node(&registry, "input.vector").unwrap();
+    let scale = graph.add_node(&registry, "test.scale").unwrap();
+    let output = graph.add_node(&registry, GRAIN_OUTPUT).unwrap();
+
+    // Scalars widen to fields
+    graph.connect(&registry, SocketRef::new(value, "value"), SocketRef::new(scale, "field")).unwrap();
+    graph.connect(&registry, SocketRef::new(scale, "field"), SocketRef::new(output, "grain")).unwrap();
+
+    // Vectors do not
+    let err = graph.connect(&registry, SocketRef::new(vector, "vector"), SocketRef::new(scale, "factor")).unwrap_err();
+    assert!(matches!(err, GraphError::TypeMismatch { from_type: "vec2", to_type: "scalar", .. }), "{err}");
+    let message = err.to_string();
+    assert!(message.contains("input.vector [") && message.contains("].factor"), "{message}");
+
+    let err = graph.connect(&registry, SocketRef::new(scale, "field"), SocketRef::new(scale, "factor")).unwrap_err();
+    assert!(matches!(err, GraphError::TypeMismatch { from_type: "field", to_type: "scalar", .. }));
+
+    let err = graph.connect(&registry, SocketRef::new(value, "nope"), SocketRef::new(scale, "factor")).unwrap_err();
+    assert!(matches!(err, GraphError::UnknownSocket { direction: "output", .. }), "{err}");
+    assert_eq!(graph.connections().len(), 2);
+    graph.validate(&registry).unwrap();
+}
+
+#[test]
+fn cycles_are_rejected_with_their_path() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let a = graph.add_node(&registry, "test.scale").unwrap();
+    let b = graph.add_node(&registry, "test.scale").unwrap();
+    let c = graph.add_node(&registry, "test.scale").unwrap();
+    let value = graph.add_node(&registry, "input.value").unwrap();
+    graph.connect(&registry, SocketRef::new(value, "value"), SocketRef::new(a, "field")).unwrap();
+    graph.connect(&registry, SocketRef::new(a, "field"), SocketRef::new(b, "field")).unwrap();
+    graph.connect(&registry, SocketRef::new(b, "field"), SocketRef::new(c, "field")).unwrap();
+
+    let err = graph.connect(&registry, SocketRef::new(c, "field"), SocketRef::new(a, "field")).unwrap_err();
+    let GraphError::Cycle { path } = &err else { panic!("{err}") };
+    let steps: Vec<&str> = path.split(" -> ").collect();
+    assert_eq!(steps.len(), 4, "{path}");
+    assert_eq!(steps.first(), steps.last(), "{path}");
+    for id in [a, b, c] {
+        assert!(steps.contains(&graph.node(id).unwrap().to_string().as_str()), "{path}");
+    }
+
+    // The rejected connection left the old one in place
+    assert_eq!(graph.source(&SocketRef::new(a, "field")), Some(&SocketRef::new(value, "value")));
+    let err = graph.connect(&registry, SocketRef::new(a, "field"), SocketRef::new(a, "field")).unwrap_err();
+    assert!(matches!(err, GraphError::Cycle { .. }));
+
+    // Sources come before the nodes they feed
+    assert_eq!(graph.topological_order().unwrap(), vec![value, a, b, c]);
+}
+
+#[test]
+fn validation_catches_hand_edited_graphs() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let value = graph.add_node(&registry, "input.value").unwrap();
+    let output = graph.add_node(&registry, GRAIN_OUTPUT).unwrap();
+
+    // The output has no default, so it must be connected
+    let err = graph.validate(&registry).unwrap_err();
+    assert!(matches!(err, GraphError::UnconnectedInput { .. }), "{err}");
+    assert!(err.to_string().contains(".grain"));
+
+    graph.connect(&registry, SocketRef::new(value, "value"), SocketRef::new(output, "grain")).unwrap();
+    graph.validate(&registry).unwrap();
+
+    // Connections edited on disk bypass `connect`
+    let mut json = serde_json::to_value(&graph).unwrap();
+    let connection = json["connections"][0].clone();
+    json["connections"].as_array_mut().unwrap().push(connection);
+    let doubled: NodeGraph = serde_json::from_value(json.clone()).unwrap();
+    assert!(matches!(doubled.validate(&registry), Err(GraphError::DuplicateInput { .. })));
+
+    json["connections"].as_array_mut().unwrap().pop();
+    json["nodes"][0]["kind"] = "noise.missing".into();
+    let unknown: NodeGraph = serde_json::from_value(json).unwrap();
+    assert!(matches!(unknown.validate(&registry), Err(GraphError::UnknownNodeType(kind)) if kind == "noise.missing"));
+}
+
+#[test]
+fn graphs_round_trip_through_json() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let color = graph.add_node(&registry, "input.color").unwrap();
+    let output = graph.add_node(&registry, GRAIN_OUTPUT).unwrap();
+    graph.connect(&registry, SocketRef::new(color, "color"), SocketRef::new(output, "grain")).unwrap();
+    graph.node_mut(color).unwrap().set_parameter("color", ParameterValue::Color([1.0, 0.5, 0.25, 1.0])).unwrap();
+    graph.node_mut(output).unwrap().position = [320.0, 40.0];
+
+    let json = serde_json::to_string(&graph).unwrap();
+    let loaded: NodeGraph = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, graph);
+    assert_eq!(loaded.node(color).unwrap().id, color, "ids are stable across saves");
+    assert_eq!(loaded.node(color).unwrap().parameter("color").unwrap().value, ParameterValue::Color([1.0, 0.5, 0.25, 1.0]));
+    loaded.validate(&registry).unwrap();
+}
+
+#[test]
+fn parameters_keep_their_kind() {
+    let registry = registry();
+    let mut graph = NodeGraph::new();
+    let value = graph.add_node(&registry, "input.value").unwrap();
+    let node = graph.node_mut(value).unwrap();
+
+    node.set_parameter("value", ParameterValue::Float(2.0)).unwrap();
+    let err = node.set_parameter("value", ParameterValue::Bool(true)).unwrap_err();
+    assert!(err.to_string().contains("Expected a float value, got a bool"), "{err}");
+    assert!(node.set_parameter("missing", ParameterValue::Float(1.0)).is_err());
+
+    assert!(graph.remove_node(value).is_some());
+    assert!(graph.nodes().is_empty());
+    assert!(matches!(graph.add_node(&registry, "nope"), Err(GraphError::UnknownNodeType(_))));
+}
@@ -0,0 +1,173 @@
+//! Math, filter and color nodes: what they compute and GPU/CPU agreement.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{connect, evaluate, finish, gpu_renderer, max_difference, render, set};
+use grainforge::core::parameter::ParameterValue;
+use grainforge::nodes::evaluator::{Field, Value};
+use grainforge::nodes::node_graph::{NodeGraph, NodeId};
+use grainforge::nodes::node_types::{NodeCategory, NodeRegistry, NodeType, SocketType};
+use grainforge::nodes::nodes::output_nodes::GRAIN_OUTPUT;
+
+fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+    let close = actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-5);
+    assert!(close, "{actual:?} != {expected:?}");
+}
+
+/// A constant fed into the `value` input of a `kind` node
+fn on_constant(registry: &NodeRegistry, kind: &str, value: f32) -> (NodeGraph, NodeId) {
+    let mut graph = NodeGraph::new();
+    let constant = graph.add_node(registry, "input.value").unwrap();
+    let node = graph.add_node(registry, kind).unwrap();
+    set(&mut graph, constant, "value", ParameterValue::Float(value));
+    connect(registry, &mut graph, (constant, "value"), (node, "value"));
+    finish(registry, &mut graph, node, "result");
+    (graph, node)
+}
+
+#[test]
+fn math_nodes_compute_per_channel() {
+    let registry = NodeRegistry::builtin();
+    let result = |graph: &NodeGraph| evaluate(&registry, graph, 2, 2).get(1, 1);
+
+    let mut graph = NodeGraph::new();
+    let add = graph.add_node(&registry, "math.add").unwrap();
+    set(&mut graph, add, "a", ParameterValue::Float(0.25));
+    set(&mut graph, add, "b", ParameterValue::Float(0.5));
+    finish(&registry, &mut graph, add, "result");
+    assert_close(result(&graph), [0.75, 0.75, 0.75, 1.0]);
+
+    let (mut graph, remap) = on_constant(&registry, "math.remap", 0.5);
+    set(&mut graph, remap, "to_min", ParameterValue::Float(-1.0));
+    set(&mut graph, remap, "to_max", ParameterValue::Float(3.0));
+    assert_close(result(&graph), [1.0, 1.0, 1.0, 1.0]);
+
+    let (mut graph, clamp) = on_constant(&registry, "math.clamp", 1.5);
+    set(&mut graph, clamp, "high", ParameterValue::Float(0.8));
+    assert_close(result(&graph), [0.8, 0.8, 0.8, 1.0]);
+
+    let (mut graph, power) = on_constant(&registry, "math.power", -0.5);
+    set(&mut graph, power, "exponent", ParameterValue::Float(2.0));
+    assert_close(result(&graph), [-0.25, -0.25, -0.25, 1.0]);
+
+    // The default curve is the identity; moving a point bends it through that point
+    let (mut graph, curve) = on_constant(&registry, "math.curve", 0.3);
+    assert_close(result(&graph), [0.3, 0.3, 0.3, 1.0]);
+    set(&mut graph, curve, "midtones", ParameterValue::Float(0.7));
+    assert!(result(&graph)[0] > 0.3);
+    let (mut graph, curve) = on_constant(&registry, "math.curve", 0.5);
+    set(&mut graph, curve, "midtones", ParameterValue::Float(0.7));
+    assert_close(result(&graph), [0.7, 0.7, 0.7, 1.0]);
+}
+
+#[test]
+fn filters_respect_their_neighbourhoods() {
+    let mut registry = NodeRegistry::builtin();
+    // One bright pixel at (4, 4) on black
+    registry.register(
+        NodeType::new("test.dot", "Dot", NodeCategory::Noise)
+            .with_output("field", SocketType::Field)
+            .with_evaluate(|ctx| {
+                let field = Field::from_fn(ctx.width, ctx.height, |x, y| if (x, y) == (4, 4) { [1.0; 4] } else { [0.0, 0.0, 0.0, 1.0] });
+                Ok(vec![Value::Field(Arc::new(field))])
+            }),
+    );
+    let filtered = |kind: &str, radius: f32| {
+        let mut graph = NodeGraph::new();
+        let dot = graph.add_node(&registry, "test.dot").unwrap();
+        let filter = graph.add_node(&registry, kind).unwrap();
+        set(&mut graph, filter, "radius", ParameterValue::Float(radius));
+        connect(&registry, &mut graph, (dot, "field"), (filter, "field"));
+        finish(&registry, &mut graph, filter, "field");
+        evaluate(&registry, &graph, 9, 9)
+    };
+    let lit = |field: &Field| field.pixels().iter().filter(|p| p[0] > 0.5).count();
+
+    assert_eq!(lit(&filtered("filter.median", 1.0)), 0, "median keeps an outlier");
+    assert_eq!(lit(&filtered("filter.dilate", 2.0)), 13, "radius 2 disc");
+    assert_eq!(lit(&filtered("filter.erode", 1.0)), 0);
+
+    let blurred = filtered("filter.gaussian_blur", 1.0);
+    let total: f32 = blurred.pixels().iter().map(|p| p[0]).sum();
+    assert!((total - 1.0).abs() < 1e-4, "blur does not preserve energy: {total}");
+    assert!(blurred.get(4, 4)[0] > blurred.get(5, 4)[0] && blurred.get(5, 4)[0] > blurred.get(5, 5)[0]);
+
+    let high_pass = filtered("filter.high_pass", 1.0);
+    assert!(high_pass.get(4, 4)[0] > 0.5 && high_pass.get(5, 4)[0] < 0.5 && high_pass.get(0, 8)[0] == 0.5);
+    assert!(filtered("filter.sharpen", 1.0).get(4, 4)[0] > 1.0);
+}
+
+#[test]
+fn color_nodes_round_trip() {
+    let registry = NodeRegistry::builtin();
+    let mut graph = NodeGraph::new();
+    let color = graph.add_node(&registry, "input.color").unwrap();
+    set(&mut graph, color, "color", ParameterValue::Color([0.2, 0.5, 0.9, 0.75]));
+
+    // split -> merge gives the color back
+    let split = graph.add_node(&registry, "color.split").unwrap();
+    let merge = graph.add_node(&registry, "color.merge").unwrap();
+    connect(&registry, &mut graph, (color, "color"), (split, "color"));
+    for channel in ["r", "g", "b", "a"] {
+        connect(&registry, &mut graph, (split, channel), (merge, channel));
+    }
+    // linear -> sRGB -> linear too
+    let to_srgb = graph.add_node(&registry, "color.to_srgb").unwrap();
+    let to_linear = graph.add_node(&registry, "color.to_linear").unwrap();
+    connect(&registry, &mut graph, (merge, "color"), (to_srgb, "color"));
+    connect(&registry, &mut graph, (to_srgb, "color"), (to_linear, "color"));
+    finish(&registry, &mut graph, to_linear, "color");
+    assert_close(evaluate(&registry, &graph, 3, 3).get(2, 1), [0.2, 0.5, 0.9, 0.75]);
+
+    // No saturation is the luminance
+    let saturation = graph.add_node(&registry, "color.saturation").unwrap();
+    set(&mut graph, saturation, "saturation", ParameterValue::Float(0.0));
+    connect(&registry, &mut graph, (color, "color"), (saturation, "color"));
+    let output = graph.nodes().iter().find(|node| node.kind == GRAIN_OUTPUT).unwrap().id;
+    connect(&registry, &mut graph, (saturation, "color"), (output, "grain"));
+    let luma = 0.2126 * 0.2 + 0.7152 * 0.5 + 0.0722 * 0.9;
+    assert_close(evaluate(&registry, &graph, 3, 3).get(0, 0), [luma, luma, luma, 0.75]);
+}
+
+#[test]
+fn every_builtin_node_runs_on_both_backends() {
+    let registry = NodeRegistry::builtin();
+    for node_type in registry.types() {
+        assert!(node_type.evaluate.is_some(), "{} has no CPU implementation", node_type.id);
+        if node_type.id != GRAIN_OUTPUT {
+            assert!(node_type.wgsl.is_some(), "{} has no GPU implementation", node_type.id);
+        }
+    }
+}
+
+#[test]
+fn gpu_library_matches_the_cpu() {
+    let (width, height) = (40, 24);
+    let Some((context, mut renderer)) = gpu_renderer(width, height) else {
+        return;
+    };
+    let registry = NodeRegistry::builtin();
+    let families = [NodeCategory::Math, NodeCategory::Filter, NodeCategory::Color];
+
+    for node_type in registry.types().filter(|node_type| families.contains(&node_type.category)) {
+        // Colored cells into every field input, so neighbourhoods and channels both matter
+        let mut graph = NodeGraph::new();
+        let node = graph.add_node(&registry, node_type.id).unwrap();
+        for (seed, input) in node_type.inputs.iter().filter(|input| input.ty == SocketType::Field).enumerate() {
+            let cells = graph.add_node(&registry, "noise.voronoi").unwrap();
+            set(&mut graph, cells, "seed", ParameterValue::Float(seed as f32 + 1.0));
+            set(&mut graph, cells, "scale", ParameterValue::Float(6.0));
+            connect(&registry, &mut graph, (cells, "cell_id"), (node, input.name));
+        }
+        let grain = graph.add_node(&registry, GRAIN_OUTPUT).unwrap();
+
+        for output in &node_type.outputs {
+            connect(&registry, &mut graph, (node, output.name), (grain, "grain"));
+            let gpu = render(&context, &mut renderer, &graph, &registry);
+            let error = max_difference(&gpu, &evaluate(&registry, &graph, width, height));
+            assert!(error < 1e-3, "{}.{} differs by {error}", node_type.id, output.name);
+        }
+    }
+}
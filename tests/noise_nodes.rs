@@ -0,0 +1,97 @@
+//! Noise nodes: seeding, tiling, distributions and GPU/CPU agreement.
+
+mod common;
+
+use common::{evaluate, gpu_renderer, max_difference, render, set, single};
+use grainforge::core::parameter::ParameterValue;
+use grainforge::nodes::node_types::{NodeCategory, NodeRegistry};
+
+/// Every noise node with each of its outputs
+fn noise_outputs(registry: &NodeRegistry) -> Vec<(&'static str, &'static str)> {
+    registry.types()
+        .filter(|node_type| node_type.category == NodeCategory::Noise)
+        .flat_map(|node_type| node_type.outputs.iter().map(|output| (node_type.id, output.name)))
+        .collect()
+}
+
+#[test]
+fn every_noise_node_shares_the_lattice_inputs() {
+    let registry = NodeRegistry::builtin();
+    let outputs = noise_outputs(&registry);
+    assert_eq!(outputs.len(), 8);
+    for (kind, _) in outputs {
+        let node_type = registry.get(kind).unwrap();
+        for input in ["seed", "scale", "offset", "tiling"] {
+            assert!(node_type.input(input).is_some() && node_type.parameter(input).is_some(), "{kind} lacks {input}");
+        }
+    }
+}
+
+#[test]
+fn seeds_pick_different_patterns() {
+    let registry = NodeRegistry::builtin();
+    for (kind, output) in noise_outputs(&registry) {
+        let (mut graph, node) = single(&registry, kind, output);
+        let first = evaluate(&registry, &graph, 24, 16);
+        assert_eq!(evaluate(&registry, &graph, 24, 16), first, "{kind} is not deterministic");
+
+        set(&mut graph, node, "seed", ParameterValue::Float(7.0));
+        assert!(max_difference(&evaluate(&registry, &graph, 24, 16), &first) > 0.01, "{kind}.{output} ignores its seed");
+    }
+}
+
+#[test]
+fn tiled_noise_repeats_every_period() {
+    let registry = NodeRegistry::builtin();
+    for (kind, output) in noise_outputs(&registry) {
+        let (mut graph, node) = single(&registry, kind, output);
+        set(&mut graph, node, "scale", ParameterValue::Float(7.0));
+        set(&mut graph, node, "tiling", ParameterValue::Bool(true));
+        let tiled = evaluate(&registry, &graph, 32, 32);
+
+        // Tiling snaps 7 cells to 8, so moving the lattice by 8 cells changes nothing
+        set(&mut graph, node, "offset", ParameterValue::Vec2([8.0, -16.0]));
+        let error = max_difference(&evaluate(&registry, &graph, 32, 32), &tiled);
+        assert!(error < 1e-3, "{kind}.{output} does not tile: {error}");
+    }
+}
+
+#[test]
+fn white_and_gaussian_noise_have_their_distributions() {
+    let registry = NodeRegistry::builtin();
+    let stats = |kind: &str| {
+        let (graph, _) = single(&registry, kind, "noise");
+        let field = evaluate(&registry, &graph, 128, 128);
+        let n = field.pixels().len() as f32;
+        let mean = field.pixels().iter().map(|p| p[0]).sum::<f32>() / n;
+        let variance = field.pixels().iter().map(|p| (p[0] - mean).powi(2)).sum::<f32>() / n;
+        (mean, variance)
+    };
+
+    let (mean, variance) = stats("noise.white");
+    assert!((mean - 0.5).abs() < 0.02 && (variance - 1.0 / 12.0).abs() < 0.01, "white: {mean}, {variance}");
+    let (mean, variance) = stats("noise.gaussian");
+    assert!(mean.abs() < 0.05 && (variance - 1.0).abs() < 0.1, "gaussian: {mean}, {variance}");
+}
+
+#[test]
+fn gpu_noise_matches_the_cpu_ports() {
+    let (width, height) = (45, 27);
+    let Some((context, mut renderer)) = gpu_renderer(width, height) else {
+        return;
+    };
+    let registry = NodeRegistry::builtin();
+
+    for (kind, output) in noise_outputs(&registry) {
+        for tiling in [false, true] {
+            let (mut graph, node) = single(&registry, kind, output);
+            set(&mut graph, node, "seed", ParameterValue::Float(42.0));
+            set(&mut graph, node, "offset", ParameterValue::Vec2([0.3, -2.6]));
+            set(&mut graph, node, "tiling", ParameterValue::Bool(tiling));
+
+            let gpu = render(&context, &mut renderer, &graph, &registry);
+            let error = max_difference(&gpu, &evaluate(&registry, &graph, width, height));
+            assert!(error < 1e-3, "{kind}.{output} (tiling {tiling}) differs by {error}");
+        }
+    }
+}
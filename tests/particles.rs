@@ -0,0 +1,58 @@
+//! Particle synthesis: grain shapes set how much of the frame grains cover, and
+//! sharpness sets how hard their edges are.
+
+use grainforge::core::film_stock::{CrystalType, FilmStock, GrainSynthesis};
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+
+const SIZE: u32 = 96;
+
+fn particles(crystal_type: CrystalType, sharpness: f32) -> FilmStock {
+    let mut stock = FilmStock::default();
+    stock.color.is_color = false;
+    stock.grain.synthesis = GrainSynthesis::Particle;
+    stock.grain.crystal_type = crystal_type;
+    stock.grain.size.set(3.0);
+    stock.grain.size_variation.set(0.0);
+    stock.grain.sharpness.set(sharpness);
+    // Neutral response and full strength, so the output is the grain coverage itself
+    stock.grain.intensity.set(1.0);
+    stock
+}
+
+/// Grain coverage of every pixel
+fn coverage(stock: &FilmStock) -> Vec<f32> {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    renderer.render(&GrainParams::from_film_stock(stock, SIZE, SIZE).with_seed(4));
+    renderer.output_image_f32().pixels().map(|p| p.0[0]).collect()
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+#[test]
+fn thinner_crystals_cover_less() {
+    // Outlines of decreasing area: a square, a disc seen at an angle, a needle
+    let covered: Vec<f32> = [CrystalType::Cubic, CrystalType::Tabular, CrystalType::Needle]
+        .into_iter()
+        .map(|crystal_type| mean(&coverage(&particles(crystal_type, 1.0))))
+        .collect();
+    assert!(covered.windows(2).all(|w| w[0] > w[1]), "{covered:?}");
+
+    // Polygons with more sides are closer to their circumscribed circle
+    let triangle = mean(&coverage(&particles(CrystalType::Custom { sides: 3 }, 1.0)));
+    let octagon = mean(&coverage(&particles(CrystalType::Custom { sides: 8 }, 1.0)));
+    assert!(octagon > triangle, "{octagon} vs {triangle}");
+}
+
+#[test]
+fn sharp_grains_have_hard_edges() {
+    // Pixels partly covered by a grain edge
+    let soft_edged = |sharpness: f32| {
+        let values = coverage(&particles(CrystalType::Cubic, sharpness));
+        values.iter().filter(|&&v| v > 0.05 && v < 0.95).count() as f32 / values.len() as f32
+    };
+    assert!(soft_edged(0.0) > 2.0 * soft_edged(1.0), "{} vs {}", soft_edged(0.0), soft_edged(1.0));
+}
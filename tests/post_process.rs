@@ -0,0 +1,168 @@
+//! Post-processing: effect behaviour, serialization with the stock, tiling and GPU parity.
+
+use grainforge::core::film_stock::{FilmStock, PostEffect, PostProcessChain};
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::grain_renderer::{GrainParams, GrainRenderer};
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::post_process::apply_chain;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer};
+use grainforge::utils::validation::BoundedFloat;
+
+const SIZE: u32 = 40;
+
+fn blur(radius: f32) -> PostEffect {
+    PostEffect::Blur { radius: BoundedFloat::new(radius, 0.0, 16.0) }
+}
+
+fn unsharp(radius: f32, amount: f32) -> PostEffect {
+    PostEffect::UnsharpMask {
+        radius: BoundedFloat::new(radius, 0.0, 8.0),
+        amount: BoundedFloat::new(amount, 0.0, 3.0),
+    }
+}
+
+fn halation() -> PostEffect {
+    PostEffect::defaults().into_iter().find(|e| matches!(e, PostEffect::Halation { .. })).unwrap()
+}
+
+fn chain(effects: Vec<PostEffect>) -> PostProcessChain {
+    PostProcessChain { effects }
+}
+
+fn grain_params() -> GrainParams {
+    GrainParams::from_film_stock(&FilmStock::default(), SIZE, SIZE).with_seed(9)
+}
+
+/// Grey grain render, post-processed on the CPU, as floats
+fn processed_grain(post_process: &PostProcessChain) -> Vec<f32> {
+    let mut renderer = CpuGrainRenderer::with_format(SIZE, SIZE, OutputFormat::Rgba32Float).unwrap();
+    renderer.render(&grain_params());
+    renderer.post_process(post_process);
+    OutputFormat::Rgba32Float.decode(renderer.output())
+}
+
+fn variance(pixels: &[f32]) -> f32 {
+    let reds: Vec<f32> = pixels.iter().step_by(4).copied().collect();
+    let mean = reds.iter().sum::<f32>() / reds.len() as f32;
+    reds.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / reds.len() as f32
+}
+
+#[test]
+fn effects_soften_sharpen_and_glow() {
+    let original = variance(&processed_grain(&PostProcessChain::default()));
+    assert!(variance(&processed_grain(&chain(vec![blur(1.5)]))) < original * 0.7, "blur softens grain");
+    assert!(variance(&processed_grain(&chain(vec![unsharp(1.0, 1.0)]))) > original * 1.3, "unsharp masking crisps grain");
+    assert_eq!(processed_grain(&chain(vec![blur(0.0), unsharp(0.0, 2.0)])), processed_grain(&PostProcessChain::default()));
+
+    // A white square on black: halation glows red around it, fading with distance
+    let (width, height) = (32, 32);
+    let mut pixels: Vec<f32> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let v = if (12..20).contains(&x) && (12..20).contains(&y) { 1.0 } else { 0.0 };
+            [v, v, v, 1.0]
+        })
+        .collect();
+    apply_chain(&chain(vec![halation()]), &mut pixels, width, height, [width, height]);
+
+    let pixel = |x: u32, y: u32| &pixels[((y * width + x) * 4) as usize..][..4];
+    let near = pixel(21, 16);
+    assert!(near[0] > 0.05 && near[0] > near[1] && near[1] > near[2], "red-tinted glow, got {near:?}");
+    assert!(pixel(0, 0)[0] < near[0] / 10.0, "glow fades away from the highlight");
+    assert_eq!(pixel(16, 16)[..3], [1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn chains_are_saved_with_the_stock() {
+    let stock = FilmStock {
+        post_process: chain(vec![halation(), blur(2.0), unsharp(1.0, 0.8)]),
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&stock).unwrap();
+    assert_eq!(json["post_process"][0]["effect"], "halation");
+    assert_eq!(json["post_process"][2]["effect"], "unsharp_mask");
+    let loaded: FilmStock = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(loaded.post_process, stock.post_process);
+
+    // Stocks saved before post-processing existed load with an empty chain
+    let mut legacy = json;
+    legacy.as_object_mut().unwrap().remove("post_process");
+    assert!(serde_json::from_value::<FilmStock>(legacy).unwrap().post_process.is_empty());
+}
+
+#[test]
+fn new_effects_follow_the_stock() {
+    let effect = |stock: &FilmStock, name| PostEffect::defaults_for(stock).into_iter().find(|e| e.name() == name).unwrap();
+    let amount = |effect: PostEffect| match effect {
+        PostEffect::UnsharpMask { amount, .. } => amount.get(),
+        _ => unreachable!(),
+    };
+    let (mut soft, mut sharp) = (FilmStock::default(), FilmStock::default());
+    soft.color.dye_softness.set(1.0);
+    soft.grain.sharpness.set(0.0);
+    sharp.grain.sharpness.set(1.0);
+
+    assert!(effect(&soft, "Blur").radius() > effect(&sharp, "Blur").radius());
+    assert!(amount(effect(&sharp, "Unsharp Mask")) > amount(effect(&soft, "Unsharp Mask")));
+
+    // Radii are in pixels, so they grow with the grain
+    let mut coarse = sharp.clone();
+    coarse.grain.size.set(2.0);
+    assert_eq!(effect(&coarse, "Blur").radius(), 2.0 * effect(&sharp, "Blur").radius());
+}
+
+#[test]
+fn tiled_post_processing_matches_a_single_render() {
+    let (width, height) = (53, 37);
+    let params = GrainParams::from_film_stock(&FilmStock::default(), width, height).with_seed(9);
+    let post_process = chain(vec![halation(), unsharp(1.0, 0.8)]);
+    let post_process = post_process.scaled(0.5);
+
+    let mut single = CpuGrainRenderer::with_format(width, height, OutputFormat::Rgba32Float).unwrap();
+    single.render(&params);
+    single.post_process(&post_process);
+
+    let plan = TilePlan::new(width, height, 16, post_process.reach()).unwrap();
+    let mut tiled = Vec::new();
+    TiledRenderer::cpu(plan, OutputFormat::Rgba32Float)
+        .with_post_process(post_process.clone())
+        .unwrap()
+        .render_bands(&params, None, |band| {
+            tiled.extend(band);
+            Ok(())
+        })
+        .unwrap();
+    assert!(tiled == single.output(), "tiles must filter across their neighbours");
+
+    let narrow = TilePlan::new(width, height, 16, post_process.reach() - 1).unwrap();
+    assert!(TiledRenderer::cpu(narrow, OutputFormat::Rgba8).with_post_process(post_process).is_err());
+}
+
+#[test]
+fn gpu_post_processing_matches_the_cpu() {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let post_process = chain(vec![halation(), blur(1.0), unsharp(1.5, 1.0)]);
+
+    for format in [OutputFormat::Rgba8, OutputFormat::Rgba32Float] {
+        let mut gpu = GrainRenderer::with_format(&context.device, SIZE, SIZE, format).unwrap();
+        gpu.render(&context.device, &context.queue, &grain_params());
+        gpu.post_process(&context.device, &context.queue, &post_process).unwrap();
+        let gpu = format.decode(&gpu.read_pixels(&context.device, &context.queue).unwrap());
+
+        let mut cpu = CpuGrainRenderer::with_format(SIZE, SIZE, format).unwrap();
+        cpu.render(&grain_params());
+        cpu.post_process(&post_process);
+        let cpu = format.decode(cpu.output());
+
+        // One 8-bit step, from float rounding in the grain and the blur weights
+        let max_error = gpu.iter().zip(&cpu).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(max_error <= 1.0 / 255.0 + 1e-6, "{}: GPU differs from CPU by {max_error}", format.name());
+    }
+}
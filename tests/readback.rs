@@ -0,0 +1,80 @@
+//! Texture readback: row padding is stripped, and the async path matches the blocking one.
+
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use grainforge::core::film_stock::FilmStock;
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::grain_renderer::{GrainParams, GrainRenderer};
+use grainforge::engine::readback::{read_texture, read_texture_async};
+use wgpu::{
+    Device, Extent3d, Maintain, Origin3d, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+// 48 RGBA8 pixels are 192 bytes, so every row is padded to 256 in the staging buffer
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 5;
+
+/// Drive `future` to completion, polling the device between attempts as eframe would
+fn resolve<T>(device: &Device, future: impl Future<Output = T>) -> T {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+            return value;
+        }
+        device.poll(Maintain::Poll);
+    }
+}
+
+#[test]
+fn padded_rows_are_read_back_exactly() {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let (device, queue) = (&context.device, &context.queue);
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("Readback Test Texture"),
+        size: Extent3d { width: WIDTH, height: HEIGHT, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    // Every byte differs from its neighbours in the row above and below
+    let pixels: Vec<u8> = (0..WIDTH * HEIGHT * 4).map(|i| (i * 7 % 251) as u8).collect();
+    queue.write_texture(
+        TexelCopyTextureInfo { texture: &texture, mip_level: 0, origin: Origin3d::ZERO, aspect: TextureAspect::All },
+        &pixels,
+        TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(WIDTH * 4), rows_per_image: Some(HEIGHT) },
+        texture.size(),
+    );
+
+    assert_eq!(read_texture(device, queue, &texture).unwrap(), pixels);
+    assert_eq!(resolve(device, read_texture_async(device, queue, &texture)).unwrap(), pixels);
+}
+
+#[test]
+fn async_render_readback_matches_the_blocking_one() {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let (device, queue) = (&context.device, &context.queue);
+    let renderer = GrainRenderer::new(device, WIDTH, HEIGHT).unwrap();
+    renderer.render(device, queue, &GrainParams::from_film_stock(&FilmStock::default(), WIDTH, HEIGHT).with_seed(3));
+
+    let blocking = renderer.read_pixels(device, queue).unwrap();
+    assert_eq!(blocking.len(), (WIDTH * HEIGHT * 4) as usize);
+    assert_eq!(resolve(device, renderer.read_pixels_async(device, queue)).unwrap(), blocking);
+    assert_eq!(renderer.read_image(device, queue).unwrap().into_raw(), blocking);
+}
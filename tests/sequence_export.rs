@@ -0,0 +1,129 @@
+//! Frame sequences: naming, per-frame seeds, evolving grain and cancellation.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use grainforge::core::film_stock::FilmStock;
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer};
+use grainforge::export::sequence_export::{export_sequence, frame_path, SequenceOutcome, SequenceSettings};
+
+const SIZE: u32 = 24;
+
+#[test]
+fn frame_paths_are_zero_padded() {
+    assert_eq!(frame_path(Path::new("out/grain_####.png"), 12), Path::new("out/grain_0012.png"));
+    assert_eq!(frame_path(Path::new("grain.#.tif"), 1001), Path::new("grain.1001.tif"));
+    assert_eq!(frame_path(Path::new("plate_v#_####.exr"), 7), Path::new("plate_v#_0007.exr"));
+    assert_eq!(frame_path(Path::new("grain.png"), 3), Path::new("grain_0003.png"));
+}
+
+#[test]
+fn coherence_controls_how_fast_the_grain_evolves() {
+    let params = GrainParams::from_film_stock(&FilmStock::default(), SIZE, SIZE).with_seed(3);
+    let step = |coherence: f32| {
+        let settings = SequenceSettings { coherence, ..Default::default() };
+        let (a, b) = (settings.frame_params(&params, 0), settings.frame_params(&params, 1));
+        assert_eq!(a.seed, b.seed, "coherent frames keep the seed");
+        b.time - a.time
+    };
+
+    assert!(step(0.9) < step(0.5) && step(0.5) < step(0.1), "grain must evolve more slowly as coherence rises");
+    assert!(step(1.0) > 0.0, "full coherence still evolves");
+    assert!(step(1e-4) > 0.99, "just above 0 every grain re-rolls each frame: {}", step(1e-4));
+
+    // Full coherence evolves at the same speed in seconds whatever the frame rate
+    let per_second = |fps: f32| SequenceSettings { fps, coherence: 1.0, ..Default::default() }.frame_params(&params, 1).time * fps;
+    assert!((per_second(24.0) - per_second(60.0)).abs() < 1e-4);
+
+    // Without coherence every frame gets its own seed, starting from the still's
+    let settings = SequenceSettings::default();
+    let seeds: Vec<u64> = (0..24).map(|i| settings.frame_params(&params, i).seed_u64()).collect();
+    assert_eq!(seeds[0], 3);
+    assert!(seeds.iter().enumerate().all(|(i, a)| seeds[i + 1..].iter().all(|b| a != b)));
+    assert!((0..24).all(|i| settings.frame_params(&params, i).time == params.time));
+}
+
+/// Red channel of the `index`th frame of a sequence with the given coherence
+fn frame(coherence: f32, index: u32) -> Vec<f32> {
+    let params = GrainParams::from_film_stock(&FilmStock::default(), 64, 64).with_seed(5);
+    let settings = SequenceSettings { coherence, ..Default::default() };
+    let mut renderer = CpuGrainRenderer::with_format(64, 64, OutputFormat::Rgba32Float).unwrap();
+    renderer.render(&settings.frame_params(&params, index));
+    renderer.output_image_f32().pixels().map(|p| p.0[0]).collect()
+}
+
+/// Pearson correlation between two frames
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+    let (ma, mb) = (mean(a), mean(b));
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        ab += (x - ma) * (y - mb);
+        aa += (x - ma) * (x - ma);
+        bb += (y - mb) * (y - mb);
+    }
+    ab / (aa * bb).sqrt()
+}
+
+#[test]
+fn coherent_grain_evolves_in_place() {
+    let first = frame(0.0, 0);
+    assert_eq!(frame(0.5, 0), first, "the first frame is the still whatever the coherence");
+
+    // Coherence just above 0 looks like reseeding: consecutive frames are unrelated
+    for coherence in [0.0, 1e-3] {
+        let r = correlation(&frame(coherence, 0), &frame(coherence, 1));
+        assert!(r.abs() < 0.15, "coherence {coherence}: frames correlate by {r}");
+    }
+
+    // High coherence re-rolls a few grains and leaves the rest exactly where they were,
+    // where a moving field would change every pixel
+    let next = frame(0.95, 1);
+    let unchanged = first.iter().zip(&next).filter(|(a, b)| a == b).count() as f32 / first.len() as f32;
+    assert!(unchanged > 0.6 && unchanged < 1.0, "{unchanged} of the pixels are unchanged");
+    assert!(correlation(&first, &next) > 0.9);
+}
+
+#[test]
+fn sequences_write_numbered_frames_until_cancelled() {
+    let dir = std::env::temp_dir().join(format!("grainforge-sequence-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let params = GrainParams::from_film_stock(&FilmStock::default(), SIZE, SIZE);
+    let settings = SequenceSettings { fps: 4.0, duration: 1.5, start_frame: 1001, ..Default::default() };
+    let plan = TilePlan::new(SIZE, SIZE, 16, 4).unwrap();
+    let mut renderer = TiledRenderer::cpu(plan, OutputFormat::Rgba8);
+    let pattern = dir.join("grain_####.png");
+
+    let mut reported = Vec::new();
+    let outcome = export_sequence(&mut renderer, &params, None, &settings, &pattern, &AtomicBool::new(false), |p| {
+        reported.push((p.frame, p.fraction()));
+    })
+    .unwrap();
+
+    assert_eq!(outcome, SequenceOutcome::Completed { frames: 6 });
+    assert_eq!(reported.first(), Some(&(1001, 1.0 / 6.0)));
+    assert_eq!(reported.last(), Some(&(1006, 1.0)));
+    let first = image::open(dir.join("grain_1001.png")).unwrap();
+    let second = image::open(dir.join("grain_1002.png")).unwrap();
+    assert_ne!(first, second, "grain must change between frames");
+
+    // Cancelling from the progress callback stops before the next frame
+    let cancel = AtomicBool::new(false);
+    let pattern = dir.join("cancelled_####.png");
+    let outcome = export_sequence(&mut renderer, &params, None, &settings, &pattern, &cancel, |p| {
+        if p.frames_done == 2 {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    })
+    .unwrap();
+
+    assert_eq!(outcome, SequenceOutcome::Cancelled { frames: 2 });
+    assert!(dir.join("cancelled_1002.png").exists());
+    assert!(!dir.join("cancelled_1003.png").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
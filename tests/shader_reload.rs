@@ -0,0 +1,109 @@
+//! Shader hot reload: edits on disk are picked up, and a broken edit never replaces a
+//! working pipeline.
+
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use grainforge::core::error::{GrainError, ShaderError};
+use grainforge::core::film_stock::{FilmStock, PostEffect, PostProcessChain};
+use grainforge::engine::backend::gpu_adapter_available;
+use grainforge::engine::gpu_context::GpuContext;
+use grainforge::engine::grain_renderer::{GrainParams, GrainRenderer};
+use grainforge::engine::shaders::{ShaderLibrary, ShaderWatcher, SHADER_FILES};
+
+const SIZE: u32 = 16;
+const GRAIN_STORE: &str = "textureStore(output_texture, texel, color);";
+
+fn write_shader(dir: &Path, name: &str, source: &str, age: u64) {
+    let path = dir.join(name);
+    std::fs::write(&path, source).unwrap();
+    // Explicit times, so quick successive writes still look like separate saves
+    let modified = SystemTime::now() - Duration::from_secs(age);
+    File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+}
+
+fn builtin(name: &str) -> &'static str {
+    SHADER_FILES.iter().find(|(file, _)| *file == name).unwrap().1
+}
+
+#[test]
+fn watcher_reloads_saved_files() {
+    let dir = std::env::temp_dir().join(format!("grainforge-shader-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_shader(&dir, "grain.wgsl", builtin("grain.wgsl"), 60);
+
+    let mut watcher = ShaderWatcher::new(&dir);
+    let library = watcher.poll().expect("first poll loads the directory").unwrap();
+    assert_eq!(library.source("grain.wgsl"), Some(builtin("grain.wgsl")));
+    // Files missing from the directory fall back to the built-in ones
+    assert_eq!(library.source("noise.wgsl"), Some(builtin("noise.wgsl")));
+    assert!(watcher.poll().is_none(), "nothing changed");
+
+    let edited = builtin("grain.wgsl").replace(GRAIN_STORE, "textureStore(output_texture, texel, vec4<f32>(1.0));");
+    write_shader(&dir, "grain.wgsl", &edited, 30);
+    let library = watcher.poll().expect("edit is picked up").unwrap();
+    assert_eq!(library.source("grain.wgsl"), Some(edited.as_str()));
+
+    write_shader(&dir, "noise.wgsl", builtin("noise.wgsl"), 10);
+    assert!(watcher.poll().is_some(), "new files are picked up");
+    assert!(watcher.poll().is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_reload_keeps_the_last_good_pipeline() {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let (device, queue) = (&context.device, &context.queue);
+    let params = GrainParams::from_film_stock(&FilmStock::default(), SIZE, SIZE).with_seed(5);
+    let mut renderer = GrainRenderer::new(device, SIZE, SIZE).unwrap();
+    let render = |renderer: &GrainRenderer| {
+        renderer.render(device, queue, &params);
+        renderer.read_pixels(device, queue).unwrap()
+    };
+    let original = render(&renderer);
+
+    let mut broken = ShaderLibrary::builtin();
+    broken.set_source("grain.wgsl", builtin("grain.wgsl").replace(GRAIN_STORE, "textureStore(output_texture, texel, colour);"));
+    match renderer.reload_shaders(device, &broken) {
+        Err(GrainError::Shader(ShaderError::Compile { location, .. })) => assert!(location.starts_with("grain.wgsl:")),
+        other => panic!("expected a compile error, got {other:?}"),
+    }
+    assert_eq!(render(&renderer), original, "the previous pipeline stays in use");
+
+    let mut white = ShaderLibrary::builtin();
+    white.set_source("grain.wgsl", builtin("grain.wgsl").replace(GRAIN_STORE, "textureStore(output_texture, texel, vec4<f32>(1.0));"));
+    renderer.reload_shaders(device, &white).unwrap();
+    assert!(render(&renderer).iter().all(|&v| v == 255), "the new pipeline renders");
+}
+
+#[test]
+fn post_process_edits_are_reloaded() {
+    if !gpu_adapter_available() {
+        eprintln!("No GPU adapter, skipping");
+        return;
+    }
+    let context = GpuContext::new_headless().unwrap();
+    let (device, queue) = (&context.device, &context.queue);
+    let params = GrainParams::from_film_stock(&FilmStock::default(), SIZE, SIZE).with_seed(5);
+    let chain = PostProcessChain { effects: vec![PostEffect::defaults()[0].clone()] };
+    let mut renderer = GrainRenderer::new(device, SIZE, SIZE).unwrap();
+    let render = |renderer: &mut GrainRenderer| {
+        renderer.render(device, queue, &params);
+        renderer.post_process(device, queue, &chain).unwrap();
+        renderer.read_pixels(device, queue).unwrap()
+    };
+    assert!(render(&mut renderer).iter().any(|&v| v != 255));
+
+    // The copy pass writes every result back, so making it write white shows the edit is live
+    let mut white = ShaderLibrary::builtin();
+    let copy = "textureStore(output_texture, texel, textureLoad(source, texel, 0));";
+    white.set_source("postprocess.wgsl", builtin("postprocess.wgsl").replace(copy, "textureStore(output_texture, texel, vec4<f32>(1.0));"));
+    renderer.reload_shaders(device, &white).unwrap();
+    assert!(render(&mut renderer).iter().all(|&v| v == 255), "the edited post-process stage runs");
+}
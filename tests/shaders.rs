@@ -0,0 +1,93 @@
+//! Every shader must preprocess and pass naga validation, so broken WGSL fails here
+//! instead of when a pipeline is created.
+
+use grainforge::core::error::ShaderError;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::shaders::{ShaderLibrary, SHADER_FILES};
+
+fn with_sources(files: &[(&str, &str)]) -> ShaderLibrary {
+    let mut library = ShaderLibrary::builtin();
+    for (name, source) in files {
+        library.set_source(*name, *source);
+    }
+    library
+}
+
+#[test]
+fn builtin_shaders_validate() {
+    let library = ShaderLibrary::builtin();
+    for (name, _) in SHADER_FILES {
+        let shader = library.preprocess(name, &[]).unwrap_or_else(|e| panic!("{name}: {e}"));
+        shader.validate().unwrap_or_else(|e| panic!("{name}: {e}"));
+    }
+
+    for format in OutputFormat::ALL {
+        let shader = library.preprocess("grain.wgsl", &[("OUTPUT_FORMAT", format.wgsl_format())]).unwrap();
+        assert!(shader.code.contains(&format!("texture_storage_2d<{}, write>", format.wgsl_format())));
+        shader.validate().unwrap_or_else(|e| panic!("grain.wgsl as {}: {e}", format.name()));
+    }
+}
+
+#[test]
+fn includes_are_pasted_once() {
+    let library = with_sources(&[
+        ("common.wgsl", "const ONE: f32 = 1.0;"),
+        ("a.wgsl", "#include \"common.wgsl\"\nfn a() -> f32 { return ONE; }"),
+        ("main.wgsl", "#include \"a.wgsl\"\n#include \"common.wgsl\"\nfn b() -> f32 { return a() + ONE; }"),
+    ]);
+
+    let shader = library.preprocess("main.wgsl", &[]).unwrap();
+    assert_eq!(shader.code.matches("const ONE").count(), 1);
+    assert_eq!(shader.source_location(1), Some(("common.wgsl", 1)));
+    assert_eq!(shader.source_location(2), Some(("a.wgsl", 2)));
+    assert_eq!(shader.source_location(3), Some(("main.wgsl", 3)));
+    shader.validate().unwrap();
+}
+
+#[test]
+fn defines_toggle_and_substitute() {
+    let source = "#ifdef FAST\nconst MODE: u32 = 1u;\n#else\nconst MODE: u32 = 2u;\n#endif\n\
+                  #ifndef SCALE\n#define SCALE 4.0\n#endif\nconst K: f32 = SCALE * 2.0;";
+    let library = with_sources(&[("main.wgsl", source)]);
+
+    let shader = library.preprocess("main.wgsl", &[]).unwrap();
+    assert!(shader.code.contains("MODE: u32 = 2u") && !shader.code.contains("MODE: u32 = 1u"));
+    assert!(shader.code.contains("K: f32 = 4.0 * 2.0"));
+
+    let shader = library.preprocess("main.wgsl", &[("FAST", ""), ("SCALE", "0.5")]).unwrap();
+    assert!(shader.code.contains("MODE: u32 = 1u") && !shader.code.contains("MODE: u32 = 2u"));
+    assert!(shader.code.contains("K: f32 = 0.5 * 2.0"));
+    // Only whole identifiers are replaced
+    let library = with_sources(&[("main.wgsl", "const SCALED: f32 = SCALE;")]);
+    assert_eq!(library.preprocess("main.wgsl", &[("SCALE", "3.0")]).unwrap().code.trim(), "const SCALED: f32 = 3.0;");
+}
+
+#[test]
+fn preprocessor_errors_name_file_and_line() {
+    let library = with_sources(&[
+        ("missing.wgsl", "const A: f32 = 1.0;\n#include \"nowhere.wgsl\""),
+        ("open.wgsl", "const A: f32 = 1.0;\n\n#ifdef FAST\nconst B: f32 = 2.0;"),
+        ("unknown.wgsl", "#pragma once"),
+    ]);
+
+    for (name, line) in [("missing.wgsl", 2), ("open.wgsl", 3), ("unknown.wgsl", 1)] {
+        match library.preprocess(name, &[]) {
+            Err(ShaderError::Preprocess { file, line: at, .. }) => assert_eq!((file.as_str(), at), (name, line)),
+            other => panic!("{name}: expected a preprocess error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn compile_errors_point_at_the_original_source() {
+    // The broken line sits after a multi-line include, so naga sees it at a different line
+    let library = with_sources(&[("broken.wgsl", "#include \"random.wgsl\"\n\nfn broken() -> f32 {\n    return undefined_value;\n}")]);
+
+    let shader = library.preprocess("broken.wgsl", &[]).unwrap();
+    match shader.validate() {
+        Err(ShaderError::Compile { location, message }) => {
+            assert!(location.starts_with("broken.wgsl:4:"), "located at {location}: {message}");
+        }
+        other => panic!("expected a compile error, got {other:?}"),
+    }
+}
@@ -0,0 +1,137 @@
+//! Tiled renders must be indistinguishable from a single render of the whole output,
+//! both in memory and once streamed to disk.
+
+use grainforge::core::film_stock::{ClusteringType, FilmStock, GrainSynthesis};
+use grainforge::engine::cpu_renderer::CpuGrainRenderer;
+use grainforge::engine::grain_renderer::GrainParams;
+use grainforge::engine::output_format::OutputFormat;
+use grainforge::engine::tiled_renderer::{TilePlan, TiledRenderer};
+use image::{DynamicImage, Rgba, Rgba32FImage};
+
+// Deliberately not a multiple of the tile size, so edge tiles are partial
+const WIDTH: u32 = 70;
+const HEIGHT: u32 = 45;
+const TILE_SIZE: u32 = 16;
+const OVERLAP: u32 = 5;
+
+fn test_plate() -> Rgba32FImage {
+    Rgba32FImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        Rgba([x as f32 / WIDTH as f32, y as f32 / HEIGHT as f32, ((x + y) % 7) as f32 / 7.0, 1.0])
+    })
+}
+
+fn test_params() -> Vec<(&'static str, GrainParams)> {
+    let mut colour = FilmStock::default();
+    colour.color.is_color = true;
+    colour.texture.clustering = ClusteringType::Hybrid;
+
+    let mut boolean = FilmStock::default();
+    boolean.grain.synthesis = GrainSynthesis::Boolean;
+
+    vec![
+        ("default stock", GrainParams::from_film_stock(&FilmStock::default(), WIDTH, HEIGHT).with_seed(4)),
+        ("colour stock", GrainParams::from_film_stock(&colour, WIDTH, HEIGHT).with_seed(4)),
+        ("tileable", GrainParams::from_film_stock(&colour, WIDTH, HEIGHT).with_seed(4).with_tileable(true)),
+        ("boolean model", GrainParams::from_film_stock(&boolean, WIDTH, HEIGHT).with_seed(4).with_samples(2)),
+    ]
+}
+
+fn single_render(params: &GrainParams, plate: Option<&Rgba32FImage>, format: OutputFormat) -> CpuGrainRenderer {
+    let mut renderer = CpuGrainRenderer::with_format(WIDTH, HEIGHT, format).unwrap();
+    if let Some(plate) = plate {
+        renderer.set_input_image(plate.clone()).unwrap();
+    }
+    renderer.render(params);
+    renderer
+}
+
+#[test]
+fn tile_cores_partition_the_output() {
+    for (width, height, tile_size, overlap) in [(70, 45, 16, 5), (64, 64, 16, 0), (1, 1, 8, 4), (100, 3, 7, 20)] {
+        let plan = TilePlan::new(width, height, tile_size, overlap).unwrap();
+        let mut covered = vec![0u32; (width * height) as usize];
+
+        for tile in plan.tiles() {
+            let [offset_x, offset_y] = tile.core_offset();
+            assert!(offset_x + tile.core.width <= plan.target_size());
+            assert!(offset_y + tile.core.height <= plan.target_size());
+            for y in tile.core.y..tile.core.y + tile.core.height {
+                for x in tile.core.x..tile.core.x + tile.core.width {
+                    covered[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+
+        assert!(covered.iter().all(|&n| n == 1), "{width}x{height} in {tile_size}px tiles: cores must cover each pixel once");
+    }
+}
+
+#[test]
+fn tiled_render_matches_single_render() {
+    let plate = test_plate();
+    let plan = TilePlan::new(WIDTH, HEIGHT, TILE_SIZE, OVERLAP).unwrap();
+
+    for format in [OutputFormat::Rgba8, OutputFormat::Rgba32Float] {
+        for (name, params) in test_params() {
+            for plate in [None, Some(&plate)] {
+                let expected = single_render(&params, plate, format);
+
+                let mut tiled = Vec::new();
+                TiledRenderer::cpu(plan, format)
+                    .render_bands(&params, plate, |band| {
+                        tiled.extend(band);
+                        Ok(())
+                    })
+                    .unwrap();
+
+                assert!(
+                    tiled == expected.output(),
+                    "{name} ({}, plate: {}): tiled render differs from a single render",
+                    format.name(),
+                    plate.is_some()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn streamed_exports_match_in_memory_exports() {
+    let dir = std::env::temp_dir().join(format!("grainforge-tiled-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let plan = TilePlan::new(WIDTH, HEIGHT, TILE_SIZE, OVERLAP).unwrap();
+    let (_, params) = test_params().remove(1);
+
+    for extension in ["png", "tiff"] {
+        let path = dir.join(format!("grain8.{extension}"));
+        TiledRenderer::cpu(plan, OutputFormat::Rgba8).render_to_file(&params, None, &path).unwrap();
+        let expected = single_render(&params, None, OutputFormat::Rgba8).output_image();
+        assert_eq!(image::open(&path).unwrap().into_rgba8(), expected, "8-bit {extension}");
+
+        let path = dir.join(format!("grain16.{extension}"));
+        TiledRenderer::cpu(plan, OutputFormat::Rgba16Float).render_to_file(&params, None, &path).unwrap();
+        let expected = DynamicImage::ImageRgba32F(single_render(&params, None, OutputFormat::Rgba16Float).output_image_f32())
+            .into_rgba16();
+        let written = image::open(&path).unwrap();
+        assert!(matches!(written, DynamicImage::ImageRgba16(_)), "float renders stream as 16-bit {extension}");
+        let max_error = written.into_rgba16().as_raw().iter().zip(expected.as_raw())
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap();
+        assert!(max_error <= 1, "16-bit {extension} differs by {max_error}");
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tiles_reject_a_plate_of_the_wrong_size() {
+    let plan = TilePlan::new(WIDTH, HEIGHT, TILE_SIZE, OVERLAP).unwrap();
+    let (_, params) = test_params().remove(0);
+    let mut renderer = TiledRenderer::cpu(plan, OutputFormat::Rgba8);
+    let small = Rgba32FImage::new(WIDTH / 2, HEIGHT);
+
+    assert!(renderer.render_tile(&params, Some(&small), &plan.tile(0, 0)).is_err());
+    assert!(renderer.render_band(&params, Some(&Rgba32FImage::new(0, 0)), 0).is_err());
+    assert!(renderer.render_tile(&params, Some(&test_plate()), &plan.tile(0, 0)).is_ok());
+}
@@ -0,0 +1,115 @@
+//! Watch folders: waiting for files to settle, the processed log and restarts.
+
+mod common;
+
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
+
+use common::{temp_dir, write_plate};
+use grainforge::core::film_stock::FilmStock;
+use grainforge::export::batch::{BatchJob, BatchReport};
+use grainforge::export::watch_folder::WatchFolder;
+
+fn poll(watch: &mut WatchFolder) -> BatchReport {
+    watch.poll(&AtomicBool::new(false), |_| {}).unwrap()
+}
+
+#[test]
+fn new_files_are_processed_once_they_settle() {
+    let dir = temp_dir("watch-settle");
+    let output = dir.join("graded");
+    // Outputs land under the watched tree and must not be picked up as inputs
+    let job = BatchJob::new(format!("{}/**/*.png", dir.display()), FilmStock::default(), &output);
+    let mut watch = WatchFolder::new(job.clone()).unwrap();
+
+    write_plate(&dir.join("a.png"), 50);
+    assert!(poll(&mut watch).files.is_empty(), "a file is only taken once it stops changing");
+    let report = poll(&mut watch);
+    assert_eq!(report.written(), 1);
+    assert!(output.join("a_grain.png").exists());
+    assert!(watch.is_processed(&dir.join("a.png")));
+
+    write_plate(&dir.join("b.png"), 90);
+    poll(&mut watch);
+    let report = poll(&mut watch);
+    assert_eq!(report.files.len(), 1, "only the new file is processed");
+    assert!(report.files[0].input.ends_with("b.png"));
+    assert!(poll(&mut watch).files.is_empty());
+
+    // A restart reads the log instead of redoing the work
+    let mut restarted = WatchFolder::new(job.clone()).unwrap();
+    assert!(restarted.is_processed(&dir.join("b.png")));
+    poll(&mut restarted);
+    assert!(poll(&mut restarted).files.is_empty());
+    let log = std::fs::read_to_string(restarted.log_path()).unwrap();
+    assert_eq!(log.lines().count(), 2);
+
+    // A file that changes is processed again, replacing its output
+    write_plate(&dir.join("a.png"), 200);
+    let later = SystemTime::now() + Duration::from_secs(5);
+    std::fs::File::options().write(true).open(dir.join("a.png")).unwrap().set_modified(later).unwrap();
+    poll(&mut restarted);
+    let report = poll(&mut restarted);
+    assert_eq!(report.written(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_are_logged_and_retried_when_the_file_changes() {
+    let dir = temp_dir("watch-failures");
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).unwrap();
+    let job = BatchJob::new(format!("{}/*.png", input.display()), FilmStock::default(), dir.join("out"));
+    let mut watch = WatchFolder::new(job.clone()).unwrap();
+
+    std::fs::write(input.join("bad.png"), b"still copying").unwrap();
+    poll(&mut watch);
+    assert_eq!(poll(&mut watch).failures().count(), 1);
+    assert!(poll(&mut watch).files.is_empty(), "an unchanged failure is not retried");
+    assert!(std::fs::read_to_string(watch.log_path()).unwrap().contains("\"error\""));
+
+    write_plate(&input.join("bad.png"), 10);
+    let later = SystemTime::now() + Duration::from_secs(5);
+    std::fs::File::options().write(true).open(input.join("bad.png")).unwrap().set_modified(later).unwrap();
+    poll(&mut watch);
+    assert_eq!(poll(&mut watch).written(), 1);
+
+    // After a restart the log's later success wins over the earlier failure
+    assert!(WatchFolder::new(job).unwrap().is_processed(&input.join("bad.png")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn clashing_output_names_fail_only_the_later_file() {
+    let dir = temp_dir("watch-clash");
+    let output = dir.join("out");
+    let (first, second) = (dir.join("day1"), dir.join("day2"));
+    std::fs::create_dir_all(&first).unwrap();
+    std::fs::create_dir_all(&second).unwrap();
+    let job = BatchJob::new(format!("{}/day*/*.png", dir.display()), FilmStock::default(), &output);
+    let mut watch = WatchFolder::new(job.clone()).unwrap();
+
+    // Both would be written to a_grain.png in the same poll
+    write_plate(&first.join("a.png"), 50);
+    write_plate(&second.join("a.png"), 60);
+    write_plate(&second.join("b.png"), 70);
+    poll(&mut watch);
+    let report = poll(&mut watch);
+    assert_eq!(report.written(), 2);
+    let failures: Vec<_> = report.failures().map(|(input, _)| input.to_path_buf()).collect();
+    assert_eq!(failures, vec![second.join("a.png").canonicalize().unwrap()]);
+    assert!(poll(&mut watch).files.is_empty(), "the clash is not retried until the file changes");
+
+    // Outputs written on earlier polls, or before a restart, are taken too
+    let mut restarted = WatchFolder::new(job).unwrap();
+    write_plate(&first.join("b.png"), 80);
+    poll(&mut restarted);
+    let report = poll(&mut restarted);
+    let clashed = first.join("b.png").canonicalize().unwrap();
+    assert!(report.failures().any(|(input, _)| input == clashed));
+    assert_eq!(report.written(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}